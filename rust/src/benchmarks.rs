@@ -0,0 +1,1936 @@
+use crate::synthesis::synthesize_unitary;
+use crate::vqe::{ansatz_parameter_count, minimize_energy, ry_ansatz};
+use crate::{
+    DensityMatrix, DEFAULT_TOL, GateKind, Hamiltonian, Molecule, NaiveSimulator, NoiseModel, Operation, Pauli, QuantumCircuit, QuantumState,
+    Optimizer, VqeOptions,
+};
+use nalgebra::DMatrix;
+use num_complex::Complex64;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub name: String,
+    pub num_qubits: usize,
+    pub num_gates: usize,
+    pub num_two_qubit_gates: usize,
+    pub execution_time_ms: f64,
+    pub memory_usage_mb: f64,
+    pub circuit_depth: usize,
+    #[serde(default)]
+    pub t_count: usize,
+    #[serde(default)]
+    pub t_depth: usize,
+    /// Depth counting only two-qubit gates, the dominant error source on
+    /// hardware.
+    #[serde(default)]
+    pub two_qubit_depth: usize,
+    #[serde(default)]
+    pub parallelism: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fidelity: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measurement_circuits: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_shots: Option<usize>,
+    /// Largest amplitudes of the final state, when requested with `dump_state`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_state: Option<Vec<AmplitudeEntry>>,
+    /// `P(1)` of each qubit in the final state, when requested with
+    /// `qubit_marginals`; a compact fingerprint to diff across runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qubit_marginals: Option<Vec<f64>>,
+    /// Exact size of the statevector buffer, for the memory-scaling benchmark.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_bytes: Option<usize>,
+    /// SWAPs inserted to route the circuit onto a linear topology.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swaps: Option<usize>,
+    /// RMS deviation of a shot-sampled expectation value from the exact one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expectation_error: Option<f64>,
+    /// Fewest CNOTs that can implement a generic unitary of this size,
+    /// `ceil((4^n - 3n - 1) / 4)`, for the synthesis benchmark.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cnot_lower_bound: Option<usize>,
+    /// Per-gate depolarizing probability of a noisy run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_rate: Option<f64>,
+}
+
+impl BenchmarkResult {
+    /// A result for `circuit`, with its size, depth and gate-count metrics
+    /// taken from the circuit itself. Benchmark-specific fields stay at their
+    /// defaults for the caller to fill in.
+    pub fn for_circuit(name: String, circuit: &QuantumCircuit, execution_time: Duration, memory_usage_mb: f64) -> Self {
+        BenchmarkResult {
+            name,
+            num_qubits: circuit.num_qubits(),
+            num_gates: circuit.num_operations(),
+            num_two_qubit_gates: circuit.num_two_qubit_gates(),
+            execution_time_ms: execution_time.as_secs_f64() * 1000.0,
+            memory_usage_mb,
+            circuit_depth: circuit.depth(),
+            t_count: circuit.t_count(),
+            t_depth: circuit.t_depth(),
+            two_qubit_depth: circuit.two_qubit_depth(),
+            parallelism: circuit.parallelism(),
+            ..BenchmarkResult::default()
+        }
+    }
+}
+
+/// One basis-state amplitude in a state dump.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmplitudeEntry {
+    pub index: usize,
+    pub re: f64,
+    pub im: f64,
+}
+
+/// Cap on amplitudes included per state dump, so large registers don't
+/// produce gigantic JSON.
+pub const STATE_DUMP_MAX_AMPLITUDES: usize = 64;
+
+/// `BenchmarkSuite::library` of these results. They time the simulator in
+/// this crate rather than the published `logosq` crate, so the label keeps
+/// the two apart in cross-library comparisons.
+pub const LIBRARY_LABEL: &str = "LogosQ (in-tree)";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkSuite {
+    pub library: String,
+    pub version: String,
+    pub results: Vec<BenchmarkResult>,
+    pub total_time_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serialization: Option<SerializationTiming>,
+    /// Configurations not run because the time budget ran out or the run
+    /// was interrupted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+    /// Whether the run was stopped early through `BenchmarkConfig::interrupt`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerializationTiming {
+    pub repeats: usize,
+    pub pretty_ms: f64,
+    pub compact_ms: f64,
+    pub pretty_bytes: usize,
+    pub compact_bytes: usize,
+}
+
+/// Gate types the random circuit benchmark can draw, in `GateWeights` order.
+pub const RANDOM_GATE_NAMES: [&str; 8] = ["h", "x", "y", "z", "rx", "ry", "rz", "cnot"];
+
+/// Normalised selection probabilities over `RANDOM_GATE_NAMES`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GateWeights(Vec<f64>);
+
+impl GateWeights {
+    pub fn new(weights: Vec<f64>) -> Result<Self, String> {
+        if weights.len() != RANDOM_GATE_NAMES.len() {
+            return Err(format!(
+                "expected {} gate weights, got {}",
+                RANDOM_GATE_NAMES.len(),
+                weights.len()
+            ));
+        }
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("gate weights must be finite and non-negative".to_string());
+        }
+
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err("at least one gate weight must be positive".to_string());
+        }
+        Ok(GateWeights(weights.into_iter().map(|w| w / total).collect()))
+    }
+    /// Whether every weighted gate can be drawn on `num_qubits` qubits; a CNOT
+    /// needs two.
+    pub fn fits(&self, num_qubits: usize) -> bool {
+        num_qubits > 1 || self.0[RANDOM_GATE_NAMES.len() - 1] == 0.0
+    }
+}
+
+/// Which register sizes a time-budgeted run gets to first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BudgetOrder {
+    /// Covers the most sizes; the expensive ones are the first dropped.
+    #[default]
+    SmallestFirst,
+    /// Measures the sizes that matter most for scaling, then fills in.
+    LargestFirst,
+}
+
+/// Shared flag that stops `run_benchmarks` early when set, e.g. from a
+/// Ctrl-C handler. Clones share the flag; equality is identity.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptFlag(Arc<AtomicBool>);
+
+impl InterruptFlag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl PartialEq for InterruptFlag {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// What `run_benchmarks` measures. The default matches the standalone runner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkConfig {
+    pub qubit_sizes: Vec<usize>,
+    /// Gate distribution for the random circuit benchmark; uniform when `None`.
+    pub gate_weights: Option<GateWeights>,
+    /// Runs averaged when timing result serialization; 0 skips the timing.
+    pub serialization_repeats: usize,
+    /// Attach the largest final-state amplitudes to single-circuit results.
+    pub dump_state: bool,
+    /// Attach each qubit's final `P(1)` to single-circuit results.
+    pub qubit_marginals: bool,
+    /// Optimizer runs of the VQE benchmark, one result each.
+    pub vqe: Vec<VqeOptions>,
+    /// Wall-clock cap on the suite. Once it is spent, the remaining register
+    /// sizes and fixed-size benchmarks are skipped and listed in
+    /// `BenchmarkSuite::skipped`; a configuration already running finishes.
+    pub time_budget: Option<Duration>,
+    /// Order of the register sizes under a time budget. Without one they
+    /// run in `qubit_sizes` order.
+    pub budget_order: BudgetOrder,
+    /// Checked between configurations like the time budget: once set, the
+    /// rest of the suite is skipped and the partial results returned, with
+    /// `BenchmarkSuite::interrupted` set.
+    pub interrupt: Option<InterruptFlag>,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        BenchmarkConfig {
+            qubit_sizes: vec![4, 6, 8, 10, 12],
+            gate_weights: None,
+            serialization_repeats: 20,
+            dump_state: false,
+            qubit_marginals: false,
+            vqe: vec![
+                VqeOptions::default(),
+                VqeOptions {
+                    optimizer: Optimizer::NelderMead { initial_step: 0.1 },
+                    max_iterations: 5000,
+                    ..VqeOptions::default()
+                },
+            ],
+            time_budget: None,
+            budget_order: BudgetOrder::SmallestFirst,
+            interrupt: None,
+        }
+    }
+}
+
+fn get_memory_usage() -> f64 {
+    // Simple memory estimation - in a real implementation you'd use more sophisticated monitoring
+    std::process::Command::new("ps")
+        .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+        .output()
+        .ok()
+        .and_then(|output| {
+            String::from_utf8(output.stdout)
+                .ok()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+        .map(|kb| kb / 1024.0) // Convert KB to MB
+        .unwrap_or(0.0)
+}
+
+fn benchmark_ghz_state(num_qubits: usize, dump_state: bool, marginals: bool) -> BenchmarkResult {
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    
+    // Create GHZ state: |000...0⟩ + |111...1⟩
+    let qubits: Vec<usize> = (0..num_qubits).collect();
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    circuit.prepare_ghz(&qubits).expect("register qubits are distinct");
+    
+    let final_state = circuit.execute();
+    
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+    
+    BenchmarkResult {
+        final_state: dump_state.then(|| state_dump(&final_state)),
+        qubit_marginals: marginals.then(|| qubit_marginals(&final_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("GHZ-{}", num_qubits),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Equal superposition of the `n` one-hot basis states, via `prepare_w_state`.
+fn benchmark_w_state(num_qubits: usize, dump_state: bool, marginals: bool) -> BenchmarkResult {
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+
+    let qubits: Vec<usize> = (0..num_qubits).collect();
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    circuit.prepare_w_state(&qubits).expect("register qubits are distinct");
+
+    let final_state = circuit.execute();
+
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    // Probability mass on the single-excitation basis states
+    let single_excitation: f64 = (0..num_qubits)
+        .map(|q| final_state.get_probability(1 << q))
+        .sum();
+
+    BenchmarkResult {
+        fidelity: Some(single_excitation),
+        final_state: dump_state.then(|| state_dump(&final_state)),
+        qubit_marginals: marginals.then(|| qubit_marginals(&final_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("W-{}", num_qubits),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Draws `num_gates` gates, uniformly over the single-qubit types or from
+/// `weights` over `RANDOM_GATE_NAMES`. With `entangling` set, unweighted
+/// circuits get `num_gates / 4` extra random CNOTs at the end; without it
+/// they stay purely single-qubit to isolate single-qubit gate throughput.
+///
+/// Weighted circuits take their whole gate mix, CNOTs included, from
+/// `weights`, so no trailing CNOT layer is added. A CNOT needs two qubits, so
+/// a non-zero CNOT weight is rejected on a single-qubit register rather than
+/// silently skewing the mix.
+fn random_circuit(
+    num_qubits: usize,
+    num_gates: usize,
+    entangling: bool,
+    weights: Option<&GateWeights>,
+    rng: &mut impl Rng,
+) -> QuantumCircuit {
+    if let Some(weights) = weights {
+        assert!(
+            weights.fits(num_qubits),
+            "a non-zero CNOT weight needs at least two qubits"
+        );
+    }
+
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    let weighted = weights.map(|w| WeightedIndex::new(&w.0).expect("gate weights are validated"));
+    
+    for _ in 0..num_gates {
+        let gate_type = match &weighted {
+            Some(distribution) => distribution.sample(rng),
+            None => rng.gen_range(0..7),
+        };
+        let qubit = rng.gen_range(0..num_qubits);
+        let angle = rng.gen::<f64>() * 2.0 * PI;
+        
+        match gate_type {
+            0 => { circuit.h(qubit); }
+            1 => { circuit.x(qubit); }
+            2 => { circuit.y(qubit); }
+            3 => { circuit.z(qubit); }
+            4 => { circuit.rx(qubit, angle); }
+            5 => { circuit.ry(qubit, angle); }
+            6 => { circuit.rz(qubit, angle); }
+            _ => {
+                let target = (qubit + rng.gen_range(1..num_qubits)) % num_qubits;
+                circuit.cnot(qubit, target).expect("target differs from control");
+            }
+        }
+    }
+    
+    // Add some CNOT gates for entanglement
+    let num_cnots = if entangling && weights.is_none() && num_qubits > 1 { num_gates / 4 } else { 0 };
+    for _ in 0..num_cnots {
+        let control = rng.gen_range(0..num_qubits);
+        let mut target = rng.gen_range(0..num_qubits);
+        while target == control {
+            target = rng.gen_range(0..num_qubits);
+        }
+        circuit.cnot(control, target).expect("target differs from control");
+    }
+
+    circuit
+}
+
+fn benchmark_random_circuit(
+    num_qubits: usize,
+    num_gates: usize,
+    entangling: bool,
+    weights: Option<&GateWeights>,
+    dump_state: bool,
+    marginals: bool,
+) -> BenchmarkResult {
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    
+    let circuit = random_circuit(num_qubits, num_gates, entangling, weights, &mut rand::thread_rng());
+    
+    let final_state = circuit.execute();
+    
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+    
+    BenchmarkResult {
+        final_state: dump_state.then(|| state_dump(&final_state)),
+        qubit_marginals: marginals.then(|| qubit_marginals(&final_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("Random-{}-{}", num_qubits, num_gates),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+fn benchmark_qft_circuit(num_qubits: usize, dump_state: bool, marginals: bool) -> BenchmarkResult {
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    
+    // Implement simplified QFT
+    for i in 0..num_qubits {
+        circuit.h(i);
+        for j in (i + 1)..num_qubits {
+            let angle = PI / (1 << (j - i)) as f64;
+            circuit.cp(j, i, angle).expect("j > i");
+        }
+    }
+    
+    let final_state = circuit.execute();
+    
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+    
+    BenchmarkResult {
+        final_state: dump_state.then(|| state_dump(&final_state)),
+        qubit_marginals: marginals.then(|| qubit_marginals(&final_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("QFT-{}", num_qubits),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+fn benchmark_trotter_error(num_qubits: usize, max_steps: usize) -> Vec<BenchmarkResult> {
+    let hamiltonian = Hamiltonian::heisenberg_chain(num_qubits, 1.0);
+    let evolution_time = 1.0;
+
+    // Start from the Néel state |0101...⟩; |00...0⟩ is an eigenstate of the chain
+    let mut preparation = QuantumCircuit::new(num_qubits);
+    for qubit in (1..num_qubits).step_by(2) {
+        preparation.x(qubit);
+    }
+    let initial_state = preparation.execute();
+    let exact_state = hamiltonian
+        .evolve_exact(&initial_state, evolution_time)
+        .expect("Heisenberg chain fits the register");
+
+    let mut results = Vec::new();
+    for steps in 1..=max_steps {
+        let start_memory = get_memory_usage();
+        let start_time = Instant::now();
+
+        let circuit = hamiltonian
+            .trotter_circuit(num_qubits, evolution_time, steps, 2)
+            .expect("valid Trotter parameters");
+        let final_state = circuit.execute_from(initial_state.clone());
+
+        let execution_time = start_time.elapsed();
+        let end_memory = get_memory_usage();
+
+        results.push(BenchmarkResult {
+            fidelity: Some(final_state.fidelity(&exact_state)),
+            ..BenchmarkResult::for_circuit(
+                format!("Trotter-{}-{}", num_qubits, steps),
+                &circuit,
+                execution_time,
+                end_memory - start_memory,
+            )
+        });
+    }
+
+    results
+}
+
+/// Statevector footprint for 1 to `max_qubits` qubits, read off the
+/// allocated buffer instead of the process RSS, so the results trace the
+/// analytic `2^n * 16` bytes curve without allocator or OS noise.
+fn benchmark_memory_scaling(max_qubits: usize) -> Vec<BenchmarkResult> {
+    (1..=max_qubits)
+        .map(|num_qubits| {
+            let start_time = Instant::now();
+            let state = QuantumState::new(num_qubits);
+            let execution_time = start_time.elapsed();
+            let bytes = state.memory_bytes();
+
+            BenchmarkResult {
+                name: format!("Memory-{}", num_qubits),
+                num_qubits,
+                execution_time_ms: execution_time.as_secs_f64() * 1000.0,
+                memory_usage_mb: bytes as f64 / (1024.0 * 1024.0),
+                state_bytes: Some(bytes),
+                ..BenchmarkResult::default()
+            }
+        })
+        .collect()
+}
+
+/// Accumulated floating-point error over `depth` layers that each compose
+/// to the identity: RX and RY rotations on every qubit and a CNOT ladder,
+/// followed by their exact inverses. The angles vary per layer and qubit
+/// so rounding errors don't cancel. The fidelity with `|0...0⟩` would be
+/// exactly 1 in exact arithmetic, so its shortfall is the numerical drift.
+fn benchmark_numerical_stability(num_qubits: usize, depth: usize) -> BenchmarkResult {
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for layer in 0..depth {
+        let angle = |qubit: usize| 0.1 + 0.37 * layer as f64 + 0.53 * qubit as f64;
+        for qubit in 0..num_qubits {
+            circuit.rx(qubit, angle(qubit));
+            circuit.ry(qubit, 0.5 * angle(qubit));
+        }
+        for qubit in 1..num_qubits {
+            circuit.cnot(qubit - 1, qubit).expect("ladder qubits are in range");
+        }
+        for qubit in (1..num_qubits).rev() {
+            circuit.cnot(qubit - 1, qubit).expect("ladder qubits are in range");
+        }
+        for qubit in 0..num_qubits {
+            circuit.ry(qubit, -0.5 * angle(qubit));
+            circuit.rx(qubit, -angle(qubit));
+        }
+    }
+    let final_state = circuit.execute();
+
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    BenchmarkResult {
+        fidelity: Some(final_state.fidelity(&QuantumState::new(num_qubits))),
+        ..BenchmarkResult::for_circuit(
+            format!("NumericalStability-{}-{}", num_qubits, depth),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Reduced density matrix of the first `k` qubits for every `k` up to half
+/// the register, from an entangled RY/CNOT state. Each trace costs
+/// `O(2^(n+k))`, so the larger subsystems dominate; the timing covers only
+/// the trace, not the state preparation.
+fn benchmark_partial_trace(num_qubits: usize) -> Vec<BenchmarkResult> {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for qubit in 0..num_qubits {
+        circuit.ry(qubit, 0.4 + 0.3 * qubit as f64);
+    }
+    for qubit in 1..num_qubits {
+        circuit.cnot(qubit - 1, qubit).expect("ladder qubits are in range");
+    }
+    let state = circuit.execute();
+
+    (1..=num_qubits / 2)
+        .map(|kept| {
+            let keep: Vec<usize> = (0..kept).collect();
+            let start_memory = get_memory_usage();
+            let start_time = Instant::now();
+            std::hint::black_box(DensityMatrix::reduced_from_state(&state, &keep).expect("kept qubits are in range"));
+            let execution_time = start_time.elapsed();
+            let end_memory = get_memory_usage();
+
+            BenchmarkResult::for_circuit(
+                format!("PartialTrace-{}-{}", num_qubits, kept),
+                &circuit,
+                execution_time,
+                end_memory - start_memory,
+            )
+        })
+        .collect()
+}
+
+/// `H(s) = (1 - s) H_driver + s H_target` for the adiabatic benchmark.
+///
+/// The driver `-Σ X_i` has the uniform superposition as its ground state. The
+/// target is an antiferromagnetic Ising chain with a small field on qubit 0,
+/// which picks one of the two Néel states as the unique ground state.
+fn adiabatic_hamiltonian(num_qubits: usize, s: f64) -> Hamiltonian {
+    let mut hamiltonian = Hamiltonian::new();
+    for qubit in 0..num_qubits {
+        hamiltonian.add_term(-(1.0 - s), &[(qubit, Pauli::X)]);
+    }
+    for qubit in 0..num_qubits.saturating_sub(1) {
+        hamiltonian.add_term(s, &[(qubit, Pauli::Z), (qubit + 1, Pauli::Z)]);
+    }
+    hamiltonian.add_term(0.5 * s, &[(0, Pauli::Z)]);
+    hamiltonian
+}
+
+/// Points sampled along the path when locating the minimum gap.
+const ADIABATIC_GAP_SAMPLES: usize = 20;
+
+/// Sweep time in units of `1 / Δ_min²`.
+const ADIABATIC_TIME_SCALE: f64 = 10.0;
+
+/// Adiabatic ground-state preparation of `adiabatic_hamiltonian`.
+///
+/// The sweep takes `T = ADIABATIC_TIME_SCALE / Δ_min²`, the usual
+/// adiabatic-theorem scaling, with `Δ_min` the smallest spectral gap over
+/// `ADIABATIC_GAP_SAMPLES + 1` points on the path. Each of the `steps`
+/// slices evolves under `H(s)` at the slice midpoint for `T / steps` using
+/// one second-order Trotter step. The fidelity is the overlap of the final
+/// state with the exact target ground state.
+fn benchmark_adiabatic(num_qubits: usize, steps: usize) -> BenchmarkResult {
+    let min_gap = (0..=ADIABATIC_GAP_SAMPLES)
+        .map(|k| {
+            adiabatic_hamiltonian(num_qubits, k as f64 / ADIABATIC_GAP_SAMPLES as f64)
+                .spectral_gap(num_qubits)
+                .expect("adiabatic path fits the register")
+        })
+        .fold(f64::INFINITY, f64::min);
+    let total_time = ADIABATIC_TIME_SCALE / (min_gap * min_gap);
+    let (_, ground_state) = adiabatic_hamiltonian(num_qubits, 1.0)
+        .ground_state(num_qubits)
+        .expect("adiabatic path fits the register");
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for qubit in 0..num_qubits {
+        circuit.h(qubit);
+    }
+    let dt = total_time / steps as f64;
+    for step in 0..steps {
+        let s = (step as f64 + 0.5) / steps as f64;
+        let slice = adiabatic_hamiltonian(num_qubits, s)
+            .trotter_circuit(num_qubits, dt, 1, 2)
+            .expect("valid Trotter parameters");
+        for operation in slice.operations() {
+            circuit.add_operation(operation.clone());
+        }
+    }
+    let final_state = circuit.execute();
+
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    BenchmarkResult {
+        fidelity: Some(final_state.fidelity(&ground_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("Adiabatic-{}-{}", num_qubits, steps),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+fn controlled_phase(circuit: &mut QuantumCircuit, control: usize, target: usize, theta: f64) {
+    circuit
+        .cp(control, target, theta)
+        .expect("phase control and target are distinct");
+}
+
+/// Modulus factored by the period-finding benchmark (the textbook N = 15).
+const SHOR_MODULUS: usize = 15;
+
+fn pow_mod(base: usize, exponent: usize, modulus: usize) -> usize {
+    (0..exponent).fold(1 % modulus, |acc, _| acc * base % modulus)
+}
+
+/// Smallest `r` with `base^r ≡ 1 (mod modulus)` among the continued-fraction
+/// convergents of `outcome / 2^counting_qubits`, if any.
+fn period_from_outcome(outcome: usize, counting_qubits: usize, base: usize, modulus: usize) -> Option<usize> {
+    let (mut numerator, mut denominator) = (outcome, 1usize << counting_qubits);
+    let (mut k_prev, mut k) = (0usize, 1usize);
+    while denominator != 0 {
+        let quotient = numerator / denominator;
+        (k_prev, k) = (k, quotient * k + k_prev);
+        if k >= modulus {
+            break;
+        }
+        if pow_mod(base, k, modulus) == 1 {
+            return Some(k);
+        }
+        (numerator, denominator) = (denominator, numerator % denominator);
+    }
+    None
+}
+
+/// Quantum period finding for `base` mod 15: a counting register of
+/// `counting_qubits` qubits (0..n) controls `base^(2^j)` multiplications of a
+/// 4-qubit work register (n..n+4) prepared in |1⟩, followed by an inverse QFT
+/// on the counting register. Outcomes concentrate on multiples of 2^n / r;
+/// the period is recovered from sampled shots with continued fractions. The
+/// fidelity column is the probability mass on those multiples.
+fn benchmark_shor_period_finding(
+    counting_qubits: usize,
+    base: usize,
+    dump_state: bool,
+    marginals: bool,
+) -> BenchmarkResult {
+    let work_qubits = 4;
+    let num_qubits = counting_qubits + work_qubits;
+    let counting: Vec<usize> = (0..counting_qubits).collect();
+    let work: Vec<usize> = (counting_qubits..num_qubits).collect();
+    let shots = 64;
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    circuit.x(work[0]);
+    for &qubit in &counting {
+        circuit.h(qubit);
+    }
+    for (j, &control) in counting.iter().enumerate() {
+        let multiplier = (0..j).fold(base % SHOR_MODULUS, |acc, _| acc * acc % SHOR_MODULUS);
+        circuit
+            .controlled_mod_mul(control, &work, multiplier, SHOR_MODULUS)
+            .expect("base is coprime to the modulus");
+    }
+
+    // Counting qubit j now carries phase 2π·2^j·s/r. The inverse QFT treats it
+    // as Fourier qubit n-1-j, and the closing relabelling puts y back in
+    // little-endian order.
+    let fourier = |k: usize| counting_qubits - 1 - k;
+    for k in 0..counting_qubits {
+        for j in 0..k {
+            controlled_phase(&mut circuit, fourier(j), fourier(k), -PI / (1 << (k - j)) as f64);
+        }
+        circuit.h(fourier(k));
+    }
+    let reversal: Vec<usize> = (0..num_qubits)
+        .map(|q| if q < counting_qubits { fourier(q) } else { q })
+        .collect();
+    circuit.permute(&reversal).expect("reversal is a permutation");
+
+    let final_state = circuit.execute();
+    let distribution = final_state
+        .marginal_probabilities(&counting)
+        .expect("counting register fits the circuit");
+    let sampler = WeightedIndex::new(&distribution).expect("probabilities are non-negative");
+    let mut rng = rand::thread_rng();
+    let mut found = [0usize; SHOR_MODULUS];
+    for _ in 0..shots {
+        let outcome = sampler.sample(&mut rng);
+        if let Some(period) = period_from_outcome(outcome, counting_qubits, base, SHOR_MODULUS) {
+            found[period] += 1;
+        }
+    }
+
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    let period = (1..SHOR_MODULUS)
+        .find(|&r| pow_mod(base, r, SHOR_MODULUS) == 1)
+        .expect("base is coprime to the modulus");
+    let spacing = (1usize << counting_qubits) as f64 / period as f64;
+    let on_multiples: f64 = (0..period)
+        .map(|s| (s as f64 * spacing).round() as usize)
+        .map(|y| distribution[y % distribution.len()])
+        .sum();
+
+    let extracted = (1..SHOR_MODULUS).max_by_key(|&r| (found[r], std::cmp::Reverse(r)));
+    if extracted != Some(period) || found[period] == 0 {
+        eprintln!(
+            "Warning: period finding for {} mod {} extracted {:?}, expected {}",
+            base, SHOR_MODULUS, extracted, period
+        );
+    }
+
+    BenchmarkResult {
+        fidelity: Some(on_multiples),
+        final_state: dump_state.then(|| state_dump(&final_state)),
+        qubit_marginals: marginals.then(|| qubit_marginals(&final_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("Shor-{}-{}", num_qubits, base),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Draper adder: b <- (a + b) mod 2^n entirely in the Fourier basis (QFT on b,
+/// controlled phases from a, inverse QFT), with no carry ancillas. Register a
+/// occupies qubits 0..n and b qubits n..2n, both little-endian.
+fn draper_adder_circuit(num_bits: usize, a: usize, b: usize) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(2 * num_bits);
+    for bit in 0..num_bits {
+        if (a >> bit) & 1 == 1 {
+            circuit.x(bit);
+        }
+        if (b >> bit) & 1 == 1 {
+            circuit.x(num_bits + bit);
+        }
+    }
+
+    // QFT on b without the final swaps: qubit k picks up phase 2πb / 2^(k+1)
+    for k in (0..num_bits).rev() {
+        circuit.h(num_bits + k);
+        for j in (0..k).rev() {
+            controlled_phase(&mut circuit, num_bits + j, num_bits + k, PI / (1 << (k - j)) as f64);
+        }
+    }
+
+    // Adding a in the Fourier basis is a phase kick per (a_j, b_k) pair
+    for k in 0..num_bits {
+        for j in 0..=k {
+            controlled_phase(&mut circuit, j, num_bits + k, PI / (1 << (k - j)) as f64);
+        }
+    }
+
+    // Inverse QFT
+    for k in 0..num_bits {
+        for j in 0..k {
+            controlled_phase(&mut circuit, num_bits + j, num_bits + k, -PI / (1 << (k - j)) as f64);
+        }
+        circuit.h(num_bits + k);
+    }
+
+    circuit
+}
+
+/// Adds two random `num_bits`-bit numbers with `draper_adder_circuit`.
+fn benchmark_draper_adder(num_bits: usize, dump_state: bool, marginals: bool) -> BenchmarkResult {
+    let mut rng = rand::thread_rng();
+    let a = rng.gen_range(0..1usize << num_bits);
+    let b = rng.gen_range(0..1usize << num_bits);
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+
+    let circuit = draper_adder_circuit(num_bits, a, b);
+    let final_state = circuit.execute();
+
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    let expected = a | (((a + b) % (1 << num_bits)) << num_bits);
+    if final_state.most_likely_outcome() != expected {
+        eprintln!(
+            "Warning: Draper adder computed {} + {} incorrectly on {} bits",
+            a, b, num_bits
+        );
+    }
+
+    BenchmarkResult {
+        fidelity: Some(final_state.get_probability(expected)),
+        final_state: dump_state.then(|| state_dump(&final_state)),
+        qubit_marginals: marginals.then(|| qubit_marginals(&final_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("Draper-{}", num_bits),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Times the bit-manipulation simulator against the dense-matrix reference on
+/// the same random circuit and records how closely the final states agree.
+fn benchmark_vs_naive(num_qubits: usize) -> Vec<BenchmarkResult> {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..num_qubits * 10 {
+        let qubit = rng.gen_range(0..num_qubits);
+        let angle = rng.gen::<f64>() * 2.0 * PI;
+        match rng.gen_range(0..4) {
+            0 => { circuit.h(qubit); }
+            1 => { circuit.rx(qubit, angle); }
+            2 => { circuit.rz(qubit, angle); }
+            _ if num_qubits > 1 => {
+                let target = (qubit + rng.gen_range(1..num_qubits)) % num_qubits;
+                circuit.cnot(qubit, target).expect("target differs from control");
+            }
+            _ => { circuit.ry(qubit, angle); }
+        }
+    }
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let optimized_state = circuit.execute();
+    let optimized_time = start_time.elapsed();
+    let optimized_memory = get_memory_usage() - start_memory;
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let naive_state = NaiveSimulator::execute(&circuit);
+    let naive_time = start_time.elapsed();
+    let naive_memory = get_memory_usage() - start_memory;
+
+    let max_deviation = (optimized_state.amplitudes() - naive_state.amplitudes())
+        .iter()
+        .map(|diff| diff.norm())
+        .fold(0.0, f64::max);
+    if max_deviation > DEFAULT_TOL {
+        eprintln!(
+            "Warning: optimized and naive simulators disagree on {} qubits (max deviation {:e})",
+            num_qubits, max_deviation
+        );
+    }
+    eprintln!(
+        "Naive comparison on {} qubits: {:.1}x speedup",
+        num_qubits,
+        naive_time.as_secs_f64() / optimized_time.as_secs_f64().max(f64::EPSILON)
+    );
+
+    let agreement = optimized_state.fidelity(&naive_state);
+    [
+        ("Optimized", optimized_time, optimized_memory),
+        ("Naive", naive_time, naive_memory),
+    ]
+    .into_iter()
+    .map(|(label, time, memory)| BenchmarkResult {
+        fidelity: Some(agreement),
+        ..BenchmarkResult::for_circuit(format!("{}-{}", label, num_qubits), &circuit, time, memory)
+    })
+    .collect()
+}
+
+/// Runs `circuit` from `|0...0⟩` with each single-qubit and controlled gate
+/// matrix rebuilt from its `GateKind`, falling back to the stored matrix
+/// for custom gates.
+fn execute_from_kinds(circuit: &QuantumCircuit) -> QuantumState {
+    let mut state = QuantumState::new(circuit.num_qubits());
+    for operation in circuit.operations() {
+        match operation {
+            Operation::SingleGate { gate, kind, qubit } => match kind.matrix() {
+                Some(matrix) => state.apply_single_gate(&matrix, *qubit),
+                None => state.apply_single_gate(gate, *qubit),
+            },
+            Operation::ControlledGate {
+                gate,
+                kind,
+                control,
+                target,
+                control_value,
+            } => {
+                let matrix = kind.matrix();
+                let gate = matrix.as_ref().unwrap_or(gate);
+                state.apply_controlled_gate_with_value(gate, *control, *target, *control_value)
+            }
+            other => other.apply(&mut state),
+        }
+    }
+    state
+}
+
+/// GHZ preparation on the density-matrix simulator with every gate
+/// depolarizing at each rate in `error_rates` (single- and two-qubit gates
+/// alike), one result per rate. The fidelity with the ideal GHZ state traces
+/// the decay curve; a zero rate gives 1.
+fn benchmark_fidelity_vs_error(num_qubits: usize, error_rates: &[f64]) -> Vec<BenchmarkResult> {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    circuit.h(0);
+    for qubit in 1..num_qubits {
+        circuit.cnot(qubit - 1, qubit).expect("ladder qubits are in range");
+    }
+    let ideal = circuit.execute();
+    let mut rng = rand::thread_rng();
+
+    error_rates
+        .iter()
+        .map(|&error_rate| {
+            let noise = NoiseModel::new(error_rate, error_rate).expect("error rates are probabilities");
+            let start_memory = get_memory_usage();
+            let start_time = Instant::now();
+            let rho = circuit.execute_noisy(&noise, &mut rng);
+            let execution_time = start_time.elapsed();
+            let end_memory = get_memory_usage();
+
+            BenchmarkResult {
+                fidelity: Some(rho.fidelity(&ideal)),
+                error_rate: Some(error_rate),
+                ..BenchmarkResult::for_circuit(
+                    format!("NoisyFidelity-{}-{}", num_qubits, error_rate),
+                    &circuit,
+                    execution_time,
+                    end_memory - start_memory,
+                )
+            }
+        })
+        .collect()
+}
+
+/// Quantum Shannon Decomposition of a random unitary (the Q factor of a
+/// matrix with uniform random entries). The time covers only the synthesis;
+/// the CNOT count is compared with `cnot_lower_bound`, and the fidelity
+/// `|Tr(U†V)|² / 4^n` checks the circuit `V` against the target `U`.
+fn benchmark_unitary_synthesis(num_qubits: usize) -> BenchmarkResult {
+    let dimension = 1 << num_qubits;
+    let mut rng = rand::thread_rng();
+    let target = DMatrix::from_fn(dimension, dimension, |_, _| {
+        Complex64::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+    })
+    .qr()
+    .q();
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let mut circuit = synthesize_unitary(&target, num_qubits).expect("Q factor is unitary");
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    circuit.cache_unitary().expect("synthesis sizes are within the cache limit");
+    circuit.execute();
+    let synthesized = circuit.cached_unitary().expect("executed with caching on");
+    let fidelity = target.dotc(synthesized).norm_sqr() / (dimension * dimension) as f64;
+
+    let lower_bound = (4usize.pow(num_qubits as u32) - 3 * num_qubits - 1).div_ceil(4);
+    eprintln!(
+        "Unitary synthesis on {} qubits: {} CNOTs (lower bound {})",
+        num_qubits,
+        circuit.num_two_qubit_gates(),
+        lower_bound
+    );
+
+    BenchmarkResult {
+        fidelity: Some(fidelity),
+        cnot_lower_bound: Some(lower_bound),
+        ..BenchmarkResult::for_circuit(
+            format!("Synthesis-{}", num_qubits),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Stored gate matrices versus matrices rebuilt from `GateKind` for every
+/// gate, the trade-off of keeping only the serializable gate names on each
+/// operation. The `Kind` result's fidelity is against the stored-matrix state.
+fn benchmark_gate_representation(num_qubits: usize) -> Vec<BenchmarkResult> {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    let mut rng = rand::thread_rng();
+    for _ in 0..num_qubits * 20 {
+        let qubit = rng.gen_range(0..num_qubits);
+        let angle = rng.gen::<f64>() * 2.0 * PI;
+        match rng.gen_range(0..6) {
+            0 => { circuit.h(qubit); }
+            1 => { circuit.t(qubit); }
+            2 => { circuit.rx(qubit, angle); }
+            3 => { circuit.ry(qubit, angle); }
+            4 => { circuit.rz(qubit, angle); }
+            _ if num_qubits > 1 => {
+                let target = (qubit + rng.gen_range(1..num_qubits)) % num_qubits;
+                circuit.cnot(qubit, target).expect("target differs from control");
+            }
+            _ => { circuit.x(qubit); }
+        }
+    }
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let stored_state = circuit.execute();
+    let stored_time = start_time.elapsed();
+    let stored_memory = get_memory_usage() - start_memory;
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let rebuilt_state = execute_from_kinds(&circuit);
+    let rebuilt_time = start_time.elapsed();
+    let rebuilt_memory = get_memory_usage() - start_memory;
+
+    let gate_bytes = |with_matrix: bool| {
+        circuit.operations().len()
+            * if with_matrix {
+                std::mem::size_of::<DMatrix<Complex64>>() + 4 * std::mem::size_of::<Complex64>()
+            } else {
+                std::mem::size_of::<GateKind>()
+            }
+    };
+    eprintln!(
+        "Gate representation on {} qubits: rebuilding matrices costs {:.2}x, stored matrices take {} bytes vs {} for kinds",
+        num_qubits,
+        rebuilt_time.as_secs_f64() / stored_time.as_secs_f64().max(f64::EPSILON),
+        gate_bytes(true),
+        gate_bytes(false)
+    );
+
+    let agreement = stored_state.fidelity(&rebuilt_state);
+    [
+        ("Matrix", stored_time, stored_memory, None),
+        ("Kind", rebuilt_time, rebuilt_memory, Some(agreement)),
+    ]
+    .into_iter()
+    .map(|(label, time, memory, fidelity)| BenchmarkResult {
+        fidelity,
+        ..BenchmarkResult::for_circuit(
+            format!("GateRepr-{}-{}", label, num_qubits),
+            &circuit,
+            time,
+            memory,
+        )
+    })
+    .collect()
+}
+
+/// Cost of limited connectivity: a circuit with a CNOT between every pair of
+/// qubits (between layers of random rotations) is run as written and after
+/// `route_linear` maps it onto a nearest-neighbour line. The routed result
+/// carries the SWAP count and its fidelity against the all-to-all state.
+fn benchmark_connectivity_cost(num_qubits: usize) -> Vec<BenchmarkResult> {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    let mut rng = rand::thread_rng();
+    for qubit in 0..num_qubits {
+        circuit.ry(qubit, rng.gen::<f64>() * 2.0 * PI);
+    }
+    for control in 0..num_qubits {
+        for target in (control + 1)..num_qubits {
+            circuit.cnot(control, target).expect("target differs from control");
+            circuit.rz(target, rng.gen::<f64>() * 2.0 * PI);
+        }
+    }
+    let (routed, swaps) = circuit.route_linear().expect("circuit has only one- and two-qubit gates");
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let all_to_all_state = circuit.execute();
+    let all_to_all_time = start_time.elapsed();
+    let all_to_all_memory = get_memory_usage() - start_memory;
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+    let linear_state = routed.execute();
+    let linear_time = start_time.elapsed();
+    let linear_memory = get_memory_usage() - start_memory;
+
+    eprintln!(
+        "Linear routing on {} qubits: {} SWAPs, {:.2}x execution time",
+        num_qubits,
+        swaps,
+        linear_time.as_secs_f64() / all_to_all_time.as_secs_f64().max(f64::EPSILON)
+    );
+
+    let agreement = linear_state.fidelity(&all_to_all_state);
+    [
+        ("AllToAll", &circuit, all_to_all_time, all_to_all_memory, None),
+        ("Linear", &routed, linear_time, linear_memory, Some(swaps)),
+    ]
+    .into_iter()
+    .map(|(label, circuit, time, memory, swaps)| BenchmarkResult {
+        fidelity: swaps.map(|_| agreement),
+        swaps,
+        ..BenchmarkResult::for_circuit(
+            format!("Connectivity-{}-{}", label, num_qubits),
+            circuit,
+            time,
+            memory,
+        )
+    })
+    .collect()
+}
+
+/// Up to `STATE_DUMP_MAX_AMPLITUDES` of the largest non-negligible
+/// amplitudes, in basis order.
+fn state_dump(state: &QuantumState) -> Vec<AmplitudeEntry> {
+    let mut entries: Vec<AmplitudeEntry> = state
+        .amplitudes()
+        .iter()
+        .enumerate()
+        .filter(|(_, amp)| amp.norm() > 1e-12)
+        .map(|(index, amp)| AmplitudeEntry {
+            index,
+            re: amp.re,
+            im: amp.im,
+        })
+        .collect();
+
+    entries.sort_by(|a, b| (b.re.hypot(b.im)).total_cmp(&a.re.hypot(a.im)));
+    entries.truncate(STATE_DUMP_MAX_AMPLITUDES);
+    entries.sort_by_key(|entry| entry.index);
+    entries
+}
+
+/// `P(1)` of every qubit, in qubit order.
+fn qubit_marginals(state: &QuantumState) -> Vec<f64> {
+    (0..state.num_qubits()).map(|qubit| state.qubit_probability(qubit).1).collect()
+}
+
+/// Energy estimate from `shots` samples per measurement group. Each group is
+/// rotated into its shared basis (H for X, RX(π/2) for Y), sampled, and every
+/// term in it is read off the parity of its qubits in the same samples.
+/// Identity terms, a global phase in the evolution, are constant energy
+/// offsets and are added exactly.
+fn sampled_energy(
+    hamiltonian: &Hamiltonian,
+    state: &QuantumState,
+    groups: &[Vec<usize>],
+    shots: usize,
+    rng: &mut impl Rng,
+) -> f64 {
+    let mut energy: f64 = hamiltonian
+        .terms
+        .iter()
+        .filter(|term| term.operators.is_empty())
+        .map(|term| term.coefficient)
+        .sum();
+
+    for group in groups {
+        let mut rotation = QuantumCircuit::new(state.num_qubits());
+        let mut rotated_qubits = HashSet::new();
+        for &index in group {
+            for &(qubit, pauli) in &hamiltonian.terms[index].operators {
+                if !rotated_qubits.insert(qubit) {
+                    continue;
+                }
+                match pauli {
+                    Pauli::X => {
+                        rotation.h(qubit);
+                    }
+                    Pauli::Y => {
+                        rotation.rx(qubit, PI / 2.0);
+                    }
+                    Pauli::Z => {}
+                }
+            }
+        }
+
+        let rotated = rotation.execute_from(state.clone());
+        let sampler = WeightedIndex::new(rotated.probabilities()).expect("probabilities are non-negative");
+        let samples: Vec<usize> = (0..shots).map(|_| sampler.sample(rng)).collect();
+
+        for &index in group {
+            let term = &hamiltonian.terms[index];
+            let mask = term.operators.iter().fold(0usize, |acc, &(qubit, _)| acc | (1 << qubit));
+            let parity_sum: i64 = samples
+                .iter()
+                .map(|sample| if (sample & mask).count_ones() % 2 == 0 { 1 } else { -1 })
+                .sum();
+            energy += term.coefficient * parity_sum as f64 / shots as f64;
+        }
+    }
+
+    energy
+}
+
+/// Independent estimates averaged into each shot-noise error.
+const SHOT_NOISE_TRIALS: usize = 20;
+
+/// Shot noise of a sampled energy: `⟨H⟩` of `adiabatic_hamiltonian(n, 0.5)`
+/// (qubit-wise commuting groups, so two measurement bases) on an entangled
+/// RY/CNOT state, estimated from each entry of `shots_list` shots per group.
+/// `expectation_error` is the RMS error against the exact value over
+/// `SHOT_NOISE_TRIALS` estimates, which should fall as `1 / sqrt(shots)`;
+/// the time is per estimate.
+fn benchmark_expectation_shot_noise(num_qubits: usize, shots_list: &[usize]) -> Vec<BenchmarkResult> {
+    let hamiltonian = adiabatic_hamiltonian(num_qubits, 0.5);
+    let groups = hamiltonian.qubit_wise_commuting_groups();
+
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for qubit in 0..num_qubits {
+        circuit.ry(qubit, 0.4 + 0.3 * qubit as f64);
+    }
+    for qubit in 1..num_qubits {
+        circuit.cnot(qubit - 1, qubit).expect("ladder qubits are in range");
+    }
+    let state = circuit.execute();
+    let exact = hamiltonian.expectation(&state).expect("Hamiltonian fits the register");
+
+    let mut rng = rand::thread_rng();
+    shots_list
+        .iter()
+        .map(|&shots| {
+            let start_memory = get_memory_usage();
+            let start_time = Instant::now();
+
+            let squared_error: f64 = (0..SHOT_NOISE_TRIALS)
+                .map(|_| (sampled_energy(&hamiltonian, &state, &groups, shots, &mut rng) - exact).powi(2))
+                .sum();
+
+            let execution_time = start_time.elapsed();
+            let end_memory = get_memory_usage();
+
+            BenchmarkResult {
+                measurement_circuits: Some(groups.len()),
+                total_shots: Some(groups.len() * shots),
+                expectation_error: Some((squared_error / SHOT_NOISE_TRIALS as f64).sqrt()),
+                ..BenchmarkResult::for_circuit(
+                    format!("ShotNoise-{}-{}", num_qubits, shots),
+                    &circuit,
+                    execution_time / SHOT_NOISE_TRIALS as u32,
+                    end_memory - start_memory,
+                )
+            }
+        })
+        .collect()
+}
+
+/// Measurement cost of a VQE energy estimate on the molecule's exact ground
+/// state: one circuit per Pauli term versus one per qubit-wise commuting
+/// group, at a fixed number of shots per circuit. The state is prepared
+/// exactly rather than by a circuit, so fidelity and the circuit columns are
+/// left empty; the interesting columns are `measurement_circuits` and
+/// `total_shots`.
+fn benchmark_vqe_measurement_grouping(molecule: Molecule) -> Vec<BenchmarkResult> {
+    let shots_per_circuit = 1000;
+    let num_qubits = molecule.num_qubits();
+    let hamiltonian = molecule.hamiltonian();
+
+    let eigen = hamiltonian
+        .to_matrix(num_qubits)
+        .expect("molecular Hamiltonian fits its register")
+        .symmetric_eigen();
+    let ground_index = eigen.eigenvalues.imin();
+    let ground_state = QuantumState::from_amplitudes(eigen.eigenvectors.column(ground_index).into_owned())
+        .expect("eigenvectors are normalised");
+    let exact_energy = eigen.eigenvalues[ground_index];
+
+    let ungrouped: Vec<Vec<usize>> = (0..hamiltonian.terms.len())
+        .filter(|&index| !hamiltonian.terms[index].operators.is_empty())
+        .map(|index| vec![index])
+        .collect();
+    let grouped = hamiltonian.qubit_wise_commuting_groups();
+
+    let mut rng = rand::thread_rng();
+    let mut results = Vec::new();
+    let mut estimates = Vec::new();
+    for (label, groups) in [("Ungrouped", &ungrouped), ("Grouped", &grouped)] {
+        let start_memory = get_memory_usage();
+        let start_time = Instant::now();
+
+        let estimate = sampled_energy(&hamiltonian, &ground_state, groups, shots_per_circuit, &mut rng);
+
+        let execution_time = start_time.elapsed();
+        let end_memory = get_memory_usage();
+        estimates.push(estimate);
+
+        results.push(BenchmarkResult {
+            name: format!("VQE-{}-{}", molecule.name(), label),
+            num_qubits,
+            execution_time_ms: execution_time.as_secs_f64() * 1000.0,
+            memory_usage_mb: end_memory - start_memory,
+            measurement_circuits: Some(groups.len()),
+            total_shots: Some(groups.len() * shots_per_circuit),
+            ..BenchmarkResult::default()
+        });
+    }
+
+    eprintln!(
+        "VQE grouping for {}: {} -> {} circuits ({:.1}x fewer), energies {:.4} / {:.4} (exact {:.4})",
+        molecule.name(),
+        ungrouped.len(),
+        grouped.len(),
+        ungrouped.len() as f64 / grouped.len().max(1) as f64,
+        estimates[0],
+        estimates[1],
+        exact_energy
+    );
+
+    results
+}
+
+/// Ansatz depth for `benchmark_vqe`. From a small random start Nelder-Mead
+/// can stall at the Hartree-Fock energy with a single entangling layer; two
+/// layers reach the H2 ground state.
+const VQE_LAYERS: usize = 2;
+
+/// Full VQE loop: `options.optimizer` tunes the `ry_ansatz` parameters from
+/// a small random start until `options` says stop. The fidelity is the overlap
+/// of the optimized ansatz state with the exact ground state.
+fn benchmark_vqe(molecule: Molecule, options: &VqeOptions) -> BenchmarkResult {
+    let num_qubits = molecule.num_qubits();
+    let hamiltonian = molecule.hamiltonian();
+    let (exact_energy, ground_state) = hamiltonian
+        .ground_state(num_qubits)
+        .expect("molecular Hamiltonian fits its register");
+
+    // Start near zero but off it: all-zero angles sit on a saddle point
+    let mut rng = rand::thread_rng();
+    let initial: Vec<f64> = (0..ansatz_parameter_count(num_qubits, VQE_LAYERS))
+        .map(|_| rng.gen_range(-0.1..0.1))
+        .collect();
+
+    let start_memory = get_memory_usage();
+    let start_time = Instant::now();
+
+    let result = minimize_energy(&hamiltonian, num_qubits, VQE_LAYERS, &initial, options)
+        .expect("ansatz parameters match the register");
+
+    let execution_time = start_time.elapsed();
+    let end_memory = get_memory_usage();
+
+    let circuit =
+        ry_ansatz(num_qubits, VQE_LAYERS, &result.parameters).expect("optimizer keeps the parameter count");
+    eprintln!(
+        "VQE for {} ({}): energy {:.6} (exact {:.6}) after {} iterations{}",
+        molecule.name(),
+        options.optimizer.name(),
+        result.energy,
+        exact_energy,
+        result.iterations,
+        if result.converged { "" } else { " (iteration cap reached)" }
+    );
+
+    BenchmarkResult {
+        fidelity: Some(circuit.execute().fidelity(&ground_state)),
+        ..BenchmarkResult::for_circuit(
+            format!("VQE-{}-{}", molecule.name(), options.optimizer.name()),
+            &circuit,
+            execution_time,
+            end_memory - start_memory,
+        )
+    }
+}
+
+/// Times pretty vs compact JSON serialization of the collected results, averaged
+/// over `repeats` runs. For large sweeps this is a measurable slice of the run.
+fn benchmark_serialization(suite: &BenchmarkSuite, repeats: usize) -> SerializationTiming {
+    let repeats = repeats.max(1);
+
+    let start_time = Instant::now();
+    let mut pretty_bytes = 0;
+    for _ in 0..repeats {
+        pretty_bytes = serde_json::to_string_pretty(suite)
+            .expect("Failed to serialize benchmark results")
+            .len();
+    }
+    let pretty_ms = start_time.elapsed().as_secs_f64() * 1000.0 / repeats as f64;
+
+    let start_time = Instant::now();
+    let mut compact_bytes = 0;
+    for _ in 0..repeats {
+        compact_bytes = serde_json::to_string(suite)
+            .expect("Failed to serialize benchmark results")
+            .len();
+    }
+    let compact_ms = start_time.elapsed().as_secs_f64() * 1000.0 / repeats as f64;
+
+    SerializationTiming {
+        repeats,
+        pretty_ms,
+        compact_ms,
+        pretty_bytes,
+        compact_bytes,
+    }
+}
+
+/// Runs the whole suite for `config` and collects the results.
+pub fn run_benchmarks(config: BenchmarkConfig) -> BenchmarkSuite {
+    let suite_start = Instant::now();
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+    let interrupted = || config.interrupt.as_ref().is_some_and(InterruptFlag::is_interrupted);
+    let budget_left = || !interrupted() && config.time_budget.is_none_or(|budget| suite_start.elapsed() < budget);
+    
+    eprintln!("Starting LogosQ Rust benchmarks...");
+
+    let mut qubit_sizes = config.qubit_sizes.clone();
+    match (config.time_budget, config.budget_order) {
+        (None, _) => {}
+        (Some(_), BudgetOrder::SmallestFirst) => qubit_sizes.sort_unstable(),
+        (Some(_), BudgetOrder::LargestFirst) => qubit_sizes.sort_unstable_by(|a, b| b.cmp(a)),
+    }
+    
+    for num_qubits in qubit_sizes {
+        if num_qubits <= 14 { // Limit for exponential memory growth
+            if !budget_left() {
+                skipped.push(format!("{} qubits", num_qubits));
+                continue;
+            }
+            eprintln!("Benchmarking {} qubits...", num_qubits);
+            
+            // GHZ state benchmark
+            results.push(benchmark_ghz_state(num_qubits, config.dump_state, config.qubit_marginals));
+
+            // W state via the controlled-RY cascade
+            results.push(benchmark_w_state(num_qubits, config.dump_state, config.qubit_marginals));
+            
+            // Random circuit benchmark
+            let gate_count = num_qubits * 10;
+            match config.gate_weights.as_ref() {
+                Some(weights) if !weights.fits(num_qubits) => eprintln!(
+                    "Warning: skipping Random-{}: a non-zero CNOT weight needs at least two qubits",
+                    num_qubits
+                ),
+                weights => results.push(benchmark_random_circuit(
+                    num_qubits,
+                    gate_count,
+                    true,
+                    weights,
+                    config.dump_state,
+                    config.qubit_marginals,
+                )),
+            }
+            
+            // QFT benchmark (only for smaller systems due to complexity)
+            if num_qubits <= 10 {
+                results.push(benchmark_qft_circuit(num_qubits, config.dump_state, config.qubit_marginals));
+            }
+
+            // Fourier-basis adder on two num_qubits/2-bit registers
+            results.push(benchmark_draper_adder(num_qubits / 2, config.dump_state, config.qubit_marginals));
+
+            // Period finding for 7 mod 15 with the remaining qubits counting
+            if num_qubits >= 8 {
+                results.push(benchmark_shor_period_finding(
+                    num_qubits - 4,
+                    7,
+                    config.dump_state,
+                    config.qubit_marginals,
+                ));
+            }
+
+            // Dense-matrix reference check (2^n x 2^n matrices per gate)
+            if num_qubits <= 8 {
+                results.extend(benchmark_vs_naive(num_qubits));
+            }
+
+            // Trotter error sweep against exact evolution (dense diagonalisation)
+            if num_qubits <= 8 {
+                results.extend(benchmark_trotter_error(num_qubits, 8));
+            }
+
+            // Compiling a random unitary into CNOTs and rotations
+            if num_qubits <= 6 {
+                results.push(benchmark_unitary_synthesis(num_qubits));
+            }
+
+            // Stored gate matrices versus rebuilding them from gate kinds
+            results.extend(benchmark_gate_representation(num_qubits));
+
+            // SWAP overhead of routing an all-to-all circuit onto a line
+            results.extend(benchmark_connectivity_cost(num_qubits));
+
+            // Drift over a deep circuit that composes to the identity
+            results.push(benchmark_numerical_stability(num_qubits, 100));
+
+            // Sampled versus exact energy at increasing shot counts
+            if num_qubits <= 8 {
+                results.extend(benchmark_expectation_shot_noise(num_qubits, &[100, 1000, 10000]));
+            }
+
+            // Reduced density matrices of growing subsystems
+            results.extend(benchmark_partial_trace(num_qubits));
+
+            // Fidelity decay of noisy GHZ preparation (4^n density matrix)
+            if num_qubits <= 8 {
+                results.extend(benchmark_fidelity_vs_error(num_qubits, &[0.0, 0.001, 0.005, 0.01, 0.02, 0.05]));
+            }
+
+            // Adiabatic sweep into an Ising ground state (dense diagonalisation)
+            if num_qubits <= 8 {
+                results.push(benchmark_adiabatic(num_qubits, 100));
+            }
+        }
+    }
+    
+    // Exact statevector footprint up to the largest benchmarked register
+    let max_qubits = config.qubit_sizes.iter().copied().filter(|&n| n <= 14).max().unwrap_or(0);
+    if budget_left() {
+        results.extend(benchmark_memory_scaling(max_qubits));
+    } else {
+        skipped.push("memory scaling".to_string());
+    }
+
+    // Measurement-grouping savings for a VQE energy estimate
+    if budget_left() {
+        results.extend(benchmark_vqe_measurement_grouping(Molecule::H2));
+    } else {
+        skipped.push("VQE measurement grouping".to_string());
+    }
+
+    // Variational ground-state search, once per configured optimizer
+    for options in &config.vqe {
+        if budget_left() {
+            results.push(benchmark_vqe(Molecule::H2, options));
+        } else {
+            skipped.push(format!("VQE {}", options.optimizer.name()));
+        }
+    }
+
+    if !skipped.is_empty() {
+        let reason = if interrupted() { "Interrupted" } else { "Time budget exhausted" };
+        eprintln!("{}, skipped: {}", reason, skipped.join(", "));
+    }
+
+    let total_time = suite_start.elapsed();
+    
+    let mut benchmark_suite = BenchmarkSuite {
+        library: LIBRARY_LABEL.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        results,
+        total_time_ms: total_time.as_secs_f64() * 1000.0,
+        serialization: None,
+        skipped,
+        interrupted: interrupted(),
+    };
+
+    if config.serialization_repeats > 0 && budget_left() {
+        let serialization = benchmark_serialization(&benchmark_suite, config.serialization_repeats);
+        eprintln!(
+            "Serialization: pretty {:.3}ms ({} bytes), compact {:.3}ms ({} bytes)",
+            serialization.pretty_ms,
+            serialization.pretty_bytes,
+            serialization.compact_ms,
+            serialization.compact_bytes
+        );
+        benchmark_suite.serialization = Some(serialization);
+    }
+
+    benchmark_suite
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gates;
+    use rand::SeedableRng;
+
+    #[test]
+    fn trotter_fidelity_improves_with_steps() {
+        let results = benchmark_trotter_error(3, 6);
+        assert_eq!(results.len(), 6);
+        let fidelities: Vec<f64> = results.iter().map(|r| r.fidelity.unwrap()).collect();
+        for pair in fidelities.windows(2) {
+            assert!(pair[1] > pair[0], "fidelity not increasing: {:?}", fidelities);
+        }
+    }
+
+    #[test]
+    fn ghz_and_qft_records_describe_their_circuits() {
+        let ghz = benchmark_ghz_state(5, false, false);
+        assert_eq!((ghz.num_gates, ghz.circuit_depth), (5, 5));
+
+        // n Hadamards and n(n-1)/2 controlled phases in 2n - 1 layers
+        let qft = benchmark_qft_circuit(4, false, false);
+        assert_eq!(qft.num_gates, 4 + 6);
+        assert_eq!(qft.circuit_depth, 7);
+        assert!((qft.parallelism - qft.num_gates as f64 / qft.circuit_depth as f64).abs() < 1e-12);
+    }
+
+    #[test]
+    fn random_circuit_without_entanglers_has_no_two_qubit_gates() {
+        let result = benchmark_random_circuit(4, 200, false, None, false, false);
+        assert_eq!(result.num_gates, 200);
+        assert_eq!(benchmark_random_circuit(4, 200, true, None, false, false).num_gates, 250);
+    }
+
+    #[test]
+    fn draper_adder_adds_small_numbers() {
+        let num_bits = 3;
+        for (a, b) in [(3, 2), (5, 6), (7, 1), (0, 4)] {
+            let state = draper_adder_circuit(num_bits, a, b).execute();
+            let expected = a | ((a + b) % 8) << num_bits;
+            assert!(
+                (state.get_probability(expected) - 1.0).abs() < 1e-10,
+                "{} + {} is not deterministic",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn full_hadamard_weight_draws_only_hadamards() {
+        let mut weights = vec![0.0; RANDOM_GATE_NAMES.len()];
+        weights[0] = 1.0;
+        let weights = GateWeights::new(weights).unwrap();
+
+        let circuit = random_circuit(4, 100, true, Some(&weights), &mut rand::thread_rng());
+        assert_eq!(circuit.num_operations(), 100);
+        assert!(circuit
+            .operations()
+            .iter()
+            .all(|op| matches!(op, Operation::SingleGate { kind: GateKind::H, .. })));
+    }
+
+    #[test]
+    fn gate_weights_are_validated_and_normalised() {
+        assert!(GateWeights::new(vec![1.0; 3]).is_err());
+        assert!(GateWeights::new(vec![0.0; RANDOM_GATE_NAMES.len()]).is_err());
+        let mut negative = vec![1.0; RANDOM_GATE_NAMES.len()];
+        negative[2] = -0.5;
+        assert!(GateWeights::new(negative).is_err());
+
+        let weights = GateWeights::new(vec![2.0; RANDOM_GATE_NAMES.len()]).unwrap();
+        assert!((weights.0.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cnot_weight_needs_two_qubits() {
+        let mut weights = vec![0.0; RANDOM_GATE_NAMES.len()];
+        weights[0] = 1.0;
+        assert!(GateWeights::new(weights.clone()).unwrap().fits(1));
+        weights[RANDOM_GATE_NAMES.len() - 1] = 0.5;
+        let weights = GateWeights::new(weights).unwrap();
+        assert!(!weights.fits(1));
+        assert!(weights.fits(2));
+    }
+
+    #[test]
+    fn period_finding_concentrates_on_multiples_of_the_spacing() {
+        // 7 and 2 have period 4 mod 15, 11 and 4 period 2; both divide 2^n,
+        // so all the weight lands exactly on multiples of 2^n / r
+        for (counting_qubits, base) in [(4, 7), (4, 2), (3, 11), (3, 4)] {
+            let result = benchmark_shor_period_finding(counting_qubits, base, false, false);
+            let on_multiples = result.fidelity.unwrap();
+            assert!(on_multiples > 1.0 - 1e-9, "base {}: {}", base, on_multiples);
+        }
+    }
+
+    #[test]
+    fn run_benchmarks_with_a_tiny_config() {
+        let suite = run_benchmarks(BenchmarkConfig {
+            qubit_sizes: vec![2],
+            serialization_repeats: 1,
+            ..BenchmarkConfig::default()
+        });
+
+        assert_eq!(suite.library, LIBRARY_LABEL);
+        assert!(suite.serialization.is_some());
+        for prefix in ["GHZ-2", "W-2", "Random-2", "QFT-2", "Draper-1"] {
+            assert!(suite.results.iter().any(|r| r.name.starts_with(prefix)), "no {} result", prefix);
+        }
+        let ghz = suite.results.iter().find(|r| r.name == "GHZ-2").unwrap();
+        assert_eq!((ghz.num_qubits, ghz.num_two_qubit_gates), (2, 1));
+        assert!(suite.results.iter().all(|r| r.num_qubits <= 4));
+    }
+
+    #[test]
+    fn weighted_cnots_skip_single_qubit_random_circuits() {
+        let mut weights = vec![1.0; RANDOM_GATE_NAMES.len()];
+        let config = |weights: Vec<f64>| BenchmarkConfig {
+            qubit_sizes: vec![1],
+            gate_weights: Some(GateWeights::new(weights).unwrap()),
+            serialization_repeats: 0,
+            ..BenchmarkConfig::default()
+        };
+
+        let suite = run_benchmarks(config(weights.clone()));
+        assert!(!suite.results.iter().any(|r| r.name.starts_with("Random-")));
+
+        weights[RANDOM_GATE_NAMES.len() - 1] = 0.0;
+        let suite = run_benchmarks(config(weights));
+        let random = suite.results.iter().find(|r| r.name.starts_with("Random-")).unwrap();
+        assert_eq!(random.num_two_qubit_gates, 0);
+    }
+
+    #[test]
+    fn grouped_and_ungrouped_estimates_agree_within_shot_noise() {
+        let hamiltonian = Molecule::H2.hamiltonian();
+        let eigen = hamiltonian.to_matrix(4).unwrap().symmetric_eigen();
+        let ground_index = eigen.eigenvalues.imin();
+        let exact = eigen.eigenvalues[ground_index];
+        let ground_state =
+            QuantumState::from_amplitudes(eigen.eigenvectors.column(ground_index).into_owned()).unwrap();
+        let ungrouped: Vec<Vec<usize>> = (0..hamiltonian.terms.len())
+            .filter(|&index| !hamiltonian.terms[index].operators.is_empty())
+            .map(|index| vec![index])
+            .collect();
+        let grouped = hamiltonian.qubit_wise_commuting_groups();
+        assert!(grouped.len() < ungrouped.len());
+
+        let shots = 4000;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(232);
+        let ungrouped_estimate = sampled_energy(&hamiltonian, &ground_state, &ungrouped, shots, &mut rng);
+        let grouped_estimate = sampled_energy(&hamiltonian, &ground_state, &grouped, shots, &mut rng);
+
+        // Each estimate's standard deviation is at most λ / √shots
+        let weight: f64 = hamiltonian
+            .terms
+            .iter()
+            .filter(|term| !term.operators.is_empty())
+            .map(|term| term.coefficient.abs())
+            .sum();
+        let sigma = weight / (shots as f64).sqrt();
+        assert!((grouped_estimate - ungrouped_estimate).abs() < 5.0 * sigma);
+        assert!((grouped_estimate - exact).abs() < 5.0 * sigma);
+    }
+
+    #[test]
+    fn ghz_state_dump_holds_the_two_amplitudes() {
+        let result = benchmark_ghz_state(4, true, false);
+        let dump = result.final_state.unwrap();
+        let indices: Vec<usize> = dump.iter().map(|entry| entry.index).collect();
+        assert_eq!(indices, vec![0b0000, 0b1111]);
+        for entry in &dump {
+            assert!((entry.re - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12 && entry.im.abs() < 1e-12);
+        }
+
+        assert!(benchmark_ghz_state(4, false, false).final_state.is_none());
+    }
+
+    #[test]
+    fn state_dump_is_capped() {
+        let mut circuit = QuantumCircuit::new(8);
+        for qubit in 0..8 {
+            circuit.h(qubit);
+        }
+        assert_eq!(state_dump(&circuit.execute()).len(), STATE_DUMP_MAX_AMPLITUDES);
+    }
+
+    #[test]
+    fn slow_adiabatic_sweep_reaches_the_ground_state() {
+        let result = benchmark_adiabatic(3, 200);
+        let overlap = result.fidelity.unwrap();
+        assert!(overlap > 0.95, "ground-state overlap {}", overlap);
+    }
+
+    #[test]
+    fn memory_scaling_matches_the_analytic_footprint() {
+        let results = benchmark_memory_scaling(10);
+        assert_eq!(results.len(), 10);
+        for (result, n) in results.iter().zip(1..) {
+            assert_eq!(result.num_qubits, n);
+            assert_eq!(result.state_bytes, Some(16usize << n));
+        }
+    }
+
+    #[test]
+    fn linear_routing_of_all_to_all_circuit_needs_swaps() {
+        let results = benchmark_connectivity_cost(4);
+        assert_eq!(results[0].swaps, None);
+        let linear = &results[1];
+        assert!(linear.swaps.unwrap() > 0);
+        assert!(linear.fidelity.unwrap() > 1.0 - 1e-10);
+        assert!(linear.num_two_qubit_gates > results[0].num_two_qubit_gates);
+    }
+
+    #[test]
+    fn deep_identity_circuit_keeps_fidelity() {
+        let result = benchmark_numerical_stability(4, 200);
+        assert!(result.fidelity.unwrap() > 1.0 - 1e-10, "fidelity {:?}", result.fidelity);
+    }
+
+    #[test]
+    fn shot_noise_falls_as_inverse_square_root() {
+        let results = benchmark_expectation_shot_noise(3, &[100, 10_000]);
+        let errors: Vec<f64> = results.iter().map(|r| r.expectation_error.unwrap()).collect();
+        // 100x the shots should cut the RMS error about 10x; the RMS over
+        // SHOT_NOISE_TRIALS estimates is itself only good to ~20%
+        let ratio = errors[0] / errors[1];
+        assert!((4.0..25.0).contains(&ratio), "errors {:?}, ratio {}", errors, ratio);
+        assert_eq!(results[1].total_shots, Some(results[1].measurement_circuits.unwrap() * 10_000));
+    }
+
+    #[test]
+    fn partial_trace_benchmark_runs_and_traces_to_subsystem() {
+        let results = benchmark_partial_trace(6);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["PartialTrace-6-1", "PartialTrace-6-2", "PartialTrace-6-3"]);
+
+        let mut circuit = QuantumCircuit::new(6);
+        circuit.h(0).cnot(0, 4).unwrap().ry(2, 0.8);
+        let state = circuit.execute();
+        for kept in 1..=3 {
+            let keep: Vec<usize> = (0..kept).collect();
+            let reduced = DensityMatrix::reduced_from_state(&state, &keep).unwrap();
+            assert_eq!(reduced.num_qubits(), kept);
+            assert_eq!(reduced.matrix().shape(), (1 << kept, 1 << kept));
+            assert!((reduced.trace() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn tiny_time_budget_runs_a_subset_and_records_skips() {
+        // The first size always starts; spawning `ps` for its memory
+        // readings alone outlasts the budget, so nothing after it runs
+        let suite = run_benchmarks(BenchmarkConfig {
+            qubit_sizes: vec![6, 2, 4],
+            serialization_repeats: 1,
+            vqe: Vec::new(),
+            time_budget: Some(Duration::from_millis(1)),
+            budget_order: BudgetOrder::SmallestFirst,
+            ..BenchmarkConfig::default()
+        });
+
+        assert!(suite.results.iter().any(|r| r.name == "GHZ-2"));
+        assert!(suite.results.iter().all(|r| r.num_qubits <= 4), "larger sizes ran");
+        assert!(suite.skipped.starts_with(&["4 qubits".to_string(), "6 qubits".to_string()]), "{:?}", suite.skipped);
+        assert!(suite.skipped.len() > 2, "fixed-size benchmarks should be skipped too");
+    }
+
+    #[test]
+    fn kind_and_matrix_representations_execute_alike() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).x(1).y(2).z(0).t(2).tdg(0);
+        circuit.rx(1, 0.3).ry(2, 1.7).rz(0, -0.8);
+        circuit.add_operation(Operation::SingleGate {
+            gate: Gates::phase(2.2),
+            kind: GateKind::Phase(2.2),
+            qubit: 1,
+        });
+        circuit.cnot(0, 2).unwrap().cry(1, 2, -0.5).unwrap();
+        assert!(execute_from_kinds(&circuit).approx_eq(&circuit.execute(), 1e-12));
+
+        let results = benchmark_gate_representation(4);
+        assert_eq!(results[0].fidelity, None);
+        assert!(results[1].fidelity.unwrap() > 1.0 - 1e-10);
+    }
+
+    #[test]
+    fn ghz_benchmark_reports_half_marginals() {
+        let result = benchmark_ghz_state(5, false, true);
+        let marginals = result.qubit_marginals.unwrap();
+        assert_eq!(marginals.len(), 5);
+        assert!(marginals.iter().all(|p| (p - 0.5).abs() < 1e-12), "{:?}", marginals);
+
+        let json = serde_json::to_value(benchmark_ghz_state(2, false, true)).unwrap();
+        assert_eq!(json["qubit_marginals"].as_array().map(Vec::len), Some(2));
+        assert_eq!(benchmark_ghz_state(2, false, false).qubit_marginals, None);
+    }
+
+    #[test]
+    fn synthesis_benchmark_matches_target() {
+        for num_qubits in 1..=3 {
+            let result = benchmark_unitary_synthesis(num_qubits);
+            assert!(result.fidelity.unwrap() > 1.0 - 1e-9, "{} qubits: {:?}", num_qubits, result.fidelity);
+            let lower_bound = result.cnot_lower_bound.unwrap();
+            assert!(result.num_two_qubit_gates >= lower_bound);
+        }
+    }
+
+    #[test]
+    fn noisy_fidelity_is_one_without_error_and_falls_with_it() {
+        let rates = [0.0, 0.001, 0.01, 0.05, 0.2];
+        let results = benchmark_fidelity_vs_error(3, &rates);
+        let fidelities: Vec<f64> = results.iter().map(|r| r.fidelity.unwrap()).collect();
+        assert!((fidelities[0] - 1.0).abs() < 1e-12);
+        assert!(fidelities.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", fidelities);
+        assert!(results.iter().zip(rates).all(|(r, rate)| r.error_rate == Some(rate)));
+    }
+}
@@ -0,0 +1,703 @@
+use crate::{GateKind, Gates, Operation, QuantumError, QuantumState};
+use std::fmt;
+use std::mem::discriminant;
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex64;
+use rand::Rng;
+
+/// Mixed state `ρ` of an `n`-qubit register as a dense `2^n x 2^n` matrix,
+/// indexed like `QuantumState` (qubit 0 is the least significant bit).
+#[derive(Debug, Clone)]
+pub struct DensityMatrix {
+    matrix: DMatrix<Complex64>,
+    num_qubits: usize,
+}
+
+impl DensityMatrix {
+    /// The pure state |00...0⟩⟨00...0|.
+    pub fn new(num_qubits: usize) -> Self {
+        Self::from_state(&QuantumState::new(num_qubits))
+    }
+
+    /// The pure state `|ψ⟩⟨ψ|`.
+    pub fn from_state(state: &QuantumState) -> Self {
+        let amplitudes = state.amplitudes();
+        DensityMatrix {
+            matrix: amplitudes * amplitudes.adjoint(),
+            num_qubits: state.num_qubits(),
+        }
+    }
+
+    /// Reduced state of `state` on the qubits `keep`, tracing out the rest;
+    /// qubit `keep[k]` becomes qubit `k`. Computed as `M M†` from the
+    /// `2^|keep| x 2^(n - |keep|)` reshaping of the amplitudes, without
+    /// forming the full `4^n` density matrix.
+    pub fn reduced_from_state(state: &QuantumState, keep: &[usize]) -> Result<Self, QuantumError> {
+        let traced = traced_qubits(state.num_qubits(), keep)?;
+        let mut reshaped = DMatrix::from_element(1 << keep.len(), 1 << traced.len(), Complex64::new(0.0, 0.0));
+        for (i, amp) in state.amplitudes().iter().enumerate() {
+            reshaped[(gather_bits(i, keep), gather_bits(i, &traced))] = *amp;
+        }
+        Ok(DensityMatrix {
+            matrix: &reshaped * reshaped.adjoint(),
+            num_qubits: keep.len(),
+        })
+    }
+
+    /// `Tr_B(ρ)` for `B` every qubit not in `keep`; qubit `keep[k]` becomes
+    /// qubit `k`.
+    pub fn partial_trace(&self, keep: &[usize]) -> Result<Self, QuantumError> {
+        let traced = traced_qubits(self.num_qubits, keep)?;
+        let dim = 1usize << keep.len();
+        let matrix = DMatrix::from_fn(dim, dim, |row, column| {
+            (0..1usize << traced.len())
+                .map(|rest| {
+                    let environment = scatter_bits(rest, &traced);
+                    self.matrix[(
+                        scatter_bits(row, keep) | environment,
+                        scatter_bits(column, keep) | environment,
+                    )]
+                })
+                .sum()
+        });
+        Ok(DensityMatrix {
+            matrix,
+            num_qubits: keep.len(),
+        })
+    }
+
+    /// `ρ -> U ρ U†` for the unitary of `operation`.
+    pub fn apply_operation(&mut self, operation: &Operation) {
+        self.conjugate_by(|state| operation.apply(state));
+    }
+
+    /// `ρ -> Σ K ρ K†` for single-qubit Kraus operators `kraus` on `qubit`.
+    /// The operators must satisfy `Σ K† K = I` for the result to stay a state.
+    pub fn apply_kraus(&mut self, kraus: &[DMatrix<Complex64>], qubit: usize) -> Result<(), QuantumError> {
+        self.check_qubit(qubit)?;
+
+        let mut result = DMatrix::from_element(self.matrix.nrows(), self.matrix.ncols(), Complex64::new(0.0, 0.0));
+        for operator in kraus {
+            let mut term = self.clone();
+            term.conjugate_by(|state| state.apply_single_gate(operator, qubit));
+            result += term.matrix;
+        }
+        self.matrix = result;
+        Ok(())
+    }
+
+    /// Amplitude damping (energy relaxation towards |0⟩) with decay
+    /// probability `gamma`.
+    pub fn amplitude_damping(&mut self, qubit: usize, gamma: f64) -> Result<(), QuantumError> {
+        check_probability("damping", gamma)?;
+        let k0 = DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(1.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new((1.0 - gamma).sqrt(), 0.0),
+            ],
+        );
+        let k1 = DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(0.0, 0.0),
+                Complex64::new(gamma.sqrt(), 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+            ],
+        );
+        self.apply_kraus(&[k0, k1], qubit)
+    }
+
+    /// `amplitude_damping` with the same `gamma` on every qubit, i.e. uniform
+    /// T1 decay of the whole register over an idle period. The single-qubit
+    /// channels act on different qubits and commute, so this equals applying
+    /// them one after another in any order.
+    pub fn apply_amplitude_damping_all(&mut self, gamma: f64) -> Result<(), QuantumError> {
+        check_probability("damping", gamma)?;
+        for qubit in 0..self.num_qubits {
+            self.amplitude_damping(qubit, gamma)?;
+        }
+        Ok(())
+    }
+
+    /// Phase damping (pure dephasing): populations are kept and coherences
+    /// shrink by `sqrt(1 - lambda)`.
+    pub fn phase_damping(&mut self, qubit: usize, lambda: f64) -> Result<(), QuantumError> {
+        check_probability("dephasing", lambda)?;
+        let k0 = DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(1.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new((1.0 - lambda).sqrt(), 0.0),
+            ],
+        );
+        let k1 = DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(lambda.sqrt(), 0.0),
+            ],
+        );
+        self.apply_kraus(&[k0, k1], qubit)
+    }
+
+    /// Single-qubit depolarizing channel `ρ -> (1 - p) ρ + p (I/2 ⊗ Tr_q ρ)`,
+    /// i.e. Kraus operators `sqrt(1 - 3p/4) I` and `sqrt(p/4) X, Y, Z`.
+    pub fn depolarizing(&mut self, qubit: usize, p: f64) -> Result<(), QuantumError> {
+        check_probability("depolarizing", p)?;
+        let identity = DMatrix::<Complex64>::identity(2, 2);
+        let keep = Complex64::new((1.0 - 0.75 * p).sqrt(), 0.0);
+        let flip = Complex64::new((0.25 * p).sqrt(), 0.0);
+        self.apply_kraus(
+            &[
+                identity * keep,
+                Gates::pauli_x() * flip,
+                Gates::pauli_y() * flip,
+                Gates::pauli_z() * flip,
+            ],
+            qubit,
+        )
+    }
+
+    /// Two-qubit depolarizing channel `ρ -> (1 - p) ρ + p (I/4 ⊗ Tr_ab ρ)`.
+    /// The fully depolarized part is a Pauli twirl of both qubits, which is
+    /// the same as fully depolarizing each qubit in turn.
+    pub fn two_qubit_depolarizing(&mut self, a: usize, b: usize, p: f64) -> Result<(), QuantumError> {
+        check_probability("depolarizing", p)?;
+        if a == b {
+            return Err(QuantumError::InvalidParameter(
+                "two-qubit depolarizing needs two different qubits".to_string(),
+            ));
+        }
+
+        let mut twirled = self.clone();
+        twirled.depolarizing(a, 1.0)?;
+        twirled.depolarizing(b, 1.0)?;
+        self.matrix = &self.matrix * Complex64::new(1.0 - p, 0.0) + twirled.matrix * Complex64::new(p, 0.0);
+        Ok(())
+    }
+
+    /// Measures `qubit` in the computational basis, collapsing `ρ` onto the
+    /// sampled outcome, which is returned.
+    pub fn measure_qubit(&mut self, qubit: usize, rng: &mut impl Rng) -> Result<u8, QuantumError> {
+        self.check_qubit(qubit)?;
+
+        let mask = 1usize << qubit;
+        let p1: f64 = (0..self.matrix.nrows())
+            .filter(|i| i & mask != 0)
+            .map(|i| self.matrix[(i, i)].re)
+            .sum();
+        let outcome = u8::from(rng.gen::<f64>() < p1 / self.trace());
+        let kept = if outcome == 1 { p1 } else { self.trace() - p1 };
+
+        let keeps = |i: usize| ((i & mask) != 0) == (outcome == 1);
+        let dim = self.matrix.nrows();
+        for column in 0..dim {
+            for row in 0..dim {
+                let entry = &mut self.matrix[(row, column)];
+                *entry = if keeps(row) && keeps(column) {
+                    *entry / kept
+                } else {
+                    Complex64::new(0.0, 0.0)
+                };
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Diagonal of `ρ`: the measurement distribution in the computational basis.
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.matrix.diagonal().iter().map(|p| p.re).collect()
+    }
+
+    pub fn trace(&self) -> f64 {
+        self.matrix.trace().re
+    }
+
+    /// `Tr(ρ²)`: 1 for pure states, down to `2^-n` for the maximally mixed one.
+    pub fn purity(&self) -> f64 {
+        (&self.matrix * &self.matrix).trace().re
+    }
+
+    /// `⟨ψ|ρ|ψ⟩`, the fidelity with a pure reference state.
+    pub fn fidelity(&self, state: &QuantumState) -> f64 {
+        let amplitudes = state.amplitudes();
+        amplitudes.dotc(&(&self.matrix * amplitudes)).re
+    }
+
+    pub fn matrix(&self) -> &DMatrix<Complex64> {
+        &self.matrix
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// `ρ -> A ρ A†` where `apply` performs `A` on a statevector. Each column
+    /// of `ρ` goes through the statevector kernels, giving `A ρ`; doing the
+    /// same to `(A ρ)† = ρ† A†` and taking the adjoint again gives `A ρ A†`.
+    fn conjugate_by(&mut self, apply: impl Fn(&mut QuantumState)) {
+        for _ in 0..2 {
+            for mut column in self.matrix.column_iter_mut() {
+                let mut state = QuantumState {
+                    amplitudes: DVector::from_iterator(column.len(), column.iter().copied()),
+                    num_qubits: self.num_qubits,
+                    global_phase: 0.0,
+                };
+                apply(&mut state);
+                column.copy_from(&state.amplitudes);
+            }
+            self.matrix.adjoint_mut();
+        }
+    }
+
+    fn check_qubit(&self, qubit: usize) -> Result<(), QuantumError> {
+        if qubit >= self.num_qubits {
+            return Err(QuantumError::InvalidQubit {
+                qubit,
+                num_qubits: self.num_qubits,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// How long each class of operation takes, in the same time unit as the
+/// `t1`/`t2` of [`IdleNoise`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GateDurations {
+    pub single_qubit: f64,
+    pub two_qubit: f64,
+    /// Anything acting on three or more qubits (e.g. modular multiplication).
+    pub multi_qubit: f64,
+}
+
+impl GateDurations {
+    pub fn duration(&self, operation: &Operation) -> f64 {
+        if let Operation::HadamardTransform { .. } = operation {
+            // A layer of independent single-qubit gates
+            return self.single_qubit;
+        }
+        match operation.qubits().len() {
+            0 => 0.0,
+            1 => self.single_qubit,
+            2 => self.two_qubit,
+            _ => self.multi_qubit,
+        }
+    }
+}
+
+/// Decoherence of qubits waiting for the rest of a layer to finish: energy
+/// relaxation with time constant `t1` and total dephasing with `t2`. An
+/// infinite time constant disables that channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdleNoise {
+    durations: GateDurations,
+    t1: f64,
+    t2: f64,
+}
+
+impl IdleNoise {
+    /// Requires positive `t1`/`t2` and `t2 <= 2 t1`, the physical bound.
+    pub fn new(durations: GateDurations, t1: f64, t2: f64) -> Result<Self, QuantumError> {
+        if t1.is_nan() || t2.is_nan() || t1 <= 0.0 || t2 <= 0.0 {
+            return Err(QuantumError::InvalidParameter(format!(
+                "T1 and T2 must be positive, got {} and {}",
+                t1, t2
+            )));
+        }
+        if t2 > 2.0 * t1 {
+            return Err(QuantumError::InvalidParameter(format!(
+                "T2 = {} exceeds 2 T1 = {}",
+                t2,
+                2.0 * t1
+            )));
+        }
+        let all = [durations.single_qubit, durations.two_qubit, durations.multi_qubit];
+        if all.iter().any(|d| !d.is_finite() || *d < 0.0) {
+            return Err(QuantumError::InvalidParameter(
+                "gate durations must be finite and non-negative".to_string(),
+            ));
+        }
+
+        Ok(IdleNoise { durations, t1, t2 })
+    }
+
+    pub fn durations(&self) -> &GateDurations {
+        &self.durations
+    }
+
+    /// Relaxes `qubit` for `time`: amplitude damping `1 - e^(-t/T1)`, then
+    /// the pure dephasing `1/Tφ = 1/T2 - 1/(2 T1)` left over once damping has
+    /// taken its share of the coherence decay.
+    pub fn apply_idle(&self, rho: &mut DensityMatrix, qubit: usize, time: f64) -> Result<(), QuantumError> {
+        if time <= 0.0 {
+            return Ok(());
+        }
+
+        rho.amplitude_damping(qubit, 1.0 - (-time / self.t1).exp())?;
+        let dephasing_rate = (1.0 / self.t2 - 0.5 / self.t1).max(0.0);
+        rho.phase_damping(qubit, 1.0 - (-2.0 * time * dephasing_rate).exp())
+    }
+}
+
+/// A single-qubit error channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseChannel {
+    Depolarizing(f64),
+    AmplitudeDamping(f64),
+    PhaseDamping(f64),
+}
+
+impl NoiseChannel {
+    pub fn apply(&self, rho: &mut DensityMatrix, qubit: usize) -> Result<(), QuantumError> {
+        match *self {
+            NoiseChannel::Depolarizing(p) => rho.depolarizing(qubit, p),
+            NoiseChannel::AmplitudeDamping(gamma) => rho.amplitude_damping(qubit, gamma),
+            NoiseChannel::PhaseDamping(lambda) => rho.phase_damping(qubit, lambda),
+        }
+    }
+
+    fn validate(&self) -> Result<(), QuantumError> {
+        match *self {
+            NoiseChannel::Depolarizing(p) => check_probability("depolarizing", p),
+            NoiseChannel::AmplitudeDamping(gamma) => check_probability("damping", gamma),
+            NoiseChannel::PhaseDamping(lambda) => check_probability("dephasing", lambda),
+        }
+    }
+}
+
+/// A single-qubit error process acting on one qubit of a density matrix.
+/// Implemented by `NoiseChannel` and `ComposedChannel`; implement it for
+/// custom processes to stack them with the built-in ones.
+pub trait Channel: fmt::Debug {
+    fn apply(&self, rho: &mut DensityMatrix, qubit: usize) -> Result<(), QuantumError>;
+}
+
+impl Channel for NoiseChannel {
+    fn apply(&self, rho: &mut DensityMatrix, qubit: usize) -> Result<(), QuantumError> {
+        NoiseChannel::apply(self, rho, qubit)
+    }
+}
+
+/// Channels applied in sequence, first pushed first, e.g. dephasing followed
+/// by damping. The empty composition is the identity channel.
+#[derive(Debug, Default)]
+pub struct ComposedChannel {
+    channels: Vec<Box<dyn Channel>>,
+}
+
+impl ComposedChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `channel`, to run after those already added.
+    pub fn then(&mut self, channel: impl Channel + 'static) -> &mut Self {
+        self.channels.push(Box::new(channel));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+}
+
+impl Channel for ComposedChannel {
+    fn apply(&self, rho: &mut DensityMatrix, qubit: usize) -> Result<(), QuantumError> {
+        for channel in &self.channels {
+            channel.apply(rho, qubit)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct GateNoise {
+    kind: GateKind,
+    controlled: bool,
+    channel: NoiseChannel,
+}
+
+/// Where the errors of a noisy execution come from. Two layers, both applied
+/// after every matching operation:
+///
+/// - per gate class: each single-qubit gate depolarizes its qubit with
+///   probability `single_qubit_error`, each two-qubit gate gets a two-qubit
+///   depolarizing channel with probability `two_qubit_error`;
+/// - per gate kind: channels registered with `on_gate`/`on_controlled_gate`
+///   run on every qubit the gate touches. Kinds match regardless of angle,
+///   so `GateKind::Rx(0.0)` stands for every RX.
+///
+/// Operations on three or more qubits and measurements get no noise. The
+/// default model is noiseless.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NoiseModel {
+    single_qubit_error: f64,
+    two_qubit_error: f64,
+    gate_channels: Vec<GateNoise>,
+}
+
+impl NoiseModel {
+    pub fn new(single_qubit_error: f64, two_qubit_error: f64) -> Result<Self, QuantumError> {
+        check_probability("single-qubit error", single_qubit_error)?;
+        check_probability("two-qubit error", two_qubit_error)?;
+        Ok(NoiseModel {
+            single_qubit_error,
+            two_qubit_error,
+            gate_channels: Vec::new(),
+        })
+    }
+
+    /// Every gate is perfect.
+    pub fn noiseless() -> Self {
+        Self::default()
+    }
+
+    /// Adds `channel` after every uncontrolled gate of this kind.
+    pub fn on_gate(&mut self, kind: GateKind, channel: NoiseChannel) -> Result<&mut Self, QuantumError> {
+        self.add_gate_channel(kind, false, channel)
+    }
+
+    /// Adds `channel` after every controlled gate whose target gate has this
+    /// kind (`GateKind::X` for CNOT), on both control and target.
+    pub fn on_controlled_gate(&mut self, kind: GateKind, channel: NoiseChannel) -> Result<&mut Self, QuantumError> {
+        self.add_gate_channel(kind, true, channel)
+    }
+
+    pub fn single_qubit_error(&self) -> f64 {
+        self.single_qubit_error
+    }
+
+    pub fn two_qubit_error(&self) -> f64 {
+        self.two_qubit_error
+    }
+
+    /// Applies the error channels that follow `operation`.
+    pub fn apply_after(&self, rho: &mut DensityMatrix, operation: &Operation) -> Result<(), QuantumError> {
+        match operation {
+            Operation::Measure { .. } => return Ok(()),
+            Operation::HadamardTransform { qubits } => {
+                // Same noise as the individual Hadamards it stands for
+                for &qubit in qubits {
+                    let hadamard = Operation::SingleGate {
+                        gate: Gates::hadamard(),
+                        kind: GateKind::H,
+                        qubit,
+                    };
+                    self.apply_after(rho, &hadamard)?;
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let qubits = operation.qubits();
+        match qubits[..] {
+            [qubit] if self.single_qubit_error > 0.0 => rho.depolarizing(qubit, self.single_qubit_error)?,
+            [a, b] if self.two_qubit_error > 0.0 => rho.two_qubit_depolarizing(a, b, self.two_qubit_error)?,
+            _ => {}
+        }
+
+        let (kind, controlled) = match operation {
+            Operation::SingleGate { kind, .. } => (kind, false),
+            Operation::ControlledGate { kind, .. } => (kind, true),
+            _ => return Ok(()),
+        };
+        for noise in &self.gate_channels {
+            if noise.controlled == controlled && discriminant(&noise.kind) == discriminant(kind) {
+                for &qubit in &qubits {
+                    noise.channel.apply(rho, qubit)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_gate_channel(
+        &mut self,
+        kind: GateKind,
+        controlled: bool,
+        channel: NoiseChannel,
+    ) -> Result<&mut Self, QuantumError> {
+        channel.validate()?;
+        self.gate_channels.push(GateNoise {
+            kind,
+            controlled,
+            channel,
+        });
+        Ok(self)
+    }
+}
+
+/// Validates `keep` as distinct qubits of an `num_qubits` register and
+/// returns the complementary qubits in ascending order.
+fn traced_qubits(num_qubits: usize, keep: &[usize]) -> Result<Vec<usize>, QuantumError> {
+    let mut mask = 0usize;
+    for &qubit in keep {
+        if qubit >= num_qubits {
+            return Err(QuantumError::InvalidQubit { qubit, num_qubits });
+        }
+        if mask & (1 << qubit) != 0 {
+            return Err(QuantumError::DuplicateQubit { qubit });
+        }
+        mask |= 1 << qubit;
+    }
+    Ok((0..num_qubits).filter(|q| mask & (1 << q) == 0).collect())
+}
+
+/// Bit `qubits[k]` of `index` moved to bit `k`.
+fn gather_bits(index: usize, qubits: &[usize]) -> usize {
+    qubits
+        .iter()
+        .enumerate()
+        .fold(0usize, |acc, (k, &q)| acc | (((index >> q) & 1) << k))
+}
+
+/// Bit `k` of `value` moved to bit `qubits[k]`.
+fn scatter_bits(value: usize, qubits: &[usize]) -> usize {
+    qubits
+        .iter()
+        .enumerate()
+        .fold(0usize, |acc, (k, &q)| acc | (((value >> k) & 1) << q))
+}
+
+fn check_probability(name: &str, value: f64) -> Result<(), QuantumError> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(QuantumError::InvalidParameter(format!(
+            "{} probability must be in [0, 1], got {}",
+            name, value
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QuantumCircuit;
+
+    fn excited_population(rho: &DensityMatrix, qubit: usize) -> f64 {
+        rho.probabilities()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (i >> qubit) & 1 == 1)
+            .map(|(_, p)| p)
+            .sum()
+    }
+
+    #[test]
+    fn idle_qubit_decays_towards_zero() {
+        let durations = GateDurations {
+            single_qubit: 1.0,
+            two_qubit: 2.0,
+            multi_qubit: 4.0,
+        };
+        let t1 = 10.0;
+        let noise = IdleNoise::new(durations, t1, 2.0 * t1).unwrap();
+
+        // Qubit 0 is excited once, then waits while qubit 1 runs `layers` gates
+        let idle_population = |layers: usize| {
+            let mut circuit = QuantumCircuit::new(2);
+            circuit.x(0);
+            for _ in 0..layers {
+                circuit.x(1);
+            }
+            excited_population(&circuit.execute_with_idle_noise(&noise), 0)
+        };
+
+        let short = idle_population(5);
+        let long = idle_population(50);
+        assert!((short - (-4.0 / t1).exp()).abs() < 1e-10);
+        assert!((long - (-49.0 / t1).exp()).abs() < 1e-10);
+        assert!(long < 0.01 && long < short);
+    }
+
+    #[test]
+    fn two_qubit_only_noise_spares_single_qubit_gates() {
+        let noise = NoiseModel::new(0.0, 0.05).unwrap();
+        let mut rng = rand::thread_rng();
+
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).rx(1, 0.7).t(2).y(1).z(0);
+        let rho = circuit.execute_noisy(&noise, &mut rng);
+        assert!((rho.fidelity(&circuit.execute()) - 1.0).abs() < 1e-12);
+        assert!((rho.purity() - 1.0).abs() < 1e-12);
+
+        circuit.cnot(0, 1).unwrap();
+        let rho = circuit.execute_noisy(&noise, &mut rng);
+        assert!(rho.fidelity(&circuit.execute()) < 1.0 - 1e-3);
+        assert!(rho.purity() < 1.0 - 1e-3);
+    }
+
+    #[test]
+    fn empty_noise_model_matches_noiseless_density_matrix() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).ry(2, 0.6);
+        circuit.cnot(0, 1).unwrap();
+        circuit.cry(1, 2, 0.9).unwrap();
+
+        let noisy = circuit.execute_noisy(&NoiseModel::default(), &mut rand::thread_rng());
+        let noiseless = DensityMatrix::from_state(&circuit.execute());
+        assert!((noisy.matrix() - noiseless.matrix()).norm() < 1e-12);
+        assert_eq!(NoiseModel::new(0.0, 0.0).unwrap(), NoiseModel::noiseless());
+    }
+
+    #[test]
+    fn amplitude_damping_all_matches_sequential_channels() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).ry(1, 1.1).cnot(0, 2).unwrap().rx(2, 0.4);
+        let rho = DensityMatrix::from_state(&circuit.execute());
+
+        let mut batch = rho.clone();
+        batch.apply_amplitude_damping_all(0.3).unwrap();
+        let mut sequential = rho;
+        for qubit in 0..3 {
+            sequential.amplitude_damping(qubit, 0.3).unwrap();
+        }
+        assert!((batch.matrix() - sequential.matrix()).norm() < 1e-12);
+        assert!(batch.apply_amplitude_damping_all(1.5).is_err());
+    }
+
+    #[test]
+    fn composing_identity_with_channel_equals_channel() {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.h(0).cnot(0, 1).unwrap().ry(1, 0.6);
+        let rho = DensityMatrix::from_state(&circuit.execute());
+        let channels = [
+            NoiseChannel::Depolarizing(0.2),
+            NoiseChannel::AmplitudeDamping(0.35),
+            NoiseChannel::PhaseDamping(0.5),
+        ];
+
+        for channel in channels {
+            let mut expected = rho.clone();
+            Channel::apply(&channel, &mut expected, 1).unwrap();
+
+            let mut before = ComposedChannel::new();
+            before.then(ComposedChannel::new()).then(channel);
+            let mut after = ComposedChannel::new();
+            after.then(channel).then(NoiseChannel::Depolarizing(0.0));
+            for composed in [before, after] {
+                let mut actual = rho.clone();
+                composed.apply(&mut actual, 1).unwrap();
+                assert!((actual.matrix() - expected.matrix()).norm() < 1e-12, "{:?}", composed);
+            }
+        }
+    }
+}
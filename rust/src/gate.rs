@@ -0,0 +1,227 @@
+use crate::{is_unitary, QuantumError, QuantumState, DEFAULT_TOL};
+use nalgebra::DMatrix;
+use num_complex::Complex64;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A user-defined gate, appended with `QuantumCircuit::custom_gate` (or by
+/// name through a `GateRegistry`) and then simulated, inverted, exported
+/// and hashed like the built-in operations. Only `matrix` is required; a
+/// gate with structure to exploit can also override `apply`.
+pub trait Gate: fmt::Debug + Send + Sync {
+    /// Name the gate is registered and looked up under.
+    fn name(&self) -> &str;
+
+    fn num_qubits(&self) -> usize;
+
+    /// The `2^k x 2^k` unitary, with the first qubit the gate is applied to
+    /// as the low bit of the local index (as in `QuantumState::apply_unitary`).
+    fn matrix(&self) -> DMatrix<Complex64>;
+
+    /// Applies the gate to `qubits` of `state`, which the circuit has already
+    /// checked are distinct, in range and `num_qubits` long. Defaults to
+    /// `QuantumState::apply_unitary` with `matrix`; override it with a kernel
+    /// built from `QuantumState`'s own (diagonal phases, controlled gates,
+    /// ...) to skip the dense gather. Inverses always use the adjoint of
+    /// `matrix`.
+    fn apply(&self, state: &mut QuantumState, qubits: &[usize]) {
+        state
+            .apply_unitary(&self.matrix(), qubits)
+            .expect("custom gate qubits are validated when the gate is added");
+    }
+}
+
+/// Custom gates by case-insensitive name, so they can be defined once and
+/// reused across circuits (`QuantumCircuit::registered_gate`) and gate lists
+/// (`QuantumCircuit::from_gate_list_with_registry`).
+#[derive(Debug, Clone, Default)]
+pub struct GateRegistry {
+    gates: HashMap<String, Arc<dyn Gate>>,
+}
+
+impl GateRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `gate` under its name. Fails if the name is empty or taken, or
+    /// if the gate acts on no qubits or its matrix is not a unitary of the
+    /// matching size.
+    pub fn register(&mut self, gate: impl Gate + 'static) -> Result<&mut Self, QuantumError> {
+        let name = gate.name().to_ascii_lowercase();
+        if name.is_empty() {
+            return Err(QuantumError::InvalidParameter("gate name must not be empty".to_string()));
+        }
+        if self.gates.contains_key(&name) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "gate '{}' is already registered",
+                name
+            )));
+        }
+        validate_gate(&gate)?;
+        self.gates.insert(name, Arc::new(gate));
+        Ok(self)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Gate>> {
+        self.gates.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    /// Registered names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.gates.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+/// Checks that `gate` acts on at least one qubit and that its matrix is a
+/// unitary of size `2^k`.
+pub(crate) fn validate_gate(gate: &dyn Gate) -> Result<(), QuantumError> {
+    let num_qubits = gate.num_qubits();
+    if num_qubits == 0 {
+        return Err(QuantumError::InvalidParameter(format!(
+            "gate '{}' must act on at least one qubit",
+            gate.name()
+        )));
+    }
+    let matrix = gate.matrix();
+    let dimension = 1usize << num_qubits;
+    if matrix.shape() != (dimension, dimension) {
+        return Err(QuantumError::InvalidParameter(format!(
+            "{}-qubit gate '{}' must be {}x{}, got {}x{}",
+            num_qubits,
+            gate.name(),
+            dimension,
+            dimension,
+            matrix.nrows(),
+            matrix.ncols()
+        )));
+    }
+    if !is_unitary(&matrix, DEFAULT_TOL) {
+        return Err(QuantumError::InvalidParameter(format!(
+            "matrix of gate '{}' is not unitary",
+            gate.name()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Gates, NaiveSimulator, QuantumCircuit};
+
+    #[derive(Debug)]
+    struct FixedGate {
+        name: &'static str,
+        matrix: DMatrix<Complex64>,
+    }
+
+    impl Gate for FixedGate {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn num_qubits(&self) -> usize {
+            self.matrix.nrows().trailing_zeros() as usize
+        }
+
+        fn matrix(&self) -> DMatrix<Complex64> {
+            self.matrix.clone()
+        }
+    }
+
+    /// A two-qubit unitary that is neither symmetric under swapping its
+    /// qubits nor equal to its own adjoint.
+    fn entangler() -> DMatrix<Complex64> {
+        Gates::iswap() * Gates::u3(0.7, 0.2, -0.5).kronecker(&Gates::u3(1.3, -0.4, 0.9))
+    }
+
+    fn fixed(name: &'static str, matrix: DMatrix<Complex64>) -> FixedGate {
+        FixedGate { name, matrix }
+    }
+
+    fn close(a: &QuantumState, b: &QuantumState) -> bool {
+        (a.amplitudes() - b.amplitudes()).norm() < 1e-10
+    }
+
+    #[test]
+    fn registry_rejects_duplicate_names_and_invalid_matrices() {
+        let mut registry = GateRegistry::new();
+        registry.register(fixed("Entangler", entangler())).unwrap();
+
+        assert!(registry.register(fixed("ENTANGLER", Gates::iswap())).is_err());
+        let shear = DMatrix::from_row_slice(
+            2,
+            2,
+            &[1.0, 1.0, 0.0, 1.0].map(|entry| Complex64::new(entry, 0.0)),
+        );
+        assert!(registry.register(fixed("shear", shear)).is_err());
+        assert!(registry.register(fixed("odd", DMatrix::identity(3, 3))).is_err());
+        assert!(registry.register(fixed("", Gates::hadamard())).is_err());
+
+        assert_eq!(registry.names(), vec!["entangler"]);
+        assert_eq!(registry.get("eNtAnGlEr").unwrap().matrix(), entangler());
+        assert!(registry.get("shear").is_none());
+    }
+
+    #[test]
+    fn registered_gates_resolve_by_name_in_any_case() {
+        let mut registry = GateRegistry::new();
+        registry.register(fixed("Entangler", entangler())).unwrap();
+
+        let listed = QuantumCircuit::from_gate_list_with_registry(
+            3,
+            &[
+                ("h".to_string(), vec![1], vec![]),
+                ("ENTANGLER".to_string(), vec![2, 0], vec![]),
+            ],
+            &registry,
+        )
+        .unwrap();
+        let mut built = QuantumCircuit::new(3);
+        built.h(1).registered_gate(&registry, "entangler", &[2, 0]).unwrap();
+        assert_eq!(listed.structural_hash(), built.structural_hash());
+        assert!(close(&listed.execute(), &built.execute()));
+
+        let gate_list = |name: &str, params: Vec<f64>| vec![(name.to_string(), vec![2, 0], params)];
+        assert!(QuantumCircuit::from_gate_list_with_registry(3, &gate_list("entangler", vec![0.1]), &registry).is_err());
+        assert!(QuantumCircuit::from_gate_list(3, &gate_list("entangler", vec![])).is_err());
+        assert!(built.registered_gate(&registry, "missing", &[0, 1]).is_err());
+        assert!(built.registered_gate(&registry, "entangler", &[1]).is_err());
+        assert!(built.registered_gate(&registry, "entangler", &[1, 1]).is_err());
+    }
+
+    #[test]
+    fn custom_gate_executes_inverts_and_hashes_like_apply_unitary() {
+        let gate: Arc<dyn Gate> = Arc::new(fixed("entangler", entangler()));
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).ry(1, 0.8).rx(2, 0.3);
+        let prepared = circuit.execute();
+        circuit.custom_gate(gate.clone(), &[2, 0]).unwrap();
+
+        let mut expected = prepared.clone();
+        expected.apply_unitary(&entangler(), &[2, 0]).unwrap();
+        let state = circuit.execute();
+        assert!(close(&state, &expected));
+        assert!(close(&NaiveSimulator::execute(&circuit), &expected));
+        assert!(close(&circuit.execute_inverse_from(state), &QuantumState::new(3)));
+
+        let rebuild = |name: &'static str, qubits: &[usize]| {
+            let mut other = QuantumCircuit::new(3);
+            other.h(0).ry(1, 0.8).rx(2, 0.3);
+            other.custom_gate(Arc::new(fixed(name, entangler())), qubits).unwrap();
+            other.structural_hash()
+        };
+        assert_eq!(rebuild("entangler", &[2, 0]), circuit.structural_hash());
+        assert_ne!(rebuild("entangler", &[0, 2]), circuit.structural_hash());
+        assert_ne!(rebuild("other", &[2, 0]), circuit.structural_hash());
+
+        assert!(circuit.custom_gate(gate.clone(), &[0]).is_err());
+        assert!(circuit.custom_gate(gate, &[0, 3]).is_err());
+        let scaled: Arc<dyn Gate> = Arc::new(fixed("scaled", Gates::hadamard() * Complex64::new(2.0, 0.0)));
+        assert!(circuit.custom_gate(scaled, &[0]).is_err());
+    }
+}
@@ -0,0 +1,562 @@
+use crate::{QuantumCircuit, QuantumError, QuantumState};
+use nalgebra::{DMatrix, Dyn, SymmetricEigen};
+use num_complex::Complex64;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+
+/// Largest register the spectral methods (`spectral_gap`, `ground_state`,
+/// `eigenstate_overlaps`) will diagonalise densely.
+pub const MAX_DIAGONALIZATION_QUBITS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pauli {
+    X,
+    Y,
+    Z,
+}
+
+/// A weighted Pauli string, e.g. `0.5 * X0 Z2`. Qubits not listed carry the identity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PauliTerm {
+    pub coefficient: f64,
+    pub operators: Vec<(usize, Pauli)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hamiltonian {
+    pub terms: Vec<PauliTerm>,
+}
+
+impl Hamiltonian {
+    pub fn new() -> Self {
+        Hamiltonian { terms: Vec::new() }
+    }
+
+    pub fn add_term(&mut self, coefficient: f64, operators: &[(usize, Pauli)]) -> &mut Self {
+        self.terms.push(PauliTerm {
+            coefficient,
+            operators: operators.to_vec(),
+        });
+        self
+    }
+
+    /// Nearest-neighbour Heisenberg chain `J Σ (X_i X_i+1 + Y_i Y_i+1 + Z_i Z_i+1)`
+    /// with open boundaries.
+    pub fn heisenberg_chain(num_qubits: usize, coupling: f64) -> Self {
+        let mut hamiltonian = Hamiltonian::new();
+        for i in 0..num_qubits.saturating_sub(1) {
+            for pauli in [Pauli::X, Pauli::Y, Pauli::Z] {
+                hamiltonian.add_term(coupling, &[(i, pauli), (i + 1, pauli)]);
+            }
+        }
+        hamiltonian
+    }
+
+    /// Exact `⟨ψ|H|ψ⟩`, summing `QuantumState::expectation_pauli` over the
+    /// terms.
+    pub fn expectation(&self, state: &QuantumState) -> Result<f64, QuantumError> {
+        self.check_qubits(state.num_qubits())?;
+
+        let mut energy = 0.0;
+        for term in &self.terms {
+            energy += term.coefficient * state.expectation_pauli(&term.operators)?;
+        }
+        Ok(energy)
+    }
+
+    /// Partitions the non-identity terms (by index) into qubit-wise commuting
+    /// groups: within a group every qubit is acted on by at most one Pauli
+    /// type, so one measurement basis serves the whole group. Greedy
+    /// first-fit in term order; not guaranteed to be minimal.
+    pub fn qubit_wise_commuting_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(Vec<usize>, HashMap<usize, Pauli>)> = Vec::new();
+
+        for (index, term) in self.terms.iter().enumerate() {
+            if term.operators.is_empty() {
+                continue;
+            }
+
+            let fits = |basis: &HashMap<usize, Pauli>| {
+                term.operators
+                    .iter()
+                    .all(|(qubit, pauli)| basis.get(qubit).is_none_or(|existing| existing == pauli))
+            };
+            match groups.iter_mut().find(|(_, basis)| fits(basis)) {
+                Some((members, basis)) => {
+                    members.push(index);
+                    basis.extend(term.operators.iter().copied());
+                }
+                None => groups.push((vec![index], term.operators.iter().copied().collect())),
+            }
+        }
+
+        groups.into_iter().map(|(members, _)| members).collect()
+    }
+
+    /// Dense `2^n x 2^n` matrix of the Hamiltonian in the computational basis.
+    pub fn to_matrix(&self, num_qubits: usize) -> Result<DMatrix<Complex64>, QuantumError> {
+        self.check_qubits(num_qubits)?;
+
+        let dim = 1usize << num_qubits;
+        let mut matrix = DMatrix::from_element(dim, dim, Complex64::new(0.0, 0.0));
+        for term in &self.terms {
+            for column in 0..dim {
+                let mut row = column;
+                let mut phase = Complex64::new(term.coefficient, 0.0);
+                for &(qubit, pauli) in &term.operators {
+                    let bit = (column >> qubit) & 1;
+                    match pauli {
+                        Pauli::X => row ^= 1 << qubit,
+                        Pauli::Y => {
+                            row ^= 1 << qubit;
+                            phase *= if bit == 0 {
+                                Complex64::new(0.0, 1.0)
+                            } else {
+                                Complex64::new(0.0, -1.0)
+                            };
+                        }
+                        Pauli::Z => {
+                            if bit == 1 {
+                                phase = -phase;
+                            }
+                        }
+                    }
+                }
+                matrix[(row, column)] += phase;
+            }
+        }
+
+        Ok(matrix)
+    }
+
+    /// Gap between the two lowest eigenvalues of the dense Hamiltonian. This
+    /// bounds how fast an adiabatic sweep ending at `H` may run; a degenerate
+    /// ground state gives `0.0`.
+    pub fn spectral_gap(&self, num_qubits: usize) -> Result<f64, QuantumError> {
+        let eigen = self.diagonalize(num_qubits)?;
+        let mut energies: Vec<f64> = eigen.eigenvalues.iter().copied().collect();
+        energies.sort_by(f64::total_cmp);
+        Ok(energies[1] - energies[0])
+    }
+
+    /// Lowest eigenvalue and a matching eigenvector of the dense Hamiltonian.
+    /// With a degenerate ground state the vector is an arbitrary member of
+    /// the ground space.
+    pub fn ground_state(&self, num_qubits: usize) -> Result<(f64, QuantumState), QuantumError> {
+        let eigen = self.diagonalize(num_qubits)?;
+        let lowest = eigen.eigenvalues.imin();
+        let state = QuantumState::from_amplitudes(eigen.eigenvectors.column(lowest).into_owned())?;
+        Ok((eigen.eigenvalues[lowest], state))
+    }
+
+    /// Populations `|⟨E_k|ψ⟩|²` of `state` in each eigenstate, ordered by
+    /// ascending energy. Within a degenerate eigenspace the split between
+    /// members depends on the basis the solver picks; only their sum is
+    /// meaningful.
+    pub fn eigenstate_overlaps(
+        &self,
+        state: &QuantumState,
+        num_qubits: usize,
+    ) -> Result<Vec<f64>, QuantumError> {
+        if state.num_qubits() != num_qubits {
+            return Err(QuantumError::InvalidState(format!(
+                "state has {} qubits, expected {}",
+                state.num_qubits(),
+                num_qubits
+            )));
+        }
+
+        let eigen = self.diagonalize(num_qubits)?;
+        let mut order: Vec<usize> = (0..eigen.eigenvalues.len()).collect();
+        order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+
+        Ok(order
+            .into_iter()
+            .map(|k| eigen.eigenvectors.column(k).dotc(state.amplitudes()).norm_sqr())
+            .collect())
+    }
+
+    /// Reference evolution `exp(-i H time) |state⟩` computed by diagonalising
+    /// the dense Hamiltonian. Only practical for small registers.
+    pub fn evolve_exact(&self, state: &QuantumState, time: f64) -> Result<QuantumState, QuantumError> {
+        let matrix = self.to_matrix(state.num_qubits())?;
+        let eigen = matrix.symmetric_eigen();
+
+        let mut coefficients = eigen.eigenvectors.adjoint() * state.amplitudes();
+        for (k, coefficient) in coefficients.iter_mut().enumerate() {
+            *coefficient *= Complex64::from_polar(1.0, -eigen.eigenvalues[k] * time);
+        }
+
+        QuantumState::from_amplitudes(&eigen.eigenvectors * coefficients)
+    }
+
+    /// Shots needed to estimate the energy to within `±epsilon` (one standard
+    /// deviation).
+    ///
+    /// Every Pauli term has outcomes in `[-1, 1]`, so a single-shot estimate of
+    /// `Σ c_i ⟨P_i⟩` (sampling term `i` with probability `|c_i| / λ` and
+    /// reporting `λ sign(c_i)` times the outcome) has variance at most
+    /// `λ² = (Σ |c_i|)²`. Averaging `N` shots gives `λ² / N <= ε²`, i.e.
+    /// `N = ⌈λ² / ε²⌉`. Identity terms are constants and need no shots.
+    /// A non-positive `epsilon` yields `usize::MAX`.
+    pub fn shots_for_precision(&self, epsilon: f64) -> usize {
+        if epsilon <= 0.0 {
+            return usize::MAX;
+        }
+
+        let weight: f64 = self
+            .terms
+            .iter()
+            .filter(|term| !term.operators.is_empty())
+            .map(|term| term.coefficient.abs())
+            .sum();
+
+        (weight * weight / (epsilon * epsilon)).ceil() as usize
+    }
+
+    /// Builds a circuit approximating `exp(-i H time)` with `steps` Trotter steps.
+    ///
+    /// `order` selects the product formula. Order 1 is the Lie-Trotter product,
+    /// order 2 the symmetric Strang splitting, and every higher even order is
+    /// built with Suzuki's recursive fractal construction
+    /// `S_2k(t) = S_2k-2(p t)^2 S_2k-2((1 - 4p) t) S_2k-2(p t)^2`,
+    /// `p = 1 / (4 - 4^(1/(2k-1)))`. For `m` terms a single step uses
+    /// `m` exponentials at order 1, `2m` at order 2 and `2 * 5^(k-1) * m` at
+    /// order `2k`, so each extra order costs five times as many gates.
+    pub fn trotter_circuit(
+        &self,
+        num_qubits: usize,
+        time: f64,
+        steps: usize,
+        order: usize,
+    ) -> Result<QuantumCircuit, QuantumError> {
+        if order == 0 || (order > 1 && !order.is_multiple_of(2)) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "Trotter order must be 1 or even, got {}",
+                order
+            )));
+        }
+        if steps == 0 {
+            return Err(QuantumError::InvalidParameter(
+                "Trotter evolution needs at least one step".to_string(),
+            ));
+        }
+        self.check_qubits(num_qubits)?;
+        for term in &self.terms {
+            let mut seen = HashSet::new();
+            if let Some(&(qubit, _)) = term.operators.iter().find(|&&(qubit, _)| !seen.insert(qubit)) {
+                return Err(QuantumError::DuplicateQubit { qubit });
+            }
+        }
+
+        let dt = time / steps as f64;
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        for _ in 0..steps {
+            self.append_product_formula(&mut circuit, dt, order);
+        }
+        Ok(circuit)
+    }
+
+    fn diagonalize(
+        &self,
+        num_qubits: usize,
+    ) -> Result<SymmetricEigen<Complex64, Dyn>, QuantumError> {
+        if num_qubits == 0 || num_qubits > MAX_DIAGONALIZATION_QUBITS {
+            return Err(QuantumError::InvalidParameter(format!(
+                "diagonalisation needs 1..={} qubits, got {}",
+                MAX_DIAGONALIZATION_QUBITS, num_qubits
+            )));
+        }
+        Ok(self.to_matrix(num_qubits)?.symmetric_eigen())
+    }
+
+    fn check_qubits(&self, num_qubits: usize) -> Result<(), QuantumError> {
+        for term in &self.terms {
+            for &(qubit, _) in &term.operators {
+                if qubit >= num_qubits {
+                    return Err(QuantumError::InvalidQubit { qubit, num_qubits });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn append_product_formula(&self, circuit: &mut QuantumCircuit, dt: f64, order: usize) {
+        match order {
+            1 => {
+                for term in &self.terms {
+                    append_pauli_exponential(circuit, term, dt);
+                }
+            }
+            2 => {
+                for term in &self.terms {
+                    append_pauli_exponential(circuit, term, dt / 2.0);
+                }
+                for term in self.terms.iter().rev() {
+                    append_pauli_exponential(circuit, term, dt / 2.0);
+                }
+            }
+            _ => {
+                let k = (order / 2) as f64;
+                let p = 1.0 / (4.0 - 4.0_f64.powf(1.0 / (2.0 * k - 1.0)));
+                for _ in 0..2 {
+                    self.append_product_formula(circuit, p * dt, order - 2);
+                }
+                self.append_product_formula(circuit, (1.0 - 4.0 * p) * dt, order - 2);
+                for _ in 0..2 {
+                    self.append_product_formula(circuit, p * dt, order - 2);
+                }
+            }
+        }
+    }
+}
+
+/// Molecular Hamiltonians for VQE-style benchmarks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Molecule {
+    /// H2 in STO-3G at bond length 0.74 Å, Jordan-Wigner encoded on 4
+    /// qubits (15 terms, ground energy ≈ -1.1373 Ha). Tabulated; equal to
+    /// `molecules::h2_hamiltonian(0.74)`.
+    H2,
+    /// LiH in STO-3G from `molecules::lih_hamiltonian` on 12 qubits (631
+    /// terms, ground energy ≈ -7.8824 Ha). Dense diagonalisation at this
+    /// size takes minutes.
+    LiH,
+}
+
+impl Molecule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Molecule::H2 => "H2",
+            Molecule::LiH => "LiH",
+        }
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        match self {
+            Molecule::H2 => 4,
+            Molecule::LiH => 12,
+        }
+    }
+
+    pub fn hamiltonian(&self) -> Hamiltonian {
+        match self {
+            Molecule::H2 => {
+                use Pauli::{X, Y, Z};
+                let mut h = Hamiltonian::new();
+                h.add_term(-0.09706626816762845, &[])
+                    .add_term(0.17141282644776884, &[(0, Z)])
+                    .add_term(0.17141282644776884, &[(1, Z)])
+                    .add_term(-0.22343153690813597, &[(2, Z)])
+                    .add_term(-0.22343153690813597, &[(3, Z)])
+                    .add_term(0.16868898170361207, &[(0, Z), (1, Z)])
+                    .add_term(0.12062523483390426, &[(0, Z), (2, Z)])
+                    .add_term(0.16592785033770355, &[(0, Z), (3, Z)])
+                    .add_term(0.16592785033770355, &[(1, Z), (2, Z)])
+                    .add_term(0.12062523483390426, &[(1, Z), (3, Z)])
+                    .add_term(0.17441287612261608, &[(2, Z), (3, Z)])
+                    .add_term(-0.04530261550379928, &[(0, X), (1, X), (2, Y), (3, Y)])
+                    .add_term(0.04530261550379928, &[(0, X), (1, Y), (2, Y), (3, X)])
+                    .add_term(0.04530261550379928, &[(0, Y), (1, X), (2, X), (3, Y)])
+                    .add_term(-0.04530261550379928, &[(0, Y), (1, Y), (2, X), (3, X)]);
+                h
+            }
+            Molecule::LiH => crate::molecules::lih_hamiltonian(),
+        }
+    }
+}
+
+/// Appends `exp(-i c dt P)` for the term `c P`: rotate every factor into the Z
+/// basis, accumulate the parity onto the last qubit with a CNOT ladder, apply
+/// the RZ and undo the ladder. Two-qubit `XX`, `YY` and `ZZ` terms use the
+/// native RXX/RYY/RZZ instead. Identity terms become the circuit's global
+/// phase `e^(-i c dt)`.
+fn append_pauli_exponential(circuit: &mut QuantumCircuit, term: &PauliTerm, dt: f64) {
+    let Some(&(last, _)) = term.operators.last() else {
+        circuit.add_global_phase(-term.coefficient * dt);
+        return;
+    };
+
+    // XX, YY and ZZ couplings are single native rotations
+    if let [(a, first), (b, second)] = term.operators[..] {
+        if first == second {
+            let theta = 2.0 * term.coefficient * dt;
+            match first {
+                Pauli::X => circuit.rxx(a, b, theta),
+                Pauli::Y => circuit.ryy(a, b, theta),
+                Pauli::Z => circuit.rzz(a, b, theta),
+            }
+            .expect("trotter_circuit rejects repeated qubits");
+            return;
+        }
+    }
+
+    for &(qubit, pauli) in &term.operators {
+        match pauli {
+            Pauli::X => {
+                circuit.h(qubit);
+            }
+            Pauli::Y => {
+                circuit.rx(qubit, PI / 2.0);
+            }
+            Pauli::Z => {}
+        }
+    }
+
+    for pair in term.operators.windows(2) {
+        circuit
+            .cnot(pair[0].0, pair[1].0)
+            .expect("trotter_circuit rejects repeated qubits");
+    }
+    circuit.rz(last, 2.0 * term.coefficient * dt);
+    for pair in term.operators.windows(2).rev() {
+        circuit
+            .cnot(pair[0].0, pair[1].0)
+            .expect("trotter_circuit rejects repeated qubits");
+    }
+
+    for &(qubit, pauli) in &term.operators {
+        match pauli {
+            Pauli::X => {
+                circuit.h(qubit);
+            }
+            Pauli::Y => {
+                circuit.rx(qubit, -PI / 2.0);
+            }
+            Pauli::Z => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gates;
+    use nalgebra::{DMatrix, DVector};
+    use num_complex::Complex64;
+
+    /// Dense `H`, with qubit `q` as bit `q` of the basis index.
+    fn dense_hamiltonian(hamiltonian: &Hamiltonian, num_qubits: usize) -> DMatrix<Complex64> {
+        let dimension = 1 << num_qubits;
+        let mut matrix = DMatrix::zeros(dimension, dimension);
+        for term in &hamiltonian.terms {
+            let mut product = DMatrix::identity(1, 1);
+            for qubit in (0..num_qubits).rev() {
+                let factor = match term.operators.iter().find(|&&(q, _)| q == qubit) {
+                    Some((_, Pauli::X)) => Gates::pauli_x(),
+                    Some((_, Pauli::Y)) => Gates::pauli_y(),
+                    Some((_, Pauli::Z)) => Gates::pauli_z(),
+                    None => DMatrix::identity(2, 2),
+                };
+                product = product.kronecker(&factor);
+            }
+            matrix += product * Complex64::new(term.coefficient, 0.0);
+        }
+        matrix
+    }
+
+    fn trotter_infidelity(hamiltonian: &Hamiltonian, num_qubits: usize, order: usize) -> f64 {
+        let time = 1.0;
+        let mut prep = QuantumCircuit::new(num_qubits);
+        prep.h(0).x(1).ry(2, 0.4);
+        let initial = prep.execute();
+
+        let eigen = dense_hamiltonian(hamiltonian, num_qubits).symmetric_eigen();
+        let phases = DVector::from_iterator(
+            eigen.eigenvalues.len(),
+            eigen.eigenvalues.iter().map(|&energy| Complex64::from_polar(1.0, -energy * time)),
+        );
+        let propagator =
+            &eigen.eigenvectors * DMatrix::from_diagonal(&phases) * eigen.eigenvectors.adjoint();
+        let exact = propagator * initial.amplitudes();
+
+        let trotter = hamiltonian
+            .trotter_circuit(num_qubits, time, 2, order)
+            .unwrap()
+            .execute_from(initial);
+        1.0 - trotter.amplitudes().dotc(&exact).norm_sqr()
+    }
+
+    #[test]
+    fn fourth_order_trotter_beats_second_order() {
+        let mut hamiltonian = Hamiltonian::new();
+        for i in 0..2 {
+            for pauli in [Pauli::X, Pauli::Y, Pauli::Z] {
+                hamiltonian.add_term(1.0, &[(i, pauli), (i + 1, pauli)]);
+            }
+        }
+        hamiltonian.add_term(0.7, &[(0, Pauli::X)]).add_term(0.3, &[(2, Pauli::Z)]);
+
+        let second = trotter_infidelity(&hamiltonian, 3, 2);
+        let fourth = trotter_infidelity(&hamiltonian, 3, 4);
+        assert!(second > 1e-8, "second-order error {} is too small to compare", second);
+        assert!(fourth < second, "fourth {} >= second {}", fourth, second);
+    }
+
+    #[test]
+    fn halving_epsilon_quadruples_shots() {
+        let mut hamiltonian = Hamiltonian::heisenberg_chain(3, 0.8);
+        hamiltonian.add_term(-0.4, &[(1, Pauli::Z)]);
+        for epsilon in [0.1, 0.01, 1.6e-3] {
+            let shots = hamiltonian.shots_for_precision(epsilon) as f64;
+            let halved = hamiltonian.shots_for_precision(epsilon / 2.0) as f64;
+            // The ceiling can add at most one shot to either estimate
+            assert!((halved - 4.0 * shots).abs() <= 4.0, "{} vs {}", halved, shots);
+        }
+        assert_eq!(hamiltonian.shots_for_precision(0.0), usize::MAX);
+    }
+
+    #[test]
+    fn spectral_gap_of_a_two_level_splitting() {
+        let mut hamiltonian = Hamiltonian::new();
+        hamiltonian.add_term(0.3, &[(0, Pauli::Z)]).add_term(0.4, &[(0, Pauli::X)]);
+        // Eigenvalues ±√(0.3² + 0.4²) = ±0.5
+        assert!((hamiltonian.spectral_gap(1).unwrap() - 1.0).abs() < 1e-12);
+
+        // An idle second qubit doubles every level, closing the gap
+        assert!(hamiltonian.spectral_gap(2).unwrap().abs() < 1e-12);
+
+        assert!(hamiltonian.spectral_gap(0).is_err());
+        assert!(hamiltonian.spectral_gap(MAX_DIAGONALIZATION_QUBITS + 1).is_err());
+    }
+
+    #[test]
+    fn eigenstates_overlap_only_with_themselves() {
+        // Basis states 3, 2, 1, 0 have energies -3, -1, 1, 3
+        let mut diagonal = Hamiltonian::new();
+        diagonal.add_term(1.0, &[(0, Pauli::Z)]).add_term(2.0, &[(1, Pauli::Z)]);
+        let mut prep = QuantumCircuit::new(2);
+        prep.x(0);
+        let overlaps = diagonal.eigenstate_overlaps(&prep.execute(), 2).unwrap();
+        for (k, expected) in [0.0, 0.0, 1.0, 0.0].iter().enumerate() {
+            assert!((overlaps[k] - expected).abs() < 1e-12, "{:?}", overlaps);
+        }
+
+        let h2 = Molecule::H2.hamiltonian();
+        let (_, ground) = h2.ground_state(4).unwrap();
+        let overlaps = h2.eigenstate_overlaps(&ground, 4).unwrap();
+        assert!((overlaps[0] - 1.0).abs() < 1e-10);
+        assert!(overlaps[1..].iter().all(|&p| p < 1e-10));
+
+        assert!(h2.eigenstate_overlaps(&ground, 3).is_err());
+    }
+
+    #[test]
+    fn identity_terms_become_the_trotter_global_phase() {
+        let mut hamiltonian = Hamiltonian::new();
+        hamiltonian
+            .add_term(0.7, &[])
+            .add_term(0.4, &[(0, Pauli::Z), (1, Pauli::Z)])
+            .add_term(-0.3, &[(2, Pauli::Z)]);
+        let time = 1.5;
+        let circuit = hamiltonian.trotter_circuit(3, time, 3, 1).unwrap();
+        assert!((circuit.global_phase() - (-0.7 * time).rem_euclid(2.0 * PI)).abs() < 1e-12);
+
+        // The terms commute, so the product formula is exact, phase included
+        let mut prep = QuantumCircuit::new(3);
+        prep.h(0).h(1).ry(2, 0.9);
+        let initial = prep.execute();
+        let exact = hamiltonian.evolve_exact(&initial, time).unwrap();
+        assert!(circuit.execute_from(initial).approx_eq(&exact, 1e-10));
+    }
+}
@@ -0,0 +1,5031 @@
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex64;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use rand::Rng;
+use rayon::prelude::*;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, OnceLock};
+
+pub mod benchmarks;
+pub mod density;
+pub mod gate;
+pub mod hamiltonian;
+pub mod molecules;
+pub mod naive;
+pub mod real;
+pub mod synthesis;
+pub mod vqe;
+
+pub use density::{Channel, ComposedChannel, DensityMatrix, GateDurations, IdleNoise, NoiseChannel, NoiseModel};
+pub use gate::{Gate, GateRegistry};
+pub use hamiltonian::{Hamiltonian, Molecule, Pauli, PauliTerm, MAX_DIAGONALIZATION_QUBITS};
+pub use naive::NaiveSimulator;
+pub use real::RealStateVector;
+pub use vqe::{Optimizer, VqeOptions, VqeResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantumError {
+    InvalidQubit { qubit: usize, num_qubits: usize },
+    DuplicateQubit { qubit: usize },
+    InvalidState(String),
+    InvalidParameter(String),
+}
+
+impl fmt::Display for QuantumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantumError::InvalidQubit { qubit, num_qubits } => {
+                write!(f, "qubit {} out of range for {}-qubit register", qubit, num_qubits)
+            }
+            QuantumError::DuplicateQubit { qubit } => {
+                write!(f, "qubit {} used more than once in one operation", qubit)
+            }
+            QuantumError::InvalidState(msg) => write!(f, "invalid state: {}", msg),
+            QuantumError::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for QuantumError {}
+
+/// Default absolute tolerance of the crate's numerical comparisons and
+/// validation (`approx_eq`, `is_unitary`, normalisation checks). APIs that
+/// compare floats take a tolerance argument or have a `_with_tolerance`
+/// variant for tightening or loosening it.
+pub const DEFAULT_TOL: f64 = 1e-10;
+
+/// Largest register for which `max_entanglement_bipartition` tries every cut.
+pub const EXHAUSTIVE_BIPARTITION_QUBITS: usize = 12;
+
+/// Amplitudes per rayon task in `QuantumState::expectation_pauli`. States
+/// no larger than one chunk are reduced serially.
+pub const EXPECTATION_CHUNK: usize = 1 << 14;
+
+/// Magnitude at or below which `QuantumState::support_size` treats an
+/// amplitude as zero, absorbing rounding left by interfering paths.
+pub const NONZERO_AMPLITUDE_TOLERANCE: f64 = 1e-12;
+
+/// Largest register for which `QuantumCircuit::cache_unitary` will keep the
+/// full `2^n x 2^n` unitary (16 MiB at 10 qubits).
+pub const MAX_CACHED_UNITARY_QUBITS: usize = 10;
+
+/// Amplitudes encoded per write by `QuantumState::write_amplitudes`.
+const AMPLITUDE_CHUNK: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct QuantumState {
+    amplitudes: DVector<Complex64>,
+    num_qubits: usize,
+    /// Sum of the angles passed to `apply_global_phase`, modulo 2π.
+    global_phase: f64,
+}
+
+impl QuantumState {
+    /// Creates the computational basis state |00...0⟩.
+    pub fn new(num_qubits: usize) -> Self {
+        let size = 1usize << num_qubits;
+        let mut amplitudes = DVector::from_element(size, Complex64::new(0.0, 0.0));
+        amplitudes[0] = Complex64::new(1.0, 0.0);
+
+        QuantumState {
+            amplitudes,
+            num_qubits,
+            global_phase: 0.0,
+        }
+    }
+
+    /// Wraps an existing amplitude vector, which must have a power-of-two
+    /// length and unit norm (to `DEFAULT_TOL`).
+    pub fn from_amplitudes(amplitudes: DVector<Complex64>) -> Result<Self, QuantumError> {
+        Self::from_amplitudes_with_tolerance(amplitudes, DEFAULT_TOL)
+    }
+
+    /// Like `from_amplitudes`, accepting norms within `tolerance` of 1.
+    pub fn from_amplitudes_with_tolerance(
+        amplitudes: DVector<Complex64>,
+        tolerance: f64,
+    ) -> Result<Self, QuantumError> {
+        let size = amplitudes.len();
+        if size == 0 || !size.is_power_of_two() {
+            return Err(QuantumError::InvalidState(format!(
+                "amplitude vector length {} is not a power of two",
+                size
+            )));
+        }
+
+        let norm = amplitudes.norm();
+        if (norm - 1.0).abs() > tolerance {
+            return Err(QuantumError::InvalidState(format!(
+                "amplitude vector has norm {} (tolerance {:e})",
+                norm, tolerance
+            )));
+        }
+
+        Ok(QuantumState {
+            amplitudes,
+            num_qubits: size.trailing_zeros() as usize,
+            global_phase: 0.0,
+        })
+    }
+
+    /// Builds a state from a closure over basis indices, normalising the
+    /// result. Fails if every amplitude is zero.
+    pub fn from_fn(num_qubits: usize, f: impl Fn(usize) -> Complex64) -> Result<Self, QuantumError> {
+        let amplitudes = DVector::from_fn(1usize << num_qubits, |i, _| f(i));
+
+        let norm = amplitudes.norm();
+        if norm == 0.0 || !norm.is_finite() {
+            return Err(QuantumError::InvalidState(format!(
+                "cannot normalise amplitudes with norm {}",
+                norm
+            )));
+        }
+
+        Ok(QuantumState {
+            amplitudes: amplitudes / Complex64::new(norm, 0.0),
+            num_qubits,
+            global_phase: 0.0,
+        })
+    }
+
+    pub fn apply_single_gate(&mut self, gate: &DMatrix<Complex64>, qubit: usize) {
+        let size = self.amplitudes.len();
+        let mask = 1usize << qubit;
+
+        for i in 0..size {
+            if i & mask == 0 {
+                let j = i | mask;
+                if j < size {
+                    let amp0 = self.amplitudes[i];
+                    let amp1 = self.amplitudes[j];
+
+                    self.amplitudes[i] = gate[(0, 0)] * amp0 + gate[(0, 1)] * amp1;
+                    self.amplitudes[j] = gate[(1, 0)] * amp0 + gate[(1, 1)] * amp1;
+                }
+            }
+        }
+    }
+
+    /// Applies a Hadamard to each of `qubits` as a fast Walsh-Hadamard
+    /// transform: unnormalised `(a + b, a - b)` butterflies per qubit and a
+    /// single `2^(-k/2)` rescale at the end, instead of `k` gate passes that
+    /// each round through `1/sqrt(2)`.
+    pub fn apply_hadamard_transform(&mut self, qubits: &[usize]) {
+        let size = self.amplitudes.len();
+        for &qubit in qubits {
+            let mask = 1usize << qubit;
+            for i in (0..size).filter(|i| i & mask == 0) {
+                let amp0 = self.amplitudes[i];
+                let amp1 = self.amplitudes[i | mask];
+                self.amplitudes[i] = amp0 + amp1;
+                self.amplitudes[i | mask] = amp0 - amp1;
+            }
+        }
+
+        let scale = 0.5f64.powf(qubits.len() as f64 / 2.0);
+        self.amplitudes.scale_mut(scale);
+    }
+
+    /// Multiplies every amplitude by `e^(iθ)` and adds `θ` to `global_phase`.
+    /// A zero angle is skipped.
+    pub fn apply_global_phase(&mut self, theta: f64) {
+        if theta != 0.0 {
+            let phase = Complex64::from_polar(1.0, theta);
+            self.amplitudes.iter_mut().for_each(|amplitude| *amplitude *= phase);
+            self.global_phase = (self.global_phase + theta).rem_euclid(2.0 * PI);
+        }
+    }
+
+    /// The phase `φ` in `[0, 2π)` applied through `apply_global_phase` so
+    /// far, e.g. by a circuit's `global_phase` on execution. The amplitudes
+    /// already include it; it is kept for comparing states up to phase.
+    pub fn global_phase(&self) -> f64 {
+        self.global_phase
+    }
+
+    /// Applies `gate` to `target` on the subspace where `control` is |1⟩.
+    ///
+    /// Rather than scanning all `2^n` indices and filtering on bits, the
+    /// `2^(n-2)` amplitude pairs are enumerated directly by inserting zero bits
+    /// at the lower and higher of the two positions. This treats a control
+    /// above or below the target identically, whatever qubits sit in between.
+    ///
+    /// Panics if `control == target` or either index is outside the register.
+    pub fn apply_controlled_gate(&mut self, gate: &DMatrix<Complex64>, control: usize, target: usize) {
+        self.apply_controlled_gate_with_value(gate, control, target, true);
+    }
+
+    /// Applies `gate` to `target` on the subspace where `control` reads
+    /// `control_value`, so `false` gives a gate controlled on |0⟩ without the
+    /// surrounding X gates.
+    ///
+    /// Panics if `control == target` or either index is outside the register.
+    pub fn apply_controlled_gate_with_value(
+        &mut self,
+        gate: &DMatrix<Complex64>,
+        control: usize,
+        target: usize,
+        control_value: bool,
+    ) {
+        assert_ne!(control, target, "control and target must be different qubits");
+        assert!(
+            control < self.num_qubits && target < self.num_qubits,
+            "controlled gate on ({}, {}) out of range for {}-qubit register",
+            control,
+            target,
+            self.num_qubits
+        );
+
+        let (low, high) = if control < target {
+            (control, target)
+        } else {
+            (target, control)
+        };
+        let control_mask = if control_value { 1usize << control } else { 0 };
+        let target_mask = 1usize << target;
+
+        for k in 0..(self.amplitudes.len() >> 2) {
+            let base = insert_zero_bit(insert_zero_bit(k, low), high);
+            let i = base | control_mask;
+            let j = i | target_mask;
+
+            let amp0 = self.amplitudes[i];
+            let amp1 = self.amplitudes[j];
+
+            self.amplitudes[i] = gate[(0, 0)] * amp0 + gate[(0, 1)] * amp1;
+            self.amplitudes[j] = gate[(1, 0)] * amp0 + gate[(1, 1)] * amp1;
+        }
+    }
+
+    /// Applies a controlled-Z, which is symmetric in its two qubits: every
+    /// amplitude with both bits set changes sign and nothing else moves.
+    ///
+    /// Panics if `qubit_a == qubit_b` or either index is outside the register.
+    pub fn apply_cz(&mut self, qubit_a: usize, qubit_b: usize) {
+        assert_ne!(qubit_a, qubit_b, "CZ needs two different qubits");
+        assert!(
+            qubit_a < self.num_qubits && qubit_b < self.num_qubits,
+            "CZ on ({}, {}) out of range for {}-qubit register",
+            qubit_a,
+            qubit_b,
+            self.num_qubits
+        );
+
+        let (low, high) = if qubit_a < qubit_b {
+            (qubit_a, qubit_b)
+        } else {
+            (qubit_b, qubit_a)
+        };
+        let both = (1usize << low) | (1usize << high);
+        for k in 0..(self.amplitudes.len() >> 2) {
+            let i = insert_zero_bit(insert_zero_bit(k, low), high) | both;
+            self.amplitudes[i] = -self.amplitudes[i];
+        }
+    }
+
+    /// Applies a controlled phase `diag(1, 1, 1, e^(iθ))`, which like CZ is
+    /// symmetric in its qubits: only the `2^(n-2)` amplitudes with both bits
+    /// set are touched, each with one complex multiply.
+    ///
+    /// Panics if `qubit_a == qubit_b` or either index is outside the register.
+    pub fn apply_controlled_phase(&mut self, qubit_a: usize, qubit_b: usize, theta: f64) {
+        assert_ne!(qubit_a, qubit_b, "controlled phase needs two different qubits");
+        assert!(
+            qubit_a < self.num_qubits && qubit_b < self.num_qubits,
+            "controlled phase on ({}, {}) out of range for {}-qubit register",
+            qubit_a,
+            qubit_b,
+            self.num_qubits
+        );
+
+        let (low, high) = if qubit_a < qubit_b {
+            (qubit_a, qubit_b)
+        } else {
+            (qubit_b, qubit_a)
+        };
+        let both = (1usize << low) | (1usize << high);
+        let phase = Complex64::from_polar(1.0, theta);
+        for k in 0..(self.amplitudes.len() >> 2) {
+            let i = insert_zero_bit(insert_zero_bit(k, low), high) | both;
+            self.amplitudes[i] *= phase;
+        }
+    }
+
+    /// Exchanges qubits `qubit_a` and `qubit_b` in place by swapping the
+    /// `2^(n-2)` amplitude pairs whose two bits differ, rather than three
+    /// CNOT passes or a full relabelling copy.
+    ///
+    /// Panics if `qubit_a == qubit_b` or either index is outside the register.
+    pub fn apply_swap(&mut self, qubit_a: usize, qubit_b: usize) {
+        assert_ne!(qubit_a, qubit_b, "SWAP needs two different qubits");
+        assert!(
+            qubit_a < self.num_qubits && qubit_b < self.num_qubits,
+            "SWAP on ({}, {}) out of range for {}-qubit register",
+            qubit_a,
+            qubit_b,
+            self.num_qubits
+        );
+
+        let (low, high) = if qubit_a < qubit_b {
+            (qubit_a, qubit_b)
+        } else {
+            (qubit_b, qubit_a)
+        };
+        for k in 0..(self.amplitudes.len() >> 2) {
+            let base = insert_zero_bit(insert_zero_bit(k, low), high);
+            self.amplitudes.swap_rows(base | (1 << low), base | (1 << high));
+        }
+    }
+
+    /// Applies `gate` to `target` on the subspace where every qubit in
+    /// `controls` is |1⟩. As in `apply_controlled_gate`, only the
+    /// `2^(n-k-1)` affected amplitude pairs are visited, by inserting zero
+    /// bits at the control and target positions and then setting the
+    /// controls, so a Toffoli touches a quarter of the pairs a CNOT does.
+    ///
+    /// Panics if a qubit repeats or is outside the register.
+    pub fn apply_multi_controlled_gate(&mut self, gate: &DMatrix<Complex64>, controls: &[usize], target: usize) {
+        let controls: Vec<(usize, bool)> = controls.iter().map(|&control| (control, true)).collect();
+        self.apply_multi_controlled_gate_with_values(gate, &controls, target);
+    }
+
+    /// Applies `gate` to `target` on the subspace where each `(qubit, value)`
+    /// in `controls` reads `value`, so controls on |0⟩ and |1⟩ mix freely.
+    /// Only the controls set to `true` are OR-ed into the enumerated base
+    /// index; the rest stay at the inserted zero.
+    ///
+    /// Panics if a qubit repeats or is outside the register.
+    pub fn apply_multi_controlled_gate_with_values(
+        &mut self,
+        gate: &DMatrix<Complex64>,
+        controls: &[(usize, bool)],
+        target: usize,
+    ) {
+        let mut positions: Vec<usize> = controls
+            .iter()
+            .map(|&(control, _)| control)
+            .chain(std::iter::once(target))
+            .collect();
+        assert!(
+            positions.iter().all(|&q| q < self.num_qubits),
+            "multi-controlled gate on {:?} -> {} out of range for {}-qubit register",
+            controls,
+            target,
+            self.num_qubits
+        );
+        positions.sort_unstable();
+        assert!(
+            positions.windows(2).all(|pair| pair[0] != pair[1]),
+            "controls and target of a multi-controlled gate must be different qubits"
+        );
+
+        let control_mask = controls
+            .iter()
+            .filter(|&&(_, value)| value)
+            .fold(0usize, |mask, &(q, _)| mask | (1 << q));
+        let target_mask = 1usize << target;
+        for k in 0..(self.amplitudes.len() >> positions.len()) {
+            let i = positions.iter().fold(k, |index, &q| insert_zero_bit(index, q)) | control_mask;
+            let j = i | target_mask;
+
+            let amp0 = self.amplitudes[i];
+            let amp1 = self.amplitudes[j];
+
+            self.amplitudes[i] = gate[(0, 0)] * amp0 + gate[(0, 1)] * amp1;
+            self.amplitudes[j] = gate[(1, 0)] * amp0 + gate[(1, 1)] * amp1;
+        }
+    }
+
+    /// Applies the 4x4 `gate` to `qubit_a` (low bit of the local index) and
+    /// `qubit_b`, mixing each group of four amplitudes that differ only in
+    /// those two bits. The groups are enumerated directly as in
+    /// `apply_controlled_gate`.
+    ///
+    /// Panics if `qubit_a == qubit_b`, either index is outside the register
+    /// or `gate` is not 4x4.
+    pub fn apply_two_qubit_gate(&mut self, gate: &DMatrix<Complex64>, qubit_a: usize, qubit_b: usize) {
+        assert_ne!(qubit_a, qubit_b, "two-qubit gate needs two different qubits");
+        assert!(
+            qubit_a < self.num_qubits && qubit_b < self.num_qubits,
+            "two-qubit gate on ({}, {}) out of range for {}-qubit register",
+            qubit_a,
+            qubit_b,
+            self.num_qubits
+        );
+        assert_eq!(gate.shape(), (4, 4), "two-qubit gate must be 4x4");
+
+        let (low, high) = if qubit_a < qubit_b {
+            (qubit_a, qubit_b)
+        } else {
+            (qubit_b, qubit_a)
+        };
+        let (mask_a, mask_b) = (1usize << qubit_a, 1usize << qubit_b);
+        for k in 0..(self.amplitudes.len() >> 2) {
+            let base = insert_zero_bit(insert_zero_bit(k, low), high);
+            let indices = [base, base | mask_a, base | mask_b, base | mask_a | mask_b];
+            let amps = indices.map(|i| self.amplitudes[i]);
+            for (row, &i) in indices.iter().enumerate() {
+                self.amplitudes[i] = (0..4).map(|column| gate[(row, column)] * amps[column]).sum();
+            }
+        }
+    }
+
+    /// Applies `exp(-i θ/2 P⊗P)` for `pauli` P on both qubits without a
+    /// matrix: ZZ is a parity-dependent phase, and XX and YY mix each
+    /// amplitude with the one where both bits are flipped, `c·a - i s·b`
+    /// (with the sign of `s` flipped for YY when the two bits are equal).
+    ///
+    /// Panics if `qubit_a == qubit_b` or either index is outside the register.
+    pub fn apply_pauli_pair_rotation(&mut self, pauli: Pauli, qubit_a: usize, qubit_b: usize, theta: f64) {
+        assert_ne!(qubit_a, qubit_b, "two-qubit rotation needs two different qubits");
+        assert!(
+            qubit_a < self.num_qubits && qubit_b < self.num_qubits,
+            "two-qubit rotation on ({}, {}) out of range for {}-qubit register",
+            qubit_a,
+            qubit_b,
+            self.num_qubits
+        );
+
+        let (low, high) = if qubit_a < qubit_b {
+            (qubit_a, qubit_b)
+        } else {
+            (qubit_b, qubit_a)
+        };
+        let (mask_a, mask_b) = (1usize << qubit_a, 1usize << qubit_b);
+        let (sin, cos) = (theta / 2.0).sin_cos();
+        let even_phase = Complex64::from_polar(1.0, -theta / 2.0);
+        let odd_phase = even_phase.conj();
+        for k in 0..(self.amplitudes.len() >> 2) {
+            let base = insert_zero_bit(insert_zero_bit(k, low), high);
+            let even = (base, base | mask_a | mask_b);
+            let odd = (base | mask_a, base | mask_b);
+            let (even_sin, odd_sin) = match pauli {
+                Pauli::Z => {
+                    for i in [even.0, even.1] {
+                        self.amplitudes[i] *= even_phase;
+                    }
+                    for i in [odd.0, odd.1] {
+                        self.amplitudes[i] *= odd_phase;
+                    }
+                    continue;
+                }
+                Pauli::X => (sin, sin),
+                Pauli::Y => (-sin, sin),
+            };
+            for ((i, j), sin) in [(even, even_sin), (odd, odd_sin)] {
+                let (amp_i, amp_j) = (self.amplitudes[i], self.amplitudes[j]);
+                let mix = Complex64::new(0.0, -sin);
+                self.amplitudes[i] = cos * amp_i + mix * amp_j;
+                self.amplitudes[j] = cos * amp_j + mix * amp_i;
+            }
+        }
+    }
+
+    /// Applies the `2^k x 2^k` unitary `gate` to the `k` listed qubits, with
+    /// `qubits[0]` as the low bit of the gate's local index (the crate's
+    /// little-endian order restricted to the subset). Each of the `2^(n-k)`
+    /// blocks of amplitudes sharing the other qubits' bits is gathered,
+    /// multiplied and scattered back. `gate` is not checked for unitarity.
+    ///
+    /// Fails on out-of-range or repeated qubits, or a matrix of the wrong
+    /// size.
+    pub fn apply_unitary(&mut self, gate: &DMatrix<Complex64>, qubits: &[usize]) -> Result<(), QuantumError> {
+        let mut mask = 0usize;
+        for &qubit in qubits {
+            self.check_qubit(qubit)?;
+            if mask & (1 << qubit) != 0 {
+                return Err(QuantumError::DuplicateQubit { qubit });
+            }
+            mask |= 1 << qubit;
+        }
+        let dimension = 1usize << qubits.len();
+        if gate.shape() != (dimension, dimension) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "unitary on {} qubit(s) must be {}x{}, got {}x{}",
+                qubits.len(),
+                dimension,
+                dimension,
+                gate.nrows(),
+                gate.ncols()
+            )));
+        }
+
+        let offsets: Vec<usize> = (0..dimension)
+            .map(|local| {
+                qubits
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| local >> bit & 1 == 1)
+                    .fold(0, |offset, (_, &qubit)| offset | (1 << qubit))
+            })
+            .collect();
+        let mut sorted = qubits.to_vec();
+        sorted.sort_unstable();
+
+        let mut block = DVector::zeros(dimension);
+        for k in 0..(self.amplitudes.len() >> qubits.len()) {
+            let base = sorted.iter().fold(k, |index, &qubit| insert_zero_bit(index, qubit));
+            for (local, &offset) in offsets.iter().enumerate() {
+                block[local] = self.amplitudes[base | offset];
+            }
+            let mixed = gate * &block;
+            for (local, &offset) in offsets.iter().enumerate() {
+                self.amplitudes[base | offset] = mixed[local];
+            }
+        }
+        Ok(())
+    }
+
+    /// Exchanges `first` and `second` on the subspace where `control` is |1⟩,
+    /// swapping the `2^(n-3)` amplitude pairs in place.
+    ///
+    /// Panics if a qubit repeats or is outside the register.
+    pub fn apply_controlled_swap(&mut self, control: usize, first: usize, second: usize) {
+        let mut positions = [control, first, second];
+        assert!(
+            positions.iter().all(|&q| q < self.num_qubits),
+            "controlled swap on ({}, {}, {}) out of range for {}-qubit register",
+            control,
+            first,
+            second,
+            self.num_qubits
+        );
+        positions.sort_unstable();
+        assert!(
+            positions[0] != positions[1] && positions[1] != positions[2],
+            "control and swapped qubits must be different"
+        );
+
+        for k in 0..(self.amplitudes.len() >> 3) {
+            let base = positions.iter().fold(k, |index, &q| insert_zero_bit(index, q)) | (1 << control);
+            self.amplitudes.swap_rows(base | (1 << first), base | (1 << second));
+        }
+    }
+
+    /// Applies [`Operation::ControlledModMul`]. The caller is responsible for
+    /// `multiplier` being coprime to `modulus`; otherwise amplitudes collide.
+    pub fn apply_controlled_mod_mul(&mut self, control: usize, register: &[usize], multiplier: usize, modulus: usize) {
+        assert!(
+            std::iter::once(&control).chain(register).all(|&q| q < self.num_qubits),
+            "modular multiplication out of range for {}-qubit register",
+            self.num_qubits
+        );
+
+        let mut amplitudes = DVector::from_element(self.amplitudes.len(), Complex64::new(0.0, 0.0));
+        for (i, amp) in self.amplitudes.iter().enumerate() {
+            amplitudes[mod_mul_index(i, control, register, multiplier, modulus)] = *amp;
+        }
+        self.amplitudes = amplitudes;
+    }
+
+    pub fn get_probability(&self, state: usize) -> f64 {
+        if state < self.amplitudes.len() {
+            self.amplitudes[state].norm_sqr()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.amplitudes.iter().map(|amp| amp.norm_sqr()).collect()
+    }
+
+    /// Returns the joint outcome distribution of the listed qubits, summing
+    /// over all others. Outcome bit `k` corresponds to `qubits[k]`.
+    pub fn marginal_probabilities(&self, qubits: &[usize]) -> Result<Vec<f64>, QuantumError> {
+        for &qubit in qubits {
+            self.check_qubit(qubit)?;
+        }
+
+        let mut marginals = vec![0.0; 1usize << qubits.len()];
+        for (i, amp) in self.amplitudes.iter().enumerate() {
+            let outcome = qubits
+                .iter()
+                .enumerate()
+                .fold(0usize, |acc, (k, &q)| acc | (((i >> q) & 1) << k));
+            marginals[outcome] += amp.norm_sqr();
+        }
+
+        Ok(marginals)
+    }
+
+    /// Von Neumann entropy (natural log) of the reduced state on `subsystem`,
+    /// from the Schmidt coefficients of the `subsystem | rest` split.
+    pub fn entanglement_entropy(&self, subsystem: &[usize]) -> Result<f64, QuantumError> {
+        Ok(self.entropy_of_mask(self.subsystem_mask(subsystem)?))
+    }
+
+    /// Purity `Tr(ρ_A²)` of the reduced state on `subsystem`: 1 when it is
+    /// unentangled with the rest, down to `2^-k` for `k` maximally entangled
+    /// qubits. Needs only a matrix product of the Schmidt matrix, taken on
+    /// the smaller side of the cut, rather than the decomposition behind
+    /// `entanglement_entropy`.
+    ///
+    /// Panics if a qubit is outside the register or listed twice.
+    pub fn subsystem_purity(&self, subsystem: &[usize]) -> f64 {
+        let mask = self
+            .subsystem_mask(subsystem)
+            .unwrap_or_else(|err| panic!("invalid subsystem: {}", err));
+        let schmidt = self.schmidt_matrix(mask);
+        let reduced = if schmidt.nrows() <= schmidt.ncols() {
+            &schmidt * schmidt.adjoint()
+        } else {
+            schmidt.adjoint() * &schmidt
+        };
+        reduced.iter().map(|entry| entry.norm_sqr()).sum()
+    }
+
+    /// Wootters concurrence of the reduced state `ρ` of qubits `q0` and `q1`:
+    /// `max(0, λ1 - λ2 - λ3 - λ4)` with `λi` the decreasing square roots of the
+    /// eigenvalues of `√ρ ρ̃ √ρ`, where `ρ̃ = (Y⊗Y) ρ* (Y⊗Y)`. 1 for a Bell
+    /// pair, 0 for a product (or any separable) pair.
+    ///
+    /// Panics if either qubit is outside the register or `q0 == q1`.
+    pub fn concurrence(&self, q0: usize, q1: usize) -> f64 {
+        let rho = DensityMatrix::reduced_from_state(self, &[q0, q1])
+            .unwrap_or_else(|err| panic!("invalid concurrence qubits: {}", err));
+        let rho = rho.matrix();
+
+        // Eigenvalues within rounding of zero are clamped, or their square
+        // roots would leave ~1e-8 of spurious concurrence on pure states.
+        let eigen = rho.clone().symmetric_eigen();
+        let sqrt_values = eigen
+            .eigenvalues
+            .map(|value| Complex64::new(if value > 1e-14 { value.sqrt() } else { 0.0 }, 0.0));
+        let sqrt_rho = &eigen.eigenvectors * DMatrix::from_diagonal(&sqrt_values) * eigen.eigenvectors.adjoint();
+
+        // λi are the singular values of √ρ √ρ̃, with √ρ̃ = (Y⊗Y) √ρ* (Y⊗Y)
+        let y = Gates::pauli_y();
+        let yy = y.kronecker(&y);
+        let sqrt_flipped = &yy * sqrt_rho.conjugate() * &yy;
+        let mut lambdas: Vec<f64> = (sqrt_rho * sqrt_flipped).singular_values().iter().copied().collect();
+        lambdas.sort_by(|a, b| b.total_cmp(a));
+        (lambdas[0] - lambdas[1..].iter().sum::<f64>()).max(0.0)
+    }
+
+    /// Bipartition with the largest entanglement entropy, as the qubits on one
+    /// side plus the entropy. Every cut is searched up to
+    /// `EXHAUSTIVE_BIPARTITION_QUBITS` qubits; beyond that only contiguous
+    /// cuts `0..k | k..n` are. Ties go to the first cut found. A single-qubit
+    /// register has no cut and yields `(vec![], 0.0)`.
+    pub fn max_entanglement_bipartition(&self) -> (Vec<usize>, f64) {
+        let n = self.num_qubits;
+        let candidates: Vec<usize> = if n <= EXHAUSTIVE_BIPARTITION_QUBITS {
+            // Keep the top qubit on the far side so each cut is visited once
+            (1..(1usize << n.saturating_sub(1))).collect()
+        } else {
+            (1..n).map(|k| (1usize << k) - 1).collect()
+        };
+
+        let mut best = (0usize, 0.0);
+        for mask in candidates {
+            let entropy = self.entropy_of_mask(mask);
+            if best.0 == 0 || entropy > best.1 + 1e-12 {
+                best = (mask, entropy);
+            }
+        }
+
+        let qubits = (0..n).filter(|q| best.0 & (1 << q) != 0).collect();
+        (qubits, best.1)
+    }
+
+    /// Expectation `Σ |amp_i|² f(i)` of the diagonal observable with
+    /// eigenvalue `f(i)` on basis state `i`, e.g. parity or Hamming weight.
+    pub fn expectation_fn(&self, f: impl Fn(usize) -> f64) -> f64 {
+        self.amplitudes
+            .iter()
+            .enumerate()
+            .map(|(i, amp)| amp.norm_sqr() * f(i))
+            .sum()
+    }
+
+    /// `⟨ψ|P|ψ⟩` for the Pauli string `operators`, read straight off the
+    /// amplitudes: `P|i⟩ = phase(i) |i ^ flip⟩` with `flip` the X/Y qubits,
+    /// so no gates are applied and no copy of the state is made. The sum is
+    /// split into `EXPECTATION_CHUNK`-sized pieces reduced in parallel.
+    pub fn expectation_pauli(&self, operators: &[(usize, Pauli)]) -> Result<f64, QuantumError> {
+        self.expectation_pauli_chunked(operators, EXPECTATION_CHUNK)
+    }
+
+    /// `expectation_pauli` with `chunk_size` amplitudes per parallel task.
+    /// Chunking only regroups the floating-point sum, so results agree with
+    /// the serial reduction up to rounding.
+    pub fn expectation_pauli_chunked(
+        &self,
+        operators: &[(usize, Pauli)],
+        chunk_size: usize,
+    ) -> Result<f64, QuantumError> {
+        if chunk_size == 0 {
+            return Err(QuantumError::InvalidParameter(
+                "expectation chunk size must be positive".to_string(),
+            ));
+        }
+
+        let (mut flip, mut sign_mask, mut y_count) = (0usize, 0usize, 0u32);
+        for &(qubit, pauli) in operators {
+            self.check_qubit(qubit)?;
+            let bit = 1usize << qubit;
+            if (flip | sign_mask) & bit != 0 {
+                return Err(QuantumError::DuplicateQubit { qubit });
+            }
+            match pauli {
+                Pauli::X => flip |= bit,
+                Pauli::Y => {
+                    flip |= bit;
+                    sign_mask |= bit;
+                    y_count += 1;
+                }
+                Pauli::Z => sign_mask |= bit,
+            }
+        }
+
+        // Y|b⟩ = i (-1)^b |1-b⟩ and Z|b⟩ = (-1)^b |b⟩
+        let amplitudes = self.amplitudes.as_slice();
+        let partial = |start: usize, chunk: &[Complex64]| -> Complex64 {
+            chunk
+                .iter()
+                .enumerate()
+                .map(|(offset, amp)| {
+                    let i = start + offset;
+                    let term = amplitudes[i ^ flip].conj() * amp;
+                    if (i & sign_mask).count_ones() % 2 == 1 {
+                        -term
+                    } else {
+                        term
+                    }
+                })
+                .sum()
+        };
+        let sum: Complex64 = if amplitudes.len() <= chunk_size {
+            partial(0, amplitudes)
+        } else {
+            amplitudes
+                .par_chunks(chunk_size)
+                .enumerate()
+                .map(|(index, chunk)| partial(index * chunk_size, chunk))
+                .sum()
+        };
+
+        Ok((sum * Complex64::i().powu(y_count)).re)
+    }
+
+    /// Basis states with `|amplitude| > threshold` as `(index, magnitude,
+    /// phase)`, in index order. Meant for checking that expected
+    /// interference actually happens when a circuit gives the wrong answer.
+    pub fn amplitude_report(&self, threshold: f64) -> Vec<(usize, f64, f64)> {
+        self.amplitudes
+            .iter()
+            .enumerate()
+            .filter(|(_, amp)| amp.norm() > threshold)
+            .map(|(i, amp)| (i, amp.norm(), amp.arg()))
+            .collect()
+    }
+
+    /// Basis index with the largest probability. Exact ties go to the lowest
+    /// basis index, so e.g. a GHZ state always reports `0`.
+    pub fn most_likely_outcome(&self) -> usize {
+        self.amplitudes
+            .iter()
+            .enumerate()
+            .fold((0, f64::NEG_INFINITY), |(best, best_p), (i, amp)| {
+                let p = amp.norm_sqr();
+                if p > best_p {
+                    (i, p)
+                } else {
+                    (best, best_p)
+                }
+            })
+            .0
+    }
+
+    /// Returns `(P(0), P(1))` for a single qubit by summing amplitudes directly,
+    /// without building a reduced density matrix.
+    ///
+    /// Panics if `qubit` is outside the register.
+    pub fn qubit_probability(&self, qubit: usize) -> (f64, f64) {
+        assert!(
+            qubit < self.num_qubits,
+            "qubit {} out of range for {}-qubit register",
+            qubit,
+            self.num_qubits
+        );
+
+        let mask = 1usize << qubit;
+        self.amplitudes
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(p0, p1), (i, amp)| {
+                if i & mask == 0 {
+                    (p0 + amp.norm_sqr(), p1)
+                } else {
+                    (p0, p1 + amp.norm_sqr())
+                }
+            })
+    }
+
+    /// Measures `qubit` in the computational basis, collapsing the state onto
+    /// the sampled outcome, which is returned.
+    pub fn measure_qubit(&mut self, qubit: usize, rng: &mut impl Rng) -> u8 {
+        let (_, p1) = self.qubit_probability(qubit);
+        let outcome = u8::from(rng.gen::<f64>() < p1);
+        let kept = if outcome == 1 { p1 } else { 1.0 - p1 };
+        self.project(qubit, outcome == 1, kept);
+        outcome
+    }
+
+    /// Projects `qubit` onto `outcome` and renormalises, returning the
+    /// probability the outcome had. Fails if that probability is at most
+    /// `DEFAULT_TOL`, as the conditional state is then undefined.
+    pub fn postselect(&mut self, qubit: usize, outcome: bool) -> Result<f64, QuantumError> {
+        self.check_qubit(qubit)?;
+        let (p0, p1) = self.qubit_probability(qubit);
+        let probability = if outcome { p1 } else { p0 };
+        if probability <= DEFAULT_TOL {
+            return Err(QuantumError::InvalidState(format!(
+                "outcome {} on qubit {} has probability {:e}",
+                u8::from(outcome),
+                qubit,
+                probability
+            )));
+        }
+        self.project(qubit, outcome, probability);
+        Ok(probability)
+    }
+
+    /// Draws `shots` full-register measurements without collapsing the state
+    /// and tallies them by basis index. Each draw walks the cumulative
+    /// distribution in index order, and the map iterates in index order, so
+    /// the result depends only on the RNG stream.
+    pub fn sample_counts(&self, shots: usize, rng: &mut impl Rng) -> BTreeMap<usize, usize> {
+        let probabilities = self.probabilities();
+        let total: f64 = probabilities.iter().sum();
+        let last = probabilities.iter().rposition(|&p| p > 0.0).unwrap_or(0);
+
+        let mut counts = BTreeMap::new();
+        for _ in 0..shots {
+            let r = rng.gen::<f64>() * total;
+            let mut cumulative = 0.0;
+            let outcome = probabilities
+                .iter()
+                .position(|&p| {
+                    cumulative += p;
+                    r < cumulative
+                })
+                .unwrap_or(last);
+            *counts.entry(outcome).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the state with qubit `q` relabelled as `perm[q]`, moving each
+    /// amplitude once instead of decomposing the permutation into SWAPs.
+    pub fn apply_permutation(&self, perm: &[usize]) -> Result<QuantumState, QuantumError> {
+        validate_permutation(perm, self.num_qubits)?;
+
+        let mut amplitudes = DVector::from_element(self.amplitudes.len(), Complex64::new(0.0, 0.0));
+        for (i, amp) in self.amplitudes.iter().enumerate() {
+            let j = perm
+                .iter()
+                .enumerate()
+                .fold(0usize, |acc, (q, &position)| acc | (((i >> q) & 1) << position));
+            amplitudes[j] = *amp;
+        }
+
+        Ok(QuantumState {
+            amplitudes,
+            num_qubits: self.num_qubits,
+            global_phase: self.global_phase,
+        })
+    }
+
+    /// Overlap probability |⟨self|other⟩|² between two pure states.
+    pub fn fidelity(&self, other: &QuantumState) -> f64 {
+        self.amplitudes.dotc(&other.amplitudes).norm_sqr()
+    }
+
+    /// Whether both states have the same register size and every amplitude
+    /// differs by at most `tolerance` (typically `DEFAULT_TOL`). Global
+    /// phase counts; see `global_phase_difference` to ignore it.
+    pub fn approx_eq(&self, other: &QuantumState, tolerance: f64) -> bool {
+        self.num_qubits == other.num_qubits
+            && self
+                .amplitudes
+                .iter()
+                .zip(other.amplitudes.iter())
+                .all(|(a, b)| (a - b).norm() <= tolerance)
+    }
+
+    /// Streams the state to `writer` as a little-endian `u32` qubit count
+    /// followed by `(re, im)` `f64` pairs in basis order. Amplitudes are
+    /// encoded a chunk at a time, so large states are never duplicated in
+    /// memory. Pair with a buffered writer for files.
+    pub fn write_amplitudes<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.num_qubits as u32).to_le_bytes())?;
+
+        let mut buffer = Vec::with_capacity(AMPLITUDE_CHUNK * 16);
+        for chunk in self.amplitudes.as_slice().chunks(AMPLITUDE_CHUNK) {
+            buffer.clear();
+            for amp in chunk {
+                buffer.extend_from_slice(&amp.re.to_le_bytes());
+                buffer.extend_from_slice(&amp.im.to_le_bytes());
+            }
+            writer.write_all(&buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a state written by `write_amplitudes`.
+    pub fn read_amplitudes<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word)?;
+        let num_qubits = u32::from_le_bytes(word) as usize;
+        if num_qubits >= usize::BITS as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} qubits cannot be addressed", num_qubits),
+            ));
+        }
+
+        let size = 1usize << num_qubits;
+        let mut amplitudes = Vec::with_capacity(size);
+        let mut buffer = vec![0u8; AMPLITUDE_CHUNK * 16];
+        while amplitudes.len() < size {
+            let count = AMPLITUDE_CHUNK.min(size - amplitudes.len());
+            let bytes = &mut buffer[..count * 16];
+            reader.read_exact(bytes)?;
+            for pair in bytes.chunks_exact(16) {
+                let re = f64::from_le_bytes(pair[..8].try_into().expect("8-byte slice"));
+                let im = f64::from_le_bytes(pair[8..].try_into().expect("8-byte slice"));
+                amplitudes.push(Complex64::new(re, im));
+            }
+        }
+
+        Self::from_amplitudes(DVector::from_vec(amplitudes))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+
+    pub fn amplitudes(&self) -> &DVector<Complex64> {
+        &self.amplitudes
+    }
+
+    /// Bytes held by the amplitude buffer: `2^n` complex doubles.
+    pub fn memory_bytes(&self) -> usize {
+        self.amplitudes.len() * std::mem::size_of::<Complex64>()
+    }
+
+    /// Number of basis states with amplitude magnitude above
+    /// `NONZERO_AMPLITUDE_TOLERANCE`.
+    pub fn support_size(&self) -> usize {
+        self.amplitudes
+            .iter()
+            .filter(|amp| amp.norm() > NONZERO_AMPLITUDE_TOLERANCE)
+            .count()
+    }
+
+    pub fn size(&self) -> usize {
+        self.amplitudes.len()
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    fn entropy_of_mask(&self, mask: usize) -> f64 {
+        self.schmidt_matrix(mask)
+            .singular_values()
+            .iter()
+            .map(|s| s * s)
+            .filter(|&p| p > 1e-15)
+            .map(|p| -p * p.ln())
+            .sum()
+    }
+
+    /// Bit mask of `subsystem`, rejecting out-of-range and repeated qubits.
+    fn subsystem_mask(&self, subsystem: &[usize]) -> Result<usize, QuantumError> {
+        let mut mask = 0usize;
+        for &qubit in subsystem {
+            self.check_qubit(qubit)?;
+            if mask & (1 << qubit) != 0 {
+                return Err(QuantumError::InvalidParameter(format!(
+                    "qubit {} appears twice in subsystem",
+                    qubit
+                )));
+            }
+            mask |= 1 << qubit;
+        }
+        Ok(mask)
+    }
+
+    /// Amplitudes reshaped to a `2^|inside| x 2^|outside|` matrix, rows
+    /// indexed by the qubits set in `mask`.
+    fn schmidt_matrix(&self, mask: usize) -> DMatrix<Complex64> {
+        let inside: Vec<usize> = (0..self.num_qubits).filter(|q| mask & (1 << q) != 0).collect();
+        let outside: Vec<usize> = (0..self.num_qubits).filter(|q| mask & (1 << q) == 0).collect();
+
+        let mut schmidt = DMatrix::from_element(1 << inside.len(), 1 << outside.len(), Complex64::new(0.0, 0.0));
+        for (i, amp) in self.amplitudes.iter().enumerate() {
+            let gather = |qubits: &[usize]| {
+                qubits
+                    .iter()
+                    .enumerate()
+                    .fold(0usize, |acc, (k, &q)| acc | (((i >> q) & 1) << k))
+            };
+            schmidt[(gather(&inside), gather(&outside))] = *amp;
+        }
+        schmidt
+    }
+
+    /// Zeroes the branch of `qubit` opposite `outcome` and rescales the rest
+    /// by `1 / sqrt(probability)`.
+    fn project(&mut self, qubit: usize, outcome: bool, probability: f64) {
+        let mask = 1usize << qubit;
+        let scale = 1.0 / probability.sqrt();
+        for (i, amp) in self.amplitudes.iter_mut().enumerate() {
+            if ((i & mask) != 0) == outcome {
+                *amp *= scale;
+            } else {
+                *amp = Complex64::new(0.0, 0.0);
+            }
+        }
+    }
+
+    fn check_qubit(&self, qubit: usize) -> Result<(), QuantumError> {
+        if qubit < self.num_qubits {
+            Ok(())
+        } else {
+            Err(QuantumError::InvalidQubit {
+                qubit,
+                num_qubits: self.num_qubits,
+            })
+        }
+    }
+}
+
+pub struct Gates;
+
+impl Gates {
+    pub fn pauli_x() -> DMatrix<Complex64> {
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(0.0, 0.0),
+                Complex64::new(1.0, 0.0),
+                Complex64::new(1.0, 0.0),
+                Complex64::new(0.0, 0.0),
+            ],
+        )
+    }
+
+    pub fn pauli_y() -> DMatrix<Complex64> {
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, -1.0),
+                Complex64::new(0.0, 1.0),
+                Complex64::new(0.0, 0.0),
+            ],
+        )
+    }
+
+    pub fn pauli_z() -> DMatrix<Complex64> {
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(1.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(-1.0, 0.0),
+            ],
+        )
+    }
+
+    pub fn hadamard() -> DMatrix<Complex64> {
+        let inv_sqrt2 = 1.0 / 2.0_f64.sqrt();
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(inv_sqrt2, 0.0),
+                Complex64::new(inv_sqrt2, 0.0),
+                Complex64::new(inv_sqrt2, 0.0),
+                Complex64::new(-inv_sqrt2, 0.0),
+            ],
+        )
+    }
+
+    pub fn rx(theta: f64) -> DMatrix<Complex64> {
+        let cos_half = (theta / 2.0).cos();
+        let sin_half = (theta / 2.0).sin();
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(cos_half, 0.0),
+                Complex64::new(0.0, -sin_half),
+                Complex64::new(0.0, -sin_half),
+                Complex64::new(cos_half, 0.0),
+            ],
+        )
+    }
+
+    pub fn ry(theta: f64) -> DMatrix<Complex64> {
+        let cos_half = (theta / 2.0).cos();
+        let sin_half = (theta / 2.0).sin();
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(cos_half, 0.0),
+                Complex64::new(-sin_half, 0.0),
+                Complex64::new(sin_half, 0.0),
+                Complex64::new(cos_half, 0.0),
+            ],
+        )
+    }
+
+    /// Phase shift `diag(1, e^{iθ})`; controlled, it is the CP(θ) of QFT circuits.
+    pub fn phase(theta: f64) -> DMatrix<Complex64> {
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(1.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::from_polar(1.0, theta),
+            ],
+        )
+    }
+
+    /// `U3(θ, φ, λ) = RZ(φ) RY(θ) RZ(λ)` up to global phase, fixed so the
+    /// top-left entry is real: any single-qubit unitary, as OpenQASM's `u3`.
+    /// `u2(φ, λ)` is `u3(π/2, φ, λ)` and `u1(λ)` is `phase(λ)`.
+    pub fn u3(theta: f64, phi: f64, lambda: f64) -> DMatrix<Complex64> {
+        let (sin, cos) = (theta / 2.0).sin_cos();
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::new(cos, 0.0),
+                -Complex64::from_polar(sin, lambda),
+                Complex64::from_polar(sin, phi),
+                Complex64::from_polar(cos, phi + lambda),
+            ],
+        )
+    }
+
+    /// `SX = ½ [[1+i, 1-i], [1-i, 1+i]]`, the square root of X and a native
+    /// gate of IBM devices; `e^(iπ/4) RX(π/2)`.
+    pub fn sx() -> DMatrix<Complex64> {
+        let plus = Complex64::new(0.5, 0.5);
+        let minus = Complex64::new(0.5, -0.5);
+        DMatrix::from_row_slice(2, 2, &[plus, minus, minus, plus])
+    }
+
+    pub fn sxdg() -> DMatrix<Complex64> {
+        Self::sx().adjoint()
+    }
+
+    /// `S = diag(1, i)`, the square root of Z.
+    pub fn s() -> DMatrix<Complex64> {
+        Self::phase(PI / 2.0)
+    }
+
+    pub fn sdg() -> DMatrix<Complex64> {
+        Self::phase(-PI / 2.0)
+    }
+
+    /// `T = diag(1, e^(iπ/4))`, the non-Clifford gate of Clifford+T.
+    pub fn t() -> DMatrix<Complex64> {
+        Self::phase(PI / 4.0)
+    }
+
+    pub fn tdg() -> DMatrix<Complex64> {
+        Self::phase(-PI / 4.0)
+    }
+
+    /// iSWAP: swaps |01⟩ and |10⟩ with a phase of `i`, leaving |00⟩ and |11⟩
+    /// alone. A 4x4 matrix for `Operation::TwoQubitGate`; symmetric in its
+    /// qubits.
+    pub fn iswap() -> DMatrix<Complex64> {
+        Self::partial_iswap(1.0)
+    }
+
+    /// √iSWAP, the native entangler of several superconducting devices; two
+    /// of them make an iSWAP.
+    pub fn sqrt_iswap() -> DMatrix<Complex64> {
+        Self::partial_iswap(0.5)
+    }
+
+    /// `RXX(θ) = exp(-i θ/2 X⊗X)`, as a 4x4 matrix.
+    pub fn rxx(theta: f64) -> DMatrix<Complex64> {
+        Self::pauli_pair_rotation(Pauli::X, theta)
+    }
+
+    /// `RYY(θ) = exp(-i θ/2 Y⊗Y)`, as a 4x4 matrix.
+    pub fn ryy(theta: f64) -> DMatrix<Complex64> {
+        Self::pauli_pair_rotation(Pauli::Y, theta)
+    }
+
+    /// `RZZ(θ) = exp(-i θ/2 Z⊗Z)`, as a 4x4 matrix.
+    pub fn rzz(theta: f64) -> DMatrix<Complex64> {
+        Self::pauli_pair_rotation(Pauli::Z, theta)
+    }
+
+    /// `cos(θ/2) I - i sin(θ/2) P⊗P`.
+    fn pauli_pair_rotation(pauli: Pauli, theta: f64) -> DMatrix<Complex64> {
+        let single = match pauli {
+            Pauli::X => Self::pauli_x(),
+            Pauli::Y => Self::pauli_y(),
+            Pauli::Z => Self::pauli_z(),
+        };
+        let (sin, cos) = (theta / 2.0).sin_cos();
+        DMatrix::identity(4, 4) * Complex64::new(cos, 0.0) - single.kronecker(&single) * Complex64::new(0.0, sin)
+    }
+
+    /// Echoed cross-resonance gate `(IX - XY)/√2`, IBM's native entangler,
+    /// as a 4x4 matrix with the first qubit as the low bit: X on the first
+    /// qubit, minus X on the second times Y on the first. Its own inverse.
+    pub fn ecr() -> DMatrix<Complex64> {
+        let x = Self::pauli_x();
+        let identity = DMatrix::<Complex64>::identity(2, 2);
+        let scale = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+        (identity.kronecker(&x) - x.kronecker(&Self::pauli_y())) * scale
+    }
+
+    /// `iSWAP^power` = `exp(i π/2 · power · (XX + YY)/2)`.
+    fn partial_iswap(power: f64) -> DMatrix<Complex64> {
+        let angle = PI / 2.0 * power;
+        let zero = Complex64::new(0.0, 0.0);
+        let one = Complex64::new(1.0, 0.0);
+        let diagonal = Complex64::new(angle.cos(), 0.0);
+        let exchange = Complex64::new(0.0, angle.sin());
+        DMatrix::from_row_slice(
+            4,
+            4,
+            &[
+                one, zero, zero, zero, //
+                zero, diagonal, exchange, zero, //
+                zero, exchange, diagonal, zero, //
+                zero, zero, zero, one,
+            ],
+        )
+    }
+
+    pub fn rz(theta: f64) -> DMatrix<Complex64> {
+        DMatrix::from_row_slice(
+            2,
+            2,
+            &[
+                Complex64::from_polar(1.0, -theta / 2.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::new(0.0, 0.0),
+                Complex64::from_polar(1.0, theta / 2.0),
+            ],
+        )
+    }
+}
+
+/// Which gate an operation's matrix came from. Kept next to the matrix so
+/// passes can reason about gates by name and angle instead of by entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GateKind {
+    H,
+    X,
+    Y,
+    Z,
+    Rx(f64),
+    Ry(f64),
+    Rz(f64),
+    Phase(f64),
+    /// `U3(θ, φ, λ)`.
+    U3(f64, f64, f64),
+    Sx,
+    Sxdg,
+    S,
+    Sdg,
+    T,
+    Tdg,
+    /// Any other unitary, e.g. a user matrix or the product of a fusion pass.
+    Custom,
+}
+
+impl GateKind {
+    /// Rebuilds the gate's matrix from its name and angle; `None` for
+    /// `Custom`, whose entries only the stored matrix knows.
+    pub fn matrix(&self) -> Option<DMatrix<Complex64>> {
+        match *self {
+            GateKind::H => Some(Gates::hadamard()),
+            GateKind::X => Some(Gates::pauli_x()),
+            GateKind::Y => Some(Gates::pauli_y()),
+            GateKind::Z => Some(Gates::pauli_z()),
+            GateKind::Rx(angle) => Some(Gates::rx(angle)),
+            GateKind::Ry(angle) => Some(Gates::ry(angle)),
+            GateKind::Rz(angle) => Some(Gates::rz(angle)),
+            GateKind::Phase(angle) => Some(Gates::phase(angle)),
+            GateKind::U3(theta, phi, lambda) => Some(Gates::u3(theta, phi, lambda)),
+            GateKind::Sx => Some(Gates::sx()),
+            GateKind::Sxdg => Some(Gates::sxdg()),
+            GateKind::S => Some(Gates::s()),
+            GateKind::Sdg => Some(Gates::sdg()),
+            GateKind::T => Some(Gates::t()),
+            GateKind::Tdg => Some(Gates::tdg()),
+            GateKind::Custom => None,
+        }
+    }
+}
+
+/// Which gate a `TwoQubitGate`'s matrix came from, like `GateKind` for
+/// single-qubit gates. The Pauli rotations run on a dedicated kernel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TwoQubitKind {
+    ISwap,
+    SqrtISwap,
+    Ecr,
+    Rxx(f64),
+    Ryy(f64),
+    Rzz(f64),
+    /// Any other 4x4 unitary.
+    Custom,
+}
+
+#[derive(Debug, Clone)]
+pub enum Operation {
+    SingleGate {
+        gate: DMatrix<Complex64>,
+        kind: GateKind,
+        qubit: usize,
+    },
+    /// `kind` describes the gate applied to the target (X for a CNOT).
+    /// `control_value` is the control state that fires it: `true` for an
+    /// ordinary control, `false` for a control on |0⟩.
+    ControlledGate {
+        gate: DMatrix<Complex64>,
+        kind: GateKind,
+        control: usize,
+        target: usize,
+        control_value: bool,
+    },
+    /// `gate` on `target` when every `(qubit, value)` in `controls` reads
+    /// `value`, with `kind` as for `ControlledGate` (X for a Toffoli).
+    MultiControlledGate {
+        gate: DMatrix<Complex64>,
+        kind: GateKind,
+        controls: Vec<(usize, bool)>,
+        target: usize,
+    },
+    /// A 4x4 `gate` on two qubits; `qubits[0]` is the low bit of the
+    /// gate's local basis index, so `|q1 q0⟩` is row `2 q1 + q0`.
+    TwoQubitGate {
+        gate: DMatrix<Complex64>,
+        kind: TwoQubitKind,
+        qubits: [usize; 2],
+    },
+    /// Fredkin gate: exchanges `first` and `second` when `control` is |1⟩.
+    ControlledSwap {
+        control: usize,
+        first: usize,
+        second: usize,
+    },
+    /// A user-defined `Gate` on `qubits`, `qubits[0]` being the low bit of
+    /// its matrix. Added through `QuantumCircuit::custom_gate`.
+    CustomGate { gate: Arc<dyn Gate>, qubits: Vec<usize> },
+    /// Relabels qubits: qubit `q` moves to position `perm[q]`.
+    Permutation { perm: Vec<usize> },
+    /// `|x⟩ -> |multiplier * x mod modulus⟩` on `register` (little-endian) when
+    /// `control` is set. Values `x >= modulus` are left alone, so the map is a
+    /// permutation of the basis as long as `multiplier` is coprime to `modulus`.
+    ControlledModMul {
+        control: usize,
+        register: Vec<usize>,
+        multiplier: usize,
+        modulus: usize,
+    },
+    /// Hadamard on every listed qubit, applied as one fast Walsh-Hadamard
+    /// transform. `schedule_for_simulation` emits it for full Hadamard layers.
+    HadamardTransform { qubits: Vec<usize> },
+    /// Projective measurement of `qubit` in `basis`. Only
+    /// `execute_with_measurements` samples it; the unitary execution paths
+    /// (`execute`, `NaiveSimulator`, density matrices) treat it as identity.
+    Measure { qubit: usize, basis: PauliBasis },
+}
+
+/// Basis of a single-qubit measurement; `Z` is the computational basis.
+/// Outcome 0 is the +1 eigenstate.
+pub type PauliBasis = Pauli;
+
+impl Operation {
+    /// Qubits the operation acts on, controls first.
+    pub fn qubits(&self) -> Vec<usize> {
+        match self {
+            Operation::SingleGate { qubit, .. } => vec![*qubit],
+            Operation::ControlledGate { control, target, .. } => vec![*control, *target],
+            Operation::MultiControlledGate { controls, target, .. } => controls
+                .iter()
+                .map(|&(control, _)| control)
+                .chain(std::iter::once(*target))
+                .collect(),
+            Operation::TwoQubitGate { qubits, .. } => qubits.to_vec(),
+            Operation::ControlledSwap { control, first, second } => vec![*control, *first, *second],
+            Operation::CustomGate { qubits, .. } => qubits.clone(),
+            Operation::Permutation { perm } => perm
+                .iter()
+                .enumerate()
+                .filter(|(qubit, position)| qubit != *position)
+                .map(|(qubit, _)| qubit)
+                .collect(),
+            Operation::ControlledModMul { control, register, .. } => {
+                std::iter::once(*control).chain(register.iter().copied()).collect()
+            }
+            Operation::HadamardTransform { qubits } => qubits.clone(),
+            Operation::Measure { qubit, .. } => vec![*qubit],
+        }
+    }
+
+    pub fn apply(&self, state: &mut QuantumState) {
+        match self {
+            Operation::SingleGate { gate, qubit, .. } => state.apply_single_gate(gate, *qubit),
+            Operation::ControlledGate {
+                kind: GateKind::Z,
+                control,
+                target,
+                control_value: true,
+                ..
+            } => state.apply_cz(*control, *target),
+            Operation::ControlledGate {
+                kind: GateKind::Phase(theta),
+                control,
+                target,
+                control_value: true,
+                ..
+            } => state.apply_controlled_phase(*control, *target, *theta),
+            Operation::ControlledGate {
+                gate,
+                control,
+                target,
+                control_value,
+                ..
+            } => state.apply_controlled_gate_with_value(gate, *control, *target, *control_value),
+            Operation::MultiControlledGate {
+                gate,
+                controls,
+                target,
+                ..
+            } => state.apply_multi_controlled_gate_with_values(gate, controls, *target),
+            Operation::TwoQubitGate { gate, kind, qubits } => match *kind {
+                TwoQubitKind::Rxx(theta) => state.apply_pauli_pair_rotation(Pauli::X, qubits[0], qubits[1], theta),
+                TwoQubitKind::Ryy(theta) => state.apply_pauli_pair_rotation(Pauli::Y, qubits[0], qubits[1], theta),
+                TwoQubitKind::Rzz(theta) => state.apply_pauli_pair_rotation(Pauli::Z, qubits[0], qubits[1], theta),
+                _ => state.apply_two_qubit_gate(gate, qubits[0], qubits[1]),
+            },
+            Operation::ControlledSwap { control, first, second } => {
+                state.apply_controlled_swap(*control, *first, *second)
+            }
+            Operation::CustomGate { gate, qubits } => gate.apply(state, qubits),
+            Operation::Permutation { perm } => match transposition(perm) {
+                Some((a, b)) => state.apply_swap(a, b),
+                None => {
+                    *state = state
+                        .apply_permutation(perm)
+                        .expect("permutation validated when added to the circuit");
+                }
+            },
+            Operation::ControlledModMul {
+                control,
+                register,
+                multiplier,
+                modulus,
+            } => state.apply_controlled_mod_mul(*control, register, *multiplier, *modulus),
+            Operation::HadamardTransform { qubits } => state.apply_hadamard_transform(qubits),
+            Operation::Measure { .. } => {}
+        }
+    }
+
+    /// Applies the operation's inverse: gate adjoints, the inverse
+    /// permutation, and the modular multiplication undone by gathering
+    /// amplitudes instead of scattering them. `ControlledSwap`,
+    /// `HadamardTransform` and `Measure` are their own inverses here.
+    pub fn apply_inverse(&self, state: &mut QuantumState) {
+        match self {
+            Operation::SingleGate { gate, qubit, .. } => state.apply_single_gate(&gate.adjoint(), *qubit),
+            Operation::ControlledGate {
+                kind: GateKind::Phase(theta),
+                control,
+                target,
+                control_value: true,
+                ..
+            } => state.apply_controlled_phase(*control, *target, -theta),
+            Operation::ControlledGate {
+                gate,
+                control,
+                target,
+                control_value,
+                ..
+            } => state.apply_controlled_gate_with_value(&gate.adjoint(), *control, *target, *control_value),
+            Operation::MultiControlledGate {
+                gate,
+                controls,
+                target,
+                ..
+            } => state.apply_multi_controlled_gate_with_values(&gate.adjoint(), controls, *target),
+            Operation::TwoQubitGate { gate, kind, qubits } => match *kind {
+                TwoQubitKind::Rxx(theta) => state.apply_pauli_pair_rotation(Pauli::X, qubits[0], qubits[1], -theta),
+                TwoQubitKind::Ryy(theta) => state.apply_pauli_pair_rotation(Pauli::Y, qubits[0], qubits[1], -theta),
+                TwoQubitKind::Rzz(theta) => state.apply_pauli_pair_rotation(Pauli::Z, qubits[0], qubits[1], -theta),
+                _ => state.apply_two_qubit_gate(&gate.adjoint(), qubits[0], qubits[1]),
+            },
+            Operation::ControlledSwap { control, first, second } => {
+                state.apply_controlled_swap(*control, *first, *second)
+            }
+            Operation::CustomGate { gate, qubits } => state
+                .apply_unitary(&gate.matrix().adjoint(), qubits)
+                .expect("custom gate qubits are validated when the gate is added"),
+            Operation::Permutation { perm } => match transposition(perm) {
+                Some((a, b)) => state.apply_swap(a, b),
+                None => {
+                    let mut inverse = vec![0; perm.len()];
+                    for (qubit, &position) in perm.iter().enumerate() {
+                        inverse[position] = qubit;
+                    }
+                    *state = state
+                        .apply_permutation(&inverse)
+                        .expect("permutation validated when added to the circuit");
+                }
+            },
+            Operation::ControlledModMul {
+                control,
+                register,
+                multiplier,
+                modulus,
+            } => {
+                state.amplitudes = DVector::from_fn(state.amplitudes.len(), |i, _| {
+                    state.amplitudes[mod_mul_index(i, *control, register, *multiplier, *modulus)]
+                });
+            }
+            Operation::HadamardTransform { qubits } => state.apply_hadamard_transform(qubits),
+            Operation::Measure { .. } => {}
+        }
+    }
+
+    /// Whether the operation maps real amplitudes to real amplitudes, i.e.
+    /// every matrix entry has a zero imaginary part. Basis permutations and
+    /// measurements (identity in unitary execution) always do.
+    pub fn is_real(&self) -> bool {
+        match self {
+            Operation::SingleGate { gate, .. }
+            | Operation::ControlledGate { gate, .. }
+            | Operation::MultiControlledGate { gate, .. }
+            | Operation::TwoQubitGate { gate, .. } => gate.iter().all(|entry| entry.im == 0.0),
+            Operation::CustomGate { gate, .. } => gate.matrix().iter().all(|entry| entry.im == 0.0),
+            Operation::ControlledSwap { .. }
+            | Operation::Permutation { .. }
+            | Operation::ControlledModMul { .. }
+            | Operation::HadamardTransform { .. }
+            | Operation::Measure { .. } => true,
+        }
+    }
+}
+
+/// One instruction of `QuantumCircuit::to_qir_ops`, a flat export for QIR
+/// generators. Where a QIR quantum instruction exists, `gate` is its name in
+/// `__quantum__qis__<gate>__body` (`h`, `x`, `y`, `z`, `s`, `s__adj`, `t`,
+/// `t__adj`, `rx`, `ry`, `rz`, `rxx`, `ryy`, `rzz`, `cnot`, `cz`, `ccx`,
+/// `swap`, `mz`), with rotation angles in `params`. Everything else is passed through without
+/// loss:
+///
+/// - `unitary` on one qubit, `controlled_unitary` on `[control, target]` and
+///   `multi_controlled_unitary` on `[controls..., target]` carry the 2x2
+///   matrix row-major as `re, im` pairs in `params`, and
+///   `two_qubit_unitary` the 4x4 one (`qubits[0]` is the low bit);
+/// - custom gates export their matrix the same way, as `unitary`,
+///   `two_qubit_unitary` or, on more qubits, `multi_qubit_unitary`;
+/// - `controlled_swap` on `[control, first, second]` takes no parameters;
+/// - `controlled_mod_mul` on `[control, register...]` carries
+///   `[multiplier, modulus]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QirOp {
+    pub gate: &'static str,
+    pub qubits: Vec<usize>,
+    pub params: Vec<f64>,
+}
+
+impl QirOp {
+    fn new(gate: &'static str, qubits: Vec<usize>, params: Vec<f64>) -> Self {
+        QirOp { gate, qubits, params }
+    }
+
+    fn matrix(gate: &'static str, qubits: Vec<usize>, matrix: &DMatrix<Complex64>) -> Self {
+        let params = matrix
+            .row_iter()
+            .flat_map(|row| row.iter().flat_map(|entry| [entry.re, entry.im]).collect::<Vec<_>>())
+            .collect();
+        QirOp::new(gate, qubits, params)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuantumCircuit {
+    num_qubits: usize,
+    operations: Vec<Operation>,
+    /// Allowed deviation of the final norm from 1, when the check is enabled.
+    norm_tolerance: Option<f64>,
+    /// `φ` of the overall `e^(iφ)` factor applied after the operations, in
+    /// `[0, 2π)`.
+    global_phase: f64,
+    /// Present once `cache_unitary` is enabled; filled on the next execution
+    /// and emptied whenever an operation is added.
+    unitary_cache: Option<OnceLock<DMatrix<Complex64>>>,
+}
+
+impl QuantumCircuit {
+    pub fn new(num_qubits: usize) -> Self {
+        QuantumCircuit {
+            num_qubits,
+            operations: Vec::new(),
+            norm_tolerance: None,
+            global_phase: 0.0,
+            unitary_cache: None,
+        }
+    }
+
+    /// Builds a circuit from generic `(name, qubits, params)` triples, the
+    /// interchange format of tools that don't emit QASM. Names are matched
+    /// case-insensitively against `h x y z sx sxdg s sdg t tdg rx ry rz phase
+    /// u2 u3 cnot cz cp swap iswap ecr crx cry crz rxx ryy rzz ccx cswap`
+    /// (`cx` is accepted for `cnot`, `p` and `u1` for `phase`, `u` for `u3`,
+    /// `cphase` and `cu1` for `cp`). Unknown names, wrong qubit or parameter
+    /// counts, and out-of-range or repeated qubits are rejected with the
+    /// index of the offending entry.
+    pub fn from_gate_list(
+        num_qubits: usize,
+        gates: &[(String, Vec<usize>, Vec<f64>)],
+    ) -> Result<Self, QuantumError> {
+        Self::from_gate_list_with_registry(num_qubits, gates, &GateRegistry::new())
+    }
+
+    /// Like `from_gate_list`, with names that aren't built in looked up in
+    /// `registry`. Registered gates take no parameters, and built-in names
+    /// win over registered ones.
+    pub fn from_gate_list_with_registry(
+        num_qubits: usize,
+        gates: &[(String, Vec<usize>, Vec<f64>)],
+        registry: &GateRegistry,
+    ) -> Result<Self, QuantumError> {
+        let mut circuit = QuantumCircuit::new(num_qubits);
+
+        for (index, (name, qubits, params)) in gates.iter().enumerate() {
+            let name = name.to_ascii_lowercase();
+            let (arity, num_params) = match name.as_str() {
+                "h" | "x" | "y" | "z" | "sx" | "sxdg" | "s" | "sdg" | "t" | "tdg" => (1, 0),
+                "rx" | "ry" | "rz" | "phase" | "p" | "u1" => (1, 1),
+                "u2" => (1, 2),
+                "u3" | "u" => (1, 3),
+                "cnot" | "cx" | "cz" | "swap" | "iswap" | "ecr" => (2, 0),
+                "crx" | "cry" | "crz" | "cp" | "cphase" | "cu1" | "rxx" | "ryy" | "rzz" => (2, 1),
+                "ccx" | "cswap" => (3, 0),
+                _ => match registry.get(&name) {
+                    Some(gate) => (gate.num_qubits(), 0),
+                    None => {
+                        return Err(QuantumError::InvalidParameter(format!(
+                            "gate {}: unknown gate '{}'",
+                            index, name
+                        )))
+                    }
+                },
+            };
+            if qubits.len() != arity || params.len() != num_params {
+                return Err(QuantumError::InvalidParameter(format!(
+                    "gate {}: '{}' takes {} qubit(s) and {} parameter(s), got {} and {}",
+                    index,
+                    name,
+                    arity,
+                    num_params,
+                    qubits.len(),
+                    params.len()
+                )));
+            }
+            circuit.check_distinct_qubits(qubits)?;
+
+            let qubit = qubits[0];
+            match name.as_str() {
+                "h" => circuit.h(qubit),
+                "x" => circuit.x(qubit),
+                "y" => circuit.y(qubit),
+                "z" => circuit.z(qubit),
+                "sx" => circuit.sx(qubit),
+                "sxdg" => circuit.sxdg(qubit),
+                "s" => circuit.s(qubit),
+                "sdg" => circuit.sdg(qubit),
+                "t" => circuit.t(qubit),
+                "tdg" => circuit.tdg(qubit),
+                "rx" => circuit.rx(qubit, params[0]),
+                "ry" => circuit.ry(qubit, params[0]),
+                "rz" => circuit.rz(qubit, params[0]),
+                "phase" | "p" | "u1" => {
+                    circuit.add_single_gate(Gates::phase(params[0]), GateKind::Phase(params[0]), qubit)
+                }
+                "u2" => circuit.u3(qubit, PI / 2.0, params[0], params[1]),
+                "u3" | "u" => circuit.u3(qubit, params[0], params[1], params[2]),
+                "cnot" | "cx" => circuit.cnot(qubit, qubits[1])?,
+                "cz" => circuit.cz(qubit, qubits[1])?,
+                "swap" => circuit.swap(qubit, qubits[1])?,
+                "iswap" => circuit.iswap(qubit, qubits[1])?,
+                "ecr" => circuit.ecr(qubit, qubits[1])?,
+                "ccx" => circuit.ccx(qubit, qubits[1], qubits[2])?,
+                "cswap" => circuit.cswap(qubit, qubits[1], qubits[2])?,
+                "crx" => circuit.crx(qubit, qubits[1], params[0])?,
+                "crz" => circuit.crz(qubit, qubits[1], params[0])?,
+                "cp" | "cphase" | "cu1" => circuit.cp(qubit, qubits[1], params[0])?,
+                "rxx" => circuit.rxx(qubit, qubits[1], params[0])?,
+                "ryy" => circuit.ryy(qubit, qubits[1], params[0])?,
+                "rzz" => circuit.rzz(qubit, qubits[1], params[0])?,
+                "cry" => circuit.cry(qubit, qubits[1], params[0])?,
+                _ => circuit.registered_gate(registry, &name, qubits)?,
+            };
+        }
+
+        Ok(circuit)
+    }
+
+    /// Enables a post-execution check that the final statevector norm is
+    /// within `tolerance` (e.g. `DEFAULT_TOL`) of 1, to catch non-unitary
+    /// custom gates or accumulated rounding. `execute` then warns on stderr
+    /// and the `_checked` variants return an error. Off by default.
+    pub fn check_norm(&mut self, tolerance: f64) -> &mut Self {
+        self.norm_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Makes execution build the circuit's full unitary on first use and
+    /// keep it, so later `execute_from` calls are one matrix-vector product.
+    /// Worth it when a circuit runs from many initial states; the unitary
+    /// costs `2^n` ordinary executions to build. Adding an operation drops
+    /// the cached matrix. Fails above `MAX_CACHED_UNITARY_QUBITS`.
+    pub fn cache_unitary(&mut self) -> Result<&mut Self, QuantumError> {
+        if self.num_qubits > MAX_CACHED_UNITARY_QUBITS {
+            return Err(QuantumError::InvalidParameter(format!(
+                "unitary caching supports at most {} qubits, circuit has {}",
+                MAX_CACHED_UNITARY_QUBITS, self.num_qubits
+            )));
+        }
+        self.unitary_cache.get_or_insert_with(OnceLock::new);
+        Ok(self)
+    }
+
+    /// The unitary built since caching was enabled or an operation was last
+    /// added, if any execution has built it yet.
+    pub fn cached_unitary(&self) -> Option<&DMatrix<Complex64>> {
+        self.unitary_cache.as_ref().and_then(OnceLock::get)
+    }
+
+    pub fn h(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::hadamard(), GateKind::H, qubit)
+    }
+
+    pub fn x(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::pauli_x(), GateKind::X, qubit)
+    }
+
+    pub fn y(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::pauli_y(), GateKind::Y, qubit)
+    }
+
+    pub fn z(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::pauli_z(), GateKind::Z, qubit)
+    }
+
+    pub fn rx(&mut self, qubit: usize, theta: f64) -> &mut Self {
+        self.add_single_gate(Gates::rx(theta), GateKind::Rx(theta), qubit)
+    }
+
+    pub fn ry(&mut self, qubit: usize, theta: f64) -> &mut Self {
+        self.add_single_gate(Gates::ry(theta), GateKind::Ry(theta), qubit)
+    }
+
+    pub fn rz(&mut self, qubit: usize, theta: f64) -> &mut Self {
+        self.add_single_gate(Gates::rz(theta), GateKind::Rz(theta), qubit)
+    }
+
+    pub fn u3(&mut self, qubit: usize, theta: f64, phi: f64, lambda: f64) -> &mut Self {
+        self.add_single_gate(Gates::u3(theta, phi, lambda), GateKind::U3(theta, phi, lambda), qubit)
+    }
+
+    pub fn sx(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::sx(), GateKind::Sx, qubit)
+    }
+
+    pub fn sxdg(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::sxdg(), GateKind::Sxdg, qubit)
+    }
+
+    pub fn s(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::s(), GateKind::S, qubit)
+    }
+
+    pub fn sdg(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::sdg(), GateKind::Sdg, qubit)
+    }
+
+    pub fn t(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::t(), GateKind::T, qubit)
+    }
+
+    pub fn tdg(&mut self, qubit: usize) -> &mut Self {
+        self.add_single_gate(Gates::tdg(), GateKind::Tdg, qubit)
+    }
+
+    pub fn cnot(&mut self, control: usize, target: usize) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::pauli_x(),
+            kind: GateKind::X,
+            control,
+            target,
+            control_value: true,
+        }))
+    }
+
+    /// CNOT that fires when `control` reads `control_value`; `false` flips
+    /// `target` when the control is |0⟩, as oracles often need.
+    pub fn cnot_with_value(
+        &mut self,
+        control: usize,
+        target: usize,
+        control_value: bool,
+    ) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::pauli_x(),
+            kind: GateKind::X,
+            control,
+            target,
+            control_value,
+        }))
+    }
+
+    /// Applies the 2x2 `gate` to `target` when `control` reads
+    /// `control_value`, simulated directly instead of by X gates around an
+    /// ordinary controlled gate.
+    pub fn controlled_gate_with_value(
+        &mut self,
+        gate: DMatrix<Complex64>,
+        control: usize,
+        target: usize,
+        control_value: bool,
+    ) -> Result<&mut Self, QuantumError> {
+        if gate.shape() != (2, 2) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "controlled gate must be 2x2, got {}x{}",
+                gate.nrows(),
+                gate.ncols()
+            )));
+        }
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate,
+            kind: GateKind::Custom,
+            control,
+            target,
+            control_value,
+        }))
+    }
+
+    /// Toffoli: flips `target` when both controls are |1⟩, simulated
+    /// directly rather than decomposed into CNOTs and T gates.
+    pub fn ccx(&mut self, control1: usize, control2: usize, target: usize) -> Result<&mut Self, QuantumError> {
+        self.mcx(&[control1, control2], target)
+    }
+
+    /// Flips `target` when every qubit in `controls` is |1⟩, as one
+    /// operation the kernel checks all control bits for, with no ancillas.
+    /// No controls is a plain X and one control a CNOT.
+    pub fn mcx(&mut self, controls: &[usize], target: usize) -> Result<&mut Self, QuantumError> {
+        let mut qubits = controls.to_vec();
+        qubits.push(target);
+        self.check_distinct_qubits(&qubits)?;
+        Ok(match controls {
+            [] => self.x(target),
+            [control] => self.cnot(*control, target)?,
+            _ => self.add_operation(Operation::MultiControlledGate {
+                gate: Gates::pauli_x(),
+                kind: GateKind::X,
+                controls: controls.iter().map(|&control| (control, true)).collect(),
+                target,
+            }),
+        })
+    }
+
+    /// Applies the 2x2 `gate` to `target` when each `(qubit, value)` in
+    /// `controls` reads `value`, the select-oracle and amplitude
+    /// amplification primitive. No controls is a plain single-qubit gate and
+    /// one control an ordinary controlled gate.
+    pub fn multi_controlled_gate(
+        &mut self,
+        gate: DMatrix<Complex64>,
+        controls: &[(usize, bool)],
+        target: usize,
+    ) -> Result<&mut Self, QuantumError> {
+        if gate.shape() != (2, 2) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "multi-controlled gate must be 2x2, got {}x{}",
+                gate.nrows(),
+                gate.ncols()
+            )));
+        }
+        let mut qubits: Vec<usize> = controls.iter().map(|&(control, _)| control).collect();
+        qubits.push(target);
+        self.check_distinct_qubits(&qubits)?;
+        Ok(match controls {
+            [] => self.add_single_gate(gate, GateKind::Custom, target),
+            [(control, control_value)] => self.controlled_gate_with_value(gate, *control, target, *control_value)?,
+            _ => self.add_operation(Operation::MultiControlledGate {
+                gate,
+                kind: GateKind::Custom,
+                controls: controls.to_vec(),
+                target,
+            }),
+        })
+    }
+
+    pub fn iswap(&mut self, qubit_a: usize, qubit_b: usize) -> Result<&mut Self, QuantumError> {
+        self.add_two_qubit_gate(Gates::iswap(), TwoQubitKind::ISwap, qubit_a, qubit_b)
+    }
+
+    pub fn sqrt_iswap(&mut self, qubit_a: usize, qubit_b: usize) -> Result<&mut Self, QuantumError> {
+        self.add_two_qubit_gate(Gates::sqrt_iswap(), TwoQubitKind::SqrtISwap, qubit_a, qubit_b)
+    }
+
+    /// ECR with `qubit_a` as the low bit of the matrix, i.e. the qubit the
+    /// `IX` term flips, matching Qiskit's `ecr(qubit_a, qubit_b)`.
+    pub fn ecr(&mut self, qubit_a: usize, qubit_b: usize) -> Result<&mut Self, QuantumError> {
+        self.add_two_qubit_gate(Gates::ecr(), TwoQubitKind::Ecr, qubit_a, qubit_b)
+    }
+
+    pub fn rxx(&mut self, qubit_a: usize, qubit_b: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.add_two_qubit_gate(Gates::rxx(theta), TwoQubitKind::Rxx(theta), qubit_a, qubit_b)
+    }
+
+    pub fn ryy(&mut self, qubit_a: usize, qubit_b: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.add_two_qubit_gate(Gates::ryy(theta), TwoQubitKind::Ryy(theta), qubit_a, qubit_b)
+    }
+
+    pub fn rzz(&mut self, qubit_a: usize, qubit_b: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.add_two_qubit_gate(Gates::rzz(theta), TwoQubitKind::Rzz(theta), qubit_a, qubit_b)
+    }
+
+    /// Appends a 4x4 `gate` with `qubit_a` as the low bit of its local index.
+    pub fn two_qubit_gate(
+        &mut self,
+        gate: DMatrix<Complex64>,
+        qubit_a: usize,
+        qubit_b: usize,
+    ) -> Result<&mut Self, QuantumError> {
+        if gate.shape() != (4, 4) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "two-qubit gate must be 4x4, got {}x{}",
+                gate.nrows(),
+                gate.ncols()
+            )));
+        }
+        self.add_two_qubit_gate(gate, TwoQubitKind::Custom, qubit_a, qubit_b)
+    }
+
+    fn add_two_qubit_gate(
+        &mut self,
+        gate: DMatrix<Complex64>,
+        kind: TwoQubitKind,
+        qubit_a: usize,
+        qubit_b: usize,
+    ) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[qubit_a, qubit_b])?;
+        Ok(self.add_operation(Operation::TwoQubitGate {
+            gate,
+            kind,
+            qubits: [qubit_a, qubit_b],
+        }))
+    }
+
+    /// Appends a user-defined `gate` on `qubits`, `qubits[0]` being the low
+    /// bit of its matrix. The gate is validated as in
+    /// `GateRegistry::register`, and `qubits` must match its qubit count.
+    pub fn custom_gate(&mut self, gate: Arc<dyn Gate>, qubits: &[usize]) -> Result<&mut Self, QuantumError> {
+        gate::validate_gate(gate.as_ref())?;
+        self.add_custom_gate(gate, qubits)
+    }
+
+    /// Appends the gate registered under `name` (case-insensitive) in
+    /// `registry` on `qubits`.
+    pub fn registered_gate(
+        &mut self,
+        registry: &GateRegistry,
+        name: &str,
+        qubits: &[usize],
+    ) -> Result<&mut Self, QuantumError> {
+        let gate = registry
+            .get(name)
+            .ok_or_else(|| QuantumError::InvalidParameter(format!("no gate named '{}' is registered", name)))?;
+        self.add_custom_gate(gate, qubits)
+    }
+
+    fn add_custom_gate(&mut self, gate: Arc<dyn Gate>, qubits: &[usize]) -> Result<&mut Self, QuantumError> {
+        if qubits.len() != gate.num_qubits() {
+            return Err(QuantumError::InvalidParameter(format!(
+                "gate '{}' acts on {} qubit(s), got {}",
+                gate.name(),
+                gate.num_qubits(),
+                qubits.len()
+            )));
+        }
+        self.check_distinct_qubits(qubits)?;
+        Ok(self.add_operation(Operation::CustomGate {
+            gate,
+            qubits: qubits.to_vec(),
+        }))
+    }
+
+    /// Fredkin gate: swaps `first` and `second` when `control` is |1⟩, the
+    /// core of swap-test fidelity estimation.
+    pub fn cswap(&mut self, control: usize, first: usize, second: usize) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, first, second])?;
+        Ok(self.add_operation(Operation::ControlledSwap { control, first, second }))
+    }
+
+    /// Controlled-Z. The gate is symmetric, so which qubit is called the
+    /// control only matters for how the circuit prints and exports.
+    pub fn cz(&mut self, control: usize, target: usize) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::pauli_z(),
+            kind: GateKind::Z,
+            control,
+            target,
+            control_value: true,
+        }))
+    }
+
+    /// Controlled phase `diag(1, 1, 1, e^(iθ))`, the workhorse of QFT, QPE
+    /// and QAOA. Symmetric like `cz`, and simulated with a diagonal kernel.
+    pub fn cp(&mut self, control: usize, target: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::phase(theta),
+            kind: GateKind::Phase(theta),
+            control,
+            target,
+            control_value: true,
+        }))
+    }
+
+    pub fn crx(&mut self, control: usize, target: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::rx(theta),
+            kind: GateKind::Rx(theta),
+            control,
+            target,
+            control_value: true,
+        }))
+    }
+
+    pub fn cry(&mut self, control: usize, target: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::ry(theta),
+            kind: GateKind::Ry(theta),
+            control,
+            target,
+            control_value: true,
+        }))
+    }
+
+    pub fn crz(&mut self, control: usize, target: usize, theta: f64) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[control, target])?;
+        Ok(self.add_operation(Operation::ControlledGate {
+            gate: Gates::rz(theta),
+            kind: GateKind::Rz(theta),
+            control,
+            target,
+            control_value: true,
+        }))
+    }
+
+    /// Applies the fixed single-qubit gate `gate_name` (`h x y z s sdg t tdg`,
+    /// case-insensitive) to every qubit in `targets` when `control` is set.
+    /// The targets commute, so this is one controlled gate per target in list
+    /// order.
+    pub fn controlled_multi_target(
+        &mut self,
+        control: usize,
+        gate_name: &str,
+        targets: &[usize],
+    ) -> Result<&mut Self, QuantumError> {
+        let (gate, kind) = match gate_name.to_ascii_lowercase().as_str() {
+            "h" => (Gates::hadamard(), GateKind::H),
+            "x" => (Gates::pauli_x(), GateKind::X),
+            "y" => (Gates::pauli_y(), GateKind::Y),
+            "z" => (Gates::pauli_z(), GateKind::Z),
+            "s" => (Gates::s(), GateKind::S),
+            "sdg" => (Gates::sdg(), GateKind::Sdg),
+            "t" => (Gates::t(), GateKind::T),
+            "tdg" => (Gates::tdg(), GateKind::Tdg),
+            _ => {
+                return Err(QuantumError::InvalidParameter(format!(
+                    "unknown single-qubit gate '{}'",
+                    gate_name
+                )))
+            }
+        };
+        let mut qubits = vec![control];
+        qubits.extend_from_slice(targets);
+        self.check_distinct_qubits(&qubits)?;
+
+        for &target in targets {
+            self.add_operation(Operation::ControlledGate {
+                gate: gate.clone(),
+                kind,
+                control,
+                target,
+                control_value: true,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Prepares the GHZ state `(|0..0⟩ + |1..1⟩) / sqrt(2)` on `qubits`, which
+    /// must start in `|0⟩`: a Hadamard on the first qubit fanned out with CNOTs.
+    pub fn prepare_ghz(&mut self, qubits: &[usize]) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(qubits)?;
+        let Some((&first, rest)) = qubits.split_first() else {
+            return Ok(self);
+        };
+
+        self.h(first);
+        for &qubit in rest {
+            self.cnot(first, qubit)?;
+        }
+        Ok(self)
+    }
+
+    /// Prepares the W state `(|10..0⟩ + |01..0⟩ + ... + |00..1⟩) / sqrt(k)` on
+    /// `qubits`, which must start in `|0⟩`. The excitation is put on the first
+    /// qubit and handed down the list: at step `i` a controlled RY leaves it
+    /// behind with probability `1 / (k - i)` and a CNOT clears the source.
+    pub fn prepare_w_state(&mut self, qubits: &[usize]) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(qubits)?;
+        let Some(&first) = qubits.first() else {
+            return Ok(self);
+        };
+
+        self.x(first);
+        let k = qubits.len();
+        for (i, pair) in qubits.windows(2).enumerate() {
+            let theta = 2.0 * (1.0 / (k - i) as f64).sqrt().acos();
+            self.cry(pair[0], pair[1], theta)?;
+            self.cnot(pair[1], pair[0])?;
+        }
+        Ok(self)
+    }
+
+    /// Controlled `|x⟩ -> |multiplier * x mod modulus⟩` on `register`, the
+    /// building block of modular exponentiation in period finding.
+    pub fn controlled_mod_mul(
+        &mut self,
+        control: usize,
+        register: &[usize],
+        multiplier: usize,
+        modulus: usize,
+    ) -> Result<&mut Self, QuantumError> {
+        let qubits: Vec<usize> = std::iter::once(control).chain(register.iter().copied()).collect();
+        self.check_distinct_qubits(&qubits)?;
+        if modulus == 0 || modulus > 1 << register.len() {
+            return Err(QuantumError::InvalidParameter(format!(
+                "modulus {} does not fit a {}-qubit register",
+                modulus,
+                register.len()
+            )));
+        }
+        if gcd(multiplier, modulus) != 1 {
+            return Err(QuantumError::InvalidParameter(format!(
+                "multiplier {} is not invertible mod {}",
+                multiplier, modulus
+            )));
+        }
+
+        Ok(self.add_operation(Operation::ControlledModMul {
+            control,
+            register: register.to_vec(),
+            multiplier: multiplier % modulus,
+            modulus,
+        }))
+    }
+
+    /// Appends `unitary` applied `power` times, conditioned on `control`: the
+    /// controlled `U^(2^k)` of phase estimation. `unitary` acts on the same
+    /// qubit indices as this circuit and must not touch `control`.
+    ///
+    /// When every gate of `unitary` acts on one qubit, the product is raised
+    /// to `power` by repeated squaring and appended as a single controlled
+    /// gate, keeping the angle for a lone rotation or phase gate. Otherwise
+    /// single-qubit gates gain `control`, controlled gates (CNOT, CRY, ...)
+    /// become doubly controlled via `doubly_controlled`, and the result is
+    /// repeated `power` times. Other operations (multi-controlled gates,
+    /// permutations, modular multiplication, measurements) have no controlled
+    /// form here and are rejected. The global phase `φ` of `unitary` becomes
+    /// `phase(power * φ)` on `control`.
+    pub fn append_controlled_power(
+        &mut self,
+        unitary: &QuantumCircuit,
+        control: usize,
+        power: usize,
+    ) -> Result<&mut Self, QuantumError> {
+        if unitary.num_qubits > self.num_qubits {
+            return Err(QuantumError::InvalidParameter(format!(
+                "{}-qubit unitary does not fit a {}-qubit circuit",
+                unitary.num_qubits, self.num_qubits
+            )));
+        }
+        self.check_distinct_qubits(&[control])?;
+
+        let mut lifted = Vec::with_capacity(unitary.operations.len());
+        for operation in &unitary.operations {
+            for qubit in operation.qubits() {
+                self.check_distinct_qubits(&[control, qubit])?;
+            }
+            match operation {
+                Operation::SingleGate { gate, kind, qubit } => lifted.push(Operation::ControlledGate {
+                    gate: gate.clone(),
+                    kind: *kind,
+                    control,
+                    target: *qubit,
+                    control_value: true,
+                }),
+                Operation::HadamardTransform { qubits } => {
+                    lifted.extend(qubits.iter().map(|&qubit| Operation::ControlledGate {
+                        gate: Gates::hadamard(),
+                        kind: GateKind::H,
+                        control,
+                        target: qubit,
+                        control_value: true,
+                    }));
+                }
+                Operation::ControlledGate {
+                    gate,
+                    control: inner,
+                    target,
+                    control_value,
+                    ..
+                } => lifted.extend(doubly_controlled(gate, control, (*inner, *control_value), *target)),
+                _ => {
+                    return Err(QuantumError::InvalidParameter(format!(
+                        "cannot add a control to an operation on qubits {:?}",
+                        operation.qubits()
+                    )))
+                }
+            }
+        }
+        if power == 0 {
+            return Ok(self);
+        }
+
+        // U's global phase is relative once controlled: `e^(ipφ)` on the
+        // |1⟩ branch of `control`
+        let phase = (power as f64 * unitary.global_phase).rem_euclid(2.0 * PI);
+        if phase != 0.0 {
+            self.add_single_gate(Gates::phase(phase), GateKind::Phase(phase), control);
+        }
+
+        let single_target = match &unitary.operations[..] {
+            [Operation::SingleGate { qubit, .. }, ..] => Some(*qubit),
+            _ => None,
+        }
+        .filter(|&first| {
+            unitary
+                .operations
+                .iter()
+                .all(|op| matches!(op, Operation::SingleGate { qubit, .. } if *qubit == first))
+        });
+        if let Some(target) = single_target {
+            let gates: Vec<(&DMatrix<Complex64>, GateKind)> = lifted
+                .iter()
+                .filter_map(|op| match op {
+                    Operation::ControlledGate { gate, kind, .. } => Some((gate, *kind)),
+                    _ => None,
+                })
+                .collect();
+            let powered = match gates[..] {
+                [(_, kind)] => powered_kind(kind, power),
+                _ => None,
+            };
+            let (gate, kind) = match powered {
+                Some(kind) => (kind.matrix().expect("powered kinds are named gates"), kind),
+                None => {
+                    let product = gates
+                        .iter()
+                        .fold(DMatrix::identity(2, 2), |acc, (gate, _)| *gate * acc);
+                    (matrix_power(&product, power), GateKind::Custom)
+                }
+            };
+            if !is_identity(&gate) {
+                self.add_operation(Operation::ControlledGate {
+                    gate,
+                    kind,
+                    control,
+                    target,
+                    control_value: true,
+                });
+            }
+            return Ok(self);
+        }
+
+        for _ in 0..power {
+            for operation in &lifted {
+                self.add_operation(operation.clone());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Measures `qubit` in `basis` when run through `execute_with_measurements`.
+    pub fn measure(&mut self, qubit: usize, basis: PauliBasis) -> Result<&mut Self, QuantumError> {
+        if qubit >= self.num_qubits {
+            return Err(QuantumError::InvalidQubit {
+                qubit,
+                num_qubits: self.num_qubits,
+            });
+        }
+        Ok(self.add_operation(Operation::Measure { qubit, basis }))
+    }
+
+    /// Relabels qubit `q` as `perm[q]` for everything that follows.
+    pub fn permute(&mut self, perm: &[usize]) -> Result<&mut Self, QuantumError> {
+        validate_permutation(perm, self.num_qubits)?;
+        Ok(self.add_operation(Operation::Permutation { perm: perm.to_vec() }))
+    }
+
+    /// Exchanges qubits `a` and `b`, recorded as a two-element permutation
+    /// and run as an in-place amplitude swap.
+    pub fn swap(&mut self, a: usize, b: usize) -> Result<&mut Self, QuantumError> {
+        self.check_distinct_qubits(&[a, b])?;
+        let mut perm: Vec<usize> = (0..self.num_qubits).collect();
+        perm.swap(a, b);
+        Ok(self.add_operation(Operation::Permutation { perm }))
+    }
+
+    /// Multiplies the whole circuit by `e^(iθ)`. Unobservable on its own,
+    /// but it keeps circuits built from RZ-style gates (`phase(θ)` is
+    /// `e^(iθ/2) RZ(θ)`) exactly equal to the unitary they stand for, so
+    /// amplitudes and `circuit_overlap` agree without a phase fit. Phases
+    /// accumulate modulo 2π.
+    pub fn add_global_phase(&mut self, theta: f64) -> &mut Self {
+        self.global_phase = (self.global_phase + theta).rem_euclid(2.0 * PI);
+        if let Some(cache) = &mut self.unitary_cache {
+            cache.take();
+        }
+        self
+    }
+
+    /// The accumulated global phase `φ` in `[0, 2π)`; see `add_global_phase`.
+    pub fn global_phase(&self) -> f64 {
+        self.global_phase
+    }
+
+    pub fn add_operation(&mut self, operation: Operation) -> &mut Self {
+        self.operations.push(operation);
+        if let Some(cache) = &mut self.unitary_cache {
+            cache.take();
+        }
+        self
+    }
+
+    pub fn execute(&self) -> QuantumState {
+        self.execute_from(QuantumState::new(self.num_qubits))
+    }
+
+    pub fn execute_from(&self, state: QuantumState) -> QuantumState {
+        let state = self.run(state);
+        if let Err(err) = self.validate_norm(&state) {
+            eprintln!("Warning: {}", err);
+        }
+        state
+    }
+
+    /// Like `execute`, but fails if the norm check is enabled and violated.
+    pub fn execute_checked(&self) -> Result<QuantumState, QuantumError> {
+        self.execute_from_checked(QuantumState::new(self.num_qubits))
+    }
+
+    pub fn execute_from_checked(&self, state: QuantumState) -> Result<QuantumState, QuantumError> {
+        let state = self.run(state);
+        self.validate_norm(&state)?;
+        Ok(state)
+    }
+
+    /// Applies the circuit's inverse to `state`, walking the operations
+    /// backwards and inverting each in place, so no inverse circuit is
+    /// built. `execute_inverse_from(execute_from(ψ))` returns `ψ`.
+    pub fn execute_inverse_from(&self, mut state: QuantumState) -> QuantumState {
+        state.apply_global_phase(-self.global_phase);
+        for operation in self.operations.iter().rev() {
+            operation.apply_inverse(&mut state);
+        }
+        if let Err(err) = self.validate_norm(&state) {
+            eprintln!("Warning: {}", err);
+        }
+        state
+    }
+
+    /// Whether `execute_real` can run this circuit.
+    pub fn is_real(&self) -> bool {
+        self.operations.iter().all(Operation::is_real)
+    }
+
+    /// Runs the circuit from `|0..0⟩` on real amplitudes. Fails if any gate
+    /// has a complex entry (RX, RZ, Y, T, ...), since the state would leave
+    /// the real subspace. The global phase is left out, as real amplitudes
+    /// can't carry it.
+    pub fn execute_real(&self) -> Result<RealStateVector, QuantumError> {
+        if let Some(index) = self.operations.iter().position(|operation| !operation.is_real()) {
+            return Err(QuantumError::InvalidParameter(format!(
+                "operation {} has complex entries",
+                index
+            )));
+        }
+
+        let mut state = RealStateVector::new(self.num_qubits);
+        for operation in &self.operations {
+            state.apply_operation(operation);
+        }
+        Ok(state)
+    }
+
+    /// Density-matrix execution with the channels of `noise` applied after
+    /// each operation. Measurements are sampled with `rng` and collapse `ρ`
+    /// onto the observed outcome, so the result is the state conditioned on
+    /// that measurement record.
+    pub fn execute_noisy(&self, noise: &NoiseModel, rng: &mut impl Rng) -> DensityMatrix {
+        let mut rho = DensityMatrix::new(self.num_qubits);
+        for operation in &self.operations {
+            match operation {
+                Operation::Measure { qubit, basis } => {
+                    let basis_change = measurement_basis_change(*basis);
+                    if let Some((to_z, _)) = &basis_change {
+                        rho.apply_operation(&single_gate_operation(to_z, *qubit));
+                    }
+                    rho.measure_qubit(*qubit, rng)
+                        .expect("measured qubit is in the register");
+                    if let Some((_, from_z)) = &basis_change {
+                        rho.apply_operation(&single_gate_operation(from_z, *qubit));
+                    }
+                }
+                other => rho.apply_operation(other),
+            }
+            noise
+                .apply_after(&mut rho, operation)
+                .expect("noise channels are validated when added to the model");
+        }
+        rho
+    }
+
+    /// Density-matrix execution layer by layer (see `layers`), where every
+    /// qubit decoheres under `noise` for the part of each layer it spends
+    /// waiting: the layer lasts as long as its slowest operation, so a qubit
+    /// outside the layer idles for all of it and one in a faster gate for the
+    /// difference. The gates themselves are applied noiselessly.
+    pub fn execute_with_idle_noise(&self, noise: &IdleNoise) -> DensityMatrix {
+        let mut rho = DensityMatrix::new(self.num_qubits);
+
+        for layer in self.layers() {
+            let mut busy = vec![0.0; self.num_qubits];
+            for operation in &layer {
+                rho.apply_operation(operation);
+                let duration = noise.durations().duration(operation);
+                for qubit in operation.qubits() {
+                    busy[qubit] = duration;
+                }
+            }
+
+            let layer_time = busy.iter().copied().fold(0.0, f64::max);
+            for (qubit, time) in busy.into_iter().enumerate() {
+                noise
+                    .apply_idle(&mut rho, qubit, layer_time - time)
+                    .expect("idle channel parameters come from a validated model");
+            }
+        }
+
+        rho
+    }
+
+    /// Runs the circuit sampling every `measure` as it is reached: the state
+    /// collapses onto the observed eigenstate of the measured basis and the
+    /// outcome bits are returned in measurement order.
+    pub fn execute_with_measurements(&self, rng: &mut impl Rng) -> (QuantumState, Vec<u8>) {
+        let mut state = QuantumState::new(self.num_qubits);
+        let mut outcomes = Vec::new();
+
+        for operation in &self.operations {
+            match operation {
+                Operation::Measure { qubit, basis } => {
+                    let basis_change = measurement_basis_change(*basis);
+                    if let Some((to_z, _)) = &basis_change {
+                        state.apply_single_gate(to_z, *qubit);
+                    }
+                    outcomes.push(state.measure_qubit(*qubit, rng));
+                    if let Some((_, from_z)) = &basis_change {
+                        state.apply_single_gate(from_z, *qubit);
+                    }
+                }
+                other => other.apply(&mut state),
+            }
+        }
+        state.apply_global_phase(self.global_phase);
+
+        (state, outcomes)
+    }
+
+    /// Runs the operations before `index` (exclusive) from |00...0⟩, for
+    /// inspecting intermediate states. Indices past the end run everything,
+    /// global phase included.
+    pub fn execute_until(&self, index: usize) -> QuantumState {
+        let mut state = QuantumState::new(self.num_qubits);
+        for operation in self.operations.iter().take(index) {
+            operation.apply(&mut state);
+        }
+        if index >= self.operations.len() {
+            state.apply_global_phase(self.global_phase);
+        }
+        state
+    }
+
+    /// Like `execute`, but also returns `support_size` after each operation,
+    /// tracing how fast the circuit spreads amplitude over the basis: a
+    /// trace that stays small suits a sparse backend.
+    pub fn execute_with_support_trace(&self) -> (QuantumState, Vec<usize>) {
+        let mut state = QuantumState::new(self.num_qubits);
+        let mut trace = Vec::with_capacity(self.operations.len());
+        for operation in &self.operations {
+            operation.apply(&mut state);
+            trace.push(state.support_size());
+        }
+        state.apply_global_phase(self.global_phase);
+        (state, trace)
+    }
+
+    /// Groups operations into parallel layers: each operation is placed in the
+    /// first layer after the last one touching any of its qubits.
+    pub fn layers(&self) -> Vec<Vec<&Operation>> {
+        let mut qubit_levels = vec![0usize; self.num_qubits];
+        let mut layers: Vec<Vec<&Operation>> = Vec::new();
+
+        for operation in &self.operations {
+            let qubits = operation.qubits();
+            let layer = qubits
+                .iter()
+                .map(|&q| qubit_levels.get(q).copied().unwrap_or(0))
+                .max()
+                .unwrap_or(0);
+
+            if layer == layers.len() {
+                layers.push(Vec::new());
+            }
+            layers[layer].push(operation);
+
+            for q in qubits {
+                if let Some(level) = qubit_levels.get_mut(q) {
+                    *level = layer + 1;
+                }
+            }
+        }
+
+        layers
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers().len()
+    }
+
+    /// Average operations per layer, `num_operations / depth`: 1 for a fully
+    /// sequential circuit such as a CNOT chain, up to `n` for a layer of
+    /// single-qubit gates. An empty circuit has parallelism 0.
+    pub fn parallelism(&self) -> f64 {
+        match self.depth() {
+            0 => 0.0,
+            depth => self.operations.len() as f64 / depth as f64,
+        }
+    }
+
+    /// Indices of the operations in the causal past of the measured qubits,
+    /// in circuit order. Everything else cannot influence their statistics.
+    pub fn light_cone(&self, measured: &[usize]) -> Vec<usize> {
+        let mut active: HashSet<usize> = measured.iter().copied().collect();
+        let mut indices = Vec::new();
+
+        for (index, operation) in self.operations.iter().enumerate().rev() {
+            let qubits = operation.qubits();
+            if qubits.iter().any(|q| active.contains(q)) {
+                active.extend(qubits);
+                indices.push(index);
+            }
+        }
+
+        indices.reverse();
+        indices
+    }
+
+    /// Drops every operation outside the light cone of `measured`. The pruned
+    /// circuit has the same marginal distribution on the measured qubits.
+    pub fn prune_for_measurement(&self, measured: &[usize]) -> QuantumCircuit {
+        let operations = self
+            .light_cone(measured)
+            .into_iter()
+            .map(|index| self.operations[index].clone())
+            .collect();
+
+        QuantumCircuit {
+            num_qubits: self.num_qubits,
+            operations,
+            norm_tolerance: self.norm_tolerance,
+            global_phase: self.global_phase,
+            unitary_cache: None,
+        }
+    }
+
+    /// Rewrites the circuit to need fewer full statevector passes.
+    ///
+    /// Single-qubit gates are held back per qubit and multiplied into one 2x2
+    /// matrix until an entangling gate forces them out. A pending gate is only
+    /// flushed if it fails to commute with that entangling gate, so diagonal
+    /// gates on a control (or gates commuting with the target unitary) keep
+    /// accumulating across it. Fused products equal to the identity are
+    /// dropped. A run of Hadamards covering every qubit of a multi-qubit
+    /// register (the opening layer of Grover, Deutsch-Jozsa or
+    /// Bernstein-Vazirani) becomes one `HadamardTransform`. The final state
+    /// is unchanged; this is a simulation pass, not a hardware transpilation.
+    pub fn schedule_for_simulation(&self) -> QuantumCircuit {
+        let mut pending: Vec<Option<DMatrix<Complex64>>> = vec![None; self.num_qubits];
+        let mut scheduled = QuantumCircuit::new(self.num_qubits);
+        scheduled.norm_tolerance = self.norm_tolerance;
+        scheduled.global_phase = self.global_phase;
+
+        let mut index = 0;
+        while let Some(operation) = self.operations.get(index) {
+            if is_hadamard_layer(&self.operations[index..], self.num_qubits) {
+                index += self.num_qubits;
+                let qubits: Vec<usize> = (0..self.num_qubits).collect();
+                for &qubit in &qubits {
+                    scheduled.push_fused(pending[qubit].take(), qubit);
+                }
+                scheduled.add_operation(Operation::HadamardTransform { qubits });
+                continue;
+            }
+            index += 1;
+
+            match operation {
+                Operation::SingleGate { gate, qubit, .. } if *qubit < self.num_qubits => {
+                    let fused = match pending[*qubit].take() {
+                        Some(previous) => gate * previous,
+                        None => gate.clone(),
+                    };
+                    pending[*qubit] = Some(fused);
+                }
+                Operation::ControlledGate {
+                    gate,
+                    control,
+                    target,
+                    ..
+                } => {
+                    if let Some(matrix) = pending.get_mut(*control) {
+                        if matrix.as_ref().is_some_and(|m| !is_diagonal(m)) {
+                            scheduled.push_fused(matrix.take(), *control);
+                        }
+                    }
+                    if let Some(matrix) = pending.get_mut(*target) {
+                        if matrix.as_ref().is_some_and(|m| !commutes(m, gate)) {
+                            scheduled.push_fused(matrix.take(), *target);
+                        }
+                    }
+                    scheduled.add_operation(operation.clone());
+                }
+                other => {
+                    for qubit in other.qubits() {
+                        if let Some(matrix) = pending.get_mut(qubit) {
+                            scheduled.push_fused(matrix.take(), qubit);
+                        }
+                    }
+                    scheduled.add_operation(other.clone());
+                }
+            }
+        }
+
+        for (qubit, matrix) in pending.into_iter().enumerate() {
+            scheduled.push_fused(matrix, qubit);
+        }
+
+        scheduled
+    }
+
+    /// Maps the circuit onto a line of qubits where only neighbours `q` and
+    /// `q + 1` can interact, returning the routed circuit and the number of
+    /// SWAPs inserted.
+    ///
+    /// Logical qubit `q` starts on physical qubit `q`. Before each two-qubit
+    /// gate on non-neighbours the control (or a `TwoQubitGate`'s or
+    /// `CustomGate`'s first qubit) is swapped one step at a time towards the
+    /// other qubit, and it stays where it ends up. Each SWAP is emitted as
+    /// three CNOTs. A closing `Permutation` (free in simulation) returns
+    /// every logical qubit to its own index, so the routed circuit produces
+    /// the same final state as the original. Operations on more than two
+    /// qubits can't be routed and are rejected.
+    pub fn route_linear(&self) -> Result<(QuantumCircuit, usize), QuantumError> {
+        let mut routed = QuantumCircuit::new(self.num_qubits);
+        routed.norm_tolerance = self.norm_tolerance;
+        routed.global_phase = self.global_phase;
+        // position[logical] = physical, logical_at[physical] = logical
+        let mut position: Vec<usize> = (0..self.num_qubits).collect();
+        let mut logical_at = position.clone();
+        let mut swaps = 0;
+        let unroutable = || {
+            QuantumError::InvalidParameter("linear routing only supports one- and two-qubit operations".to_string())
+        };
+
+        for operation in &self.operations {
+            match operation {
+                Operation::SingleGate { gate, kind, qubit } => {
+                    routed.add_operation(Operation::SingleGate {
+                        gate: gate.clone(),
+                        kind: *kind,
+                        qubit: position[*qubit],
+                    });
+                }
+                Operation::ControlledGate {
+                    control: mover,
+                    target: anchor,
+                    ..
+                }
+                | Operation::TwoQubitGate {
+                    qubits: [mover, anchor],
+                    ..
+                } => {
+                    let goal = position[*anchor];
+                    swaps += swap_until_adjacent(&mut routed, &mut position, &mut logical_at, *mover, goal)?;
+                    routed.add_operation(match operation {
+                        Operation::ControlledGate {
+                            gate,
+                            kind,
+                            control_value,
+                            ..
+                        } => Operation::ControlledGate {
+                            gate: gate.clone(),
+                            kind: *kind,
+                            control: position[*mover],
+                            target: goal,
+                            control_value: *control_value,
+                        },
+                        Operation::TwoQubitGate { gate, kind, .. } => Operation::TwoQubitGate {
+                            gate: gate.clone(),
+                            kind: *kind,
+                            qubits: [position[*mover], goal],
+                        },
+                        _ => unreachable!("matched a two-qubit gate above"),
+                    });
+                }
+                Operation::CustomGate { gate, qubits } => {
+                    let qubits = match qubits[..] {
+                        [qubit] => vec![position[qubit]],
+                        [mover, anchor] => {
+                            let goal = position[anchor];
+                            swaps += swap_until_adjacent(&mut routed, &mut position, &mut logical_at, mover, goal)?;
+                            vec![position[mover], goal]
+                        }
+                        _ => return Err(unroutable()),
+                    };
+                    routed.add_operation(Operation::CustomGate {
+                        gate: gate.clone(),
+                        qubits,
+                    });
+                }
+                Operation::Measure { qubit, basis } => {
+                    routed.measure(position[*qubit], *basis)?;
+                }
+                Operation::HadamardTransform { qubits } => {
+                    let qubits = qubits.iter().map(|&qubit| position[qubit]).collect();
+                    routed.add_operation(Operation::HadamardTransform { qubits });
+                }
+                Operation::Permutation { perm } => {
+                    // Only the labels move: logical q becomes logical perm[q]
+                    let previous = position.clone();
+                    for (qubit, &label) in perm.iter().enumerate() {
+                        position[label] = previous[qubit];
+                    }
+                    for (label, &physical) in position.iter().enumerate() {
+                        logical_at[physical] = label;
+                    }
+                }
+                Operation::MultiControlledGate { .. }
+                | Operation::ControlledSwap { .. }
+                | Operation::ControlledModMul { .. } => return Err(unroutable()),
+            }
+        }
+
+        if logical_at.iter().enumerate().any(|(physical, &logical)| physical != logical) {
+            routed.permute(&logical_at)?;
+        }
+        Ok((routed, swaps))
+    }
+
+    /// Merges runs of `rx`, `ry` or `rz` on the same qubit (with nothing else
+    /// touching that qubit in between) into one rotation with the summed angle,
+    /// reduced mod 4π. Rotations that cancel to the identity are removed.
+    pub fn merge_rotations(&self) -> QuantumCircuit {
+        let mut merged: Vec<Option<Operation>> = Vec::with_capacity(self.operations.len());
+        let mut last_on_qubit: Vec<Option<usize>> = vec![None; self.num_qubits];
+
+        for operation in &self.operations {
+            if let Operation::SingleGate { kind, qubit, .. } = operation {
+                let previous = last_on_qubit.get(*qubit).copied().flatten();
+                let combined = previous.and_then(|index| match &merged[index] {
+                    Some(Operation::SingleGate { kind: earlier, .. }) => merge_rotation_kinds(*earlier, *kind),
+                    _ => None,
+                });
+
+                if let (Some(index), Some(kind)) = (previous, combined) {
+                    merged[index] = rotation_operation(kind, *qubit);
+                    if merged[index].is_none() {
+                        last_on_qubit[*qubit] = None;
+                    }
+                    continue;
+                }
+            }
+
+            for qubit in operation.qubits() {
+                if let Some(last) = last_on_qubit.get_mut(qubit) {
+                    *last = Some(merged.len());
+                }
+            }
+            merged.push(Some(operation.clone()));
+        }
+
+        QuantumCircuit {
+            num_qubits: self.num_qubits,
+            operations: merged.into_iter().flatten().collect(),
+            norm_tolerance: self.norm_tolerance,
+            global_phase: self.global_phase,
+            unitary_cache: None,
+        }
+    }
+
+    /// Treats every `rz` as a virtual Z (a frame rotation, as on hardware)
+    /// instead of a statevector pass. Each qubit keeps a frame angle `φ`;
+    /// later gates on it are applied as `RZ(-φ) U RZ(φ)` (controls are left
+    /// alone, Z commutes with them), permutations carry the frames along,
+    /// and non-Z measurements, `HadamardTransform`, `TwoQubitGate`,
+    /// `ControlledSwap` and `ControlledModMul` apply the pending rotations
+    /// first. The frames left over are diagonal, so they don't change
+    /// computational-basis measurement probabilities; they are returned
+    /// rather than applied, and running `rz(q, frames[q])` on every qubit
+    /// afterwards recovers the original final state exactly.
+    pub fn defer_virtual_rz(&self) -> (QuantumCircuit, Vec<f64>) {
+        let period = 4.0 * PI;
+        let mut deferred = QuantumCircuit::new(self.num_qubits);
+        deferred.norm_tolerance = self.norm_tolerance;
+        deferred.global_phase = self.global_phase;
+        let mut frames = vec![0.0; self.num_qubits];
+
+        let flush = |deferred: &mut QuantumCircuit, frames: &mut [f64], qubit: usize| {
+            if let Some(rotation) = rotation_operation(GateKind::Rz(frames[qubit]), qubit) {
+                deferred.add_operation(rotation);
+            }
+            frames[qubit] = 0.0;
+        };
+        let conjugate = |gate: &DMatrix<Complex64>, frame: f64| Gates::rz(-frame) * gate * Gates::rz(frame);
+
+        for operation in &self.operations {
+            match operation {
+                Operation::SingleGate {
+                    kind: GateKind::Rz(angle),
+                    qubit,
+                    ..
+                } => {
+                    frames[*qubit] = (frames[*qubit] + angle).rem_euclid(period);
+                }
+                Operation::SingleGate { gate, qubit, .. } if frames[*qubit] != 0.0 && !is_diagonal(gate) => {
+                    deferred.add_operation(single_gate_operation(&conjugate(gate, frames[*qubit]), *qubit));
+                }
+                Operation::ControlledGate {
+                    gate,
+                    control,
+                    target,
+                    control_value,
+                    ..
+                } if frames[*target] != 0.0 && !is_diagonal(gate) => {
+                    deferred.add_operation(Operation::ControlledGate {
+                        gate: conjugate(gate, frames[*target]),
+                        kind: GateKind::Custom,
+                        control: *control,
+                        target: *target,
+                        control_value: *control_value,
+                    });
+                }
+                Operation::MultiControlledGate {
+                    gate,
+                    controls,
+                    target,
+                    ..
+                } if frames[*target] != 0.0 && !is_diagonal(gate) => {
+                    deferred.add_operation(Operation::MultiControlledGate {
+                        gate: conjugate(gate, frames[*target]),
+                        kind: GateKind::Custom,
+                        controls: controls.clone(),
+                        target: *target,
+                    });
+                }
+                Operation::Permutation { perm } => {
+                    let mut moved = vec![0.0; self.num_qubits];
+                    for (qubit, &destination) in perm.iter().enumerate() {
+                        moved[destination] = frames[qubit];
+                    }
+                    frames = moved;
+                    deferred.add_operation(operation.clone());
+                }
+                Operation::Measure { basis: Pauli::Z, .. } => {
+                    deferred.add_operation(operation.clone());
+                }
+                Operation::Measure { .. }
+                | Operation::HadamardTransform { .. }
+                | Operation::TwoQubitGate { .. }
+                | Operation::ControlledSwap { .. }
+                | Operation::CustomGate { .. }
+                | Operation::ControlledModMul { .. } => {
+                    for qubit in operation.qubits() {
+                        flush(&mut deferred, &mut frames, qubit);
+                    }
+                    deferred.add_operation(operation.clone());
+                }
+                _ => {
+                    deferred.add_operation(operation.clone());
+                }
+            }
+        }
+
+        (deferred, frames)
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn num_operations(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn num_two_qubit_gates(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|operation| is_two_qubit_gate(operation))
+            .count()
+    }
+
+    /// Two-qubit gate count per unordered qubit pair, keyed `(low, high)`:
+    /// the connectivity the circuit asks of the hardware. Operations on
+    /// three or more qubits are not broken down into pairs.
+    pub fn connectivity_graph(&self) -> HashMap<(usize, usize), usize> {
+        let mut edges = HashMap::new();
+        for operation in &self.operations {
+            if !is_two_qubit_gate(operation) {
+                continue;
+            }
+            if let [a, b] = operation.qubits()[..] {
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        edges
+    }
+
+    /// Flattens the circuit into `QirOp`s in execution order. Phase gates
+    /// become `rz` and SX gates `rx(±π/2)`, equal up to a global phase;
+    /// permutations become `swap`s; Hadamard transforms become one `h` per
+    /// qubit; X- and Y-basis measurements are wrapped in the same basis
+    /// changes `execute_with_measurements` uses (H, and RX(±π/2)) around
+    /// `mz`. Controls on |0⟩ are wrapped in `x` gates.
+    pub fn to_qir_ops(&self) -> Vec<QirOp> {
+        let mut ops = Vec::new();
+        for operation in &self.operations {
+            match operation {
+                Operation::SingleGate { gate, kind, qubit } => ops.push(match *kind {
+                    GateKind::H => QirOp::new("h", vec![*qubit], vec![]),
+                    GateKind::X => QirOp::new("x", vec![*qubit], vec![]),
+                    GateKind::Y => QirOp::new("y", vec![*qubit], vec![]),
+                    GateKind::Z => QirOp::new("z", vec![*qubit], vec![]),
+                    GateKind::Sx => QirOp::new("rx", vec![*qubit], vec![PI / 2.0]),
+                    GateKind::Sxdg => QirOp::new("rx", vec![*qubit], vec![-PI / 2.0]),
+                    GateKind::S => QirOp::new("s", vec![*qubit], vec![]),
+                    GateKind::Sdg => QirOp::new("s__adj", vec![*qubit], vec![]),
+                    GateKind::T => QirOp::new("t", vec![*qubit], vec![]),
+                    GateKind::Tdg => QirOp::new("t__adj", vec![*qubit], vec![]),
+                    GateKind::Rx(theta) => QirOp::new("rx", vec![*qubit], vec![theta]),
+                    GateKind::Ry(theta) => QirOp::new("ry", vec![*qubit], vec![theta]),
+                    GateKind::Rz(theta) | GateKind::Phase(theta) => QirOp::new("rz", vec![*qubit], vec![theta]),
+                    GateKind::U3(..) | GateKind::Custom => QirOp::matrix("unitary", vec![*qubit], gate),
+                }),
+                Operation::ControlledGate {
+                    gate,
+                    kind,
+                    control,
+                    target,
+                    control_value,
+                } => {
+                    if !control_value {
+                        ops.push(QirOp::new("x", vec![*control], vec![]));
+                    }
+                    ops.push(match kind {
+                        GateKind::X => QirOp::new("cnot", vec![*control, *target], vec![]),
+                        GateKind::Z => QirOp::new("cz", vec![*control, *target], vec![]),
+                        _ => QirOp::matrix("controlled_unitary", vec![*control, *target], gate),
+                    });
+                    if !control_value {
+                        ops.push(QirOp::new("x", vec![*control], vec![]));
+                    }
+                }
+                Operation::MultiControlledGate {
+                    gate,
+                    kind,
+                    controls,
+                    target,
+                } => {
+                    let flipped: Vec<usize> = controls
+                        .iter()
+                        .filter(|&&(_, value)| !value)
+                        .map(|&(control, _)| control)
+                        .collect();
+                    ops.extend(flipped.iter().map(|&control| QirOp::new("x", vec![control], vec![])));
+                    let mut qubits: Vec<usize> = controls.iter().map(|&(control, _)| control).collect();
+                    qubits.push(*target);
+                    ops.push(match kind {
+                        GateKind::X if controls.len() == 2 => QirOp::new("ccx", qubits, vec![]),
+                        _ => QirOp::matrix("multi_controlled_unitary", qubits, gate),
+                    });
+                    ops.extend(flipped.iter().map(|&control| QirOp::new("x", vec![control], vec![])));
+                }
+                Operation::TwoQubitGate { gate, kind, qubits } => ops.push(match *kind {
+                    TwoQubitKind::Rxx(theta) => QirOp::new("rxx", qubits.to_vec(), vec![theta]),
+                    TwoQubitKind::Ryy(theta) => QirOp::new("ryy", qubits.to_vec(), vec![theta]),
+                    TwoQubitKind::Rzz(theta) => QirOp::new("rzz", qubits.to_vec(), vec![theta]),
+                    _ => QirOp::matrix("two_qubit_unitary", qubits.to_vec(), gate),
+                }),
+                Operation::ControlledSwap { control, first, second } => {
+                    ops.push(QirOp::new("controlled_swap", vec![*control, *first, *second], vec![]));
+                }
+                Operation::CustomGate { gate, qubits } => {
+                    let name = match qubits.len() {
+                        1 => "unitary",
+                        2 => "two_qubit_unitary",
+                        _ => "multi_qubit_unitary",
+                    };
+                    ops.push(QirOp::matrix(name, qubits.clone(), &gate.matrix()));
+                }
+                Operation::Permutation { perm } => {
+                    // at[w] is the original qubit whose content sits on wire w
+                    let mut at: Vec<usize> = (0..perm.len()).collect();
+                    for (qubit, &position) in perm.iter().enumerate() {
+                        let wire = at.iter().position(|&q| q == qubit).expect("every qubit is on some wire");
+                        if wire != position {
+                            ops.push(QirOp::new("swap", vec![wire, position], vec![]));
+                            at.swap(wire, position);
+                        }
+                    }
+                }
+                Operation::ControlledModMul {
+                    control,
+                    register,
+                    multiplier,
+                    modulus,
+                } => {
+                    let mut qubits = vec![*control];
+                    qubits.extend_from_slice(register);
+                    ops.push(QirOp::new(
+                        "controlled_mod_mul",
+                        qubits,
+                        vec![*multiplier as f64, *modulus as f64],
+                    ));
+                }
+                Operation::HadamardTransform { qubits } => {
+                    ops.extend(qubits.iter().map(|&qubit| QirOp::new("h", vec![qubit], vec![])));
+                }
+                Operation::Measure { qubit, basis } => {
+                    let basis_change = match basis {
+                        Pauli::X => Some((("h", vec![]), ("h", vec![]))),
+                        Pauli::Y => Some((("rx", vec![PI / 2.0]), ("rx", vec![-PI / 2.0]))),
+                        Pauli::Z => None,
+                    };
+                    if let Some(((gate, params), _)) = &basis_change {
+                        ops.push(QirOp::new(gate, vec![*qubit], params.clone()));
+                    }
+                    ops.push(QirOp::new("mz", vec![*qubit], vec![]));
+                    if let Some((_, (gate, params))) = basis_change {
+                        ops.push(QirOp::new(gate, vec![*qubit], params));
+                    }
+                }
+            }
+        }
+        ops
+    }
+
+    /// Hash of the qubit count and operation sequence (kinds, qubits, angles
+    /// and matrix entries), for keying caches or deduplicating circuits in a
+    /// sweep. Structurally identical circuits hash equal; floats are hashed
+    /// bitwise, so numerically equal but differently rounded angles do not.
+    /// Stable within a build, not across Rust releases.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.num_qubits.hash(&mut hasher);
+        self.global_phase.to_bits().hash(&mut hasher);
+        for operation in &self.operations {
+            std::mem::discriminant(operation).hash(&mut hasher);
+            match operation {
+                Operation::SingleGate { gate, kind, qubit } => {
+                    hash_gate(gate, kind, &mut hasher);
+                    qubit.hash(&mut hasher);
+                }
+                Operation::ControlledGate {
+                    gate,
+                    kind,
+                    control,
+                    target,
+                    control_value,
+                } => {
+                    hash_gate(gate, kind, &mut hasher);
+                    (control, target, control_value).hash(&mut hasher);
+                }
+                Operation::MultiControlledGate {
+                    gate,
+                    kind,
+                    controls,
+                    target,
+                } => {
+                    hash_gate(gate, kind, &mut hasher);
+                    (controls, target).hash(&mut hasher);
+                }
+                Operation::TwoQubitGate { gate, kind, qubits } => {
+                    std::mem::discriminant(kind).hash(&mut hasher);
+                    for entry in gate.iter() {
+                        (entry.re.to_bits(), entry.im.to_bits()).hash(&mut hasher);
+                    }
+                    qubits.hash(&mut hasher);
+                }
+                Operation::ControlledSwap { control, first, second } => (control, first, second).hash(&mut hasher),
+                Operation::CustomGate { gate, qubits } => {
+                    gate.name().hash(&mut hasher);
+                    for entry in gate.matrix().iter() {
+                        (entry.re.to_bits(), entry.im.to_bits()).hash(&mut hasher);
+                    }
+                    qubits.hash(&mut hasher);
+                }
+                Operation::Permutation { perm } => perm.hash(&mut hasher),
+                Operation::ControlledModMul {
+                    control,
+                    register,
+                    multiplier,
+                    modulus,
+                } => (control, register, multiplier, modulus).hash(&mut hasher),
+                Operation::HadamardTransform { qubits } => qubits.hash(&mut hasher),
+                Operation::Measure { qubit, basis } => (qubit, basis).hash(&mut hasher),
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Number of T and T† gates, the dominant cost in fault-tolerant
+    /// execution. Fused or custom gates are not decomposed, so this only sees
+    /// gates added through `t`/`tdg`.
+    pub fn t_count(&self) -> usize {
+        self.operations.iter().filter(|operation| is_t_gate(operation)).count()
+    }
+
+    /// Largest number of T/T† gates on any causal path through the circuit,
+    /// i.e. the number of T layers when everything else is scheduled as early
+    /// as possible.
+    pub fn t_depth(&self) -> usize {
+        self.depth_counting(is_t_gate)
+    }
+
+    /// Largest number of two-qubit gates on any causal path, the entangling
+    /// layers a hardware run can't avoid. Single-qubit gates in between don't
+    /// add to it.
+    pub fn two_qubit_depth(&self) -> usize {
+        self.depth_counting(is_two_qubit_gate)
+    }
+
+    /// Longest causal path weighted by the operations `counts` accepts; the
+    /// others still order the path but add nothing to it.
+    fn depth_counting(&self, counts: impl Fn(&Operation) -> bool) -> usize {
+        let mut levels = vec![0usize; self.num_qubits];
+
+        for operation in &self.operations {
+            let qubits = operation.qubits();
+            let level = qubits
+                .iter()
+                .map(|&q| levels.get(q).copied().unwrap_or(0))
+                .max()
+                .unwrap_or(0)
+                + usize::from(counts(operation));
+
+            for q in qubits {
+                if let Some(qubit_level) = levels.get_mut(q) {
+                    *qubit_level = level;
+                }
+            }
+        }
+
+        levels.into_iter().max().unwrap_or(0)
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    fn run(&self, mut state: QuantumState) -> QuantumState {
+        if let Some(cache) = &self.unitary_cache {
+            let unitary = cache.get_or_init(|| self.build_unitary());
+            return QuantumState {
+                amplitudes: unitary * state.amplitudes,
+                num_qubits: self.num_qubits,
+                global_phase: (state.global_phase + self.global_phase).rem_euclid(2.0 * PI),
+            };
+        }
+
+        for operation in &self.operations {
+            operation.apply(&mut state);
+        }
+        state.apply_global_phase(self.global_phase);
+        state
+    }
+
+    /// Column `i` is the circuit run from basis state `|i⟩`. Measurements
+    /// are no-ops in plain execution, so this is the whole circuit.
+    fn build_unitary(&self) -> DMatrix<Complex64> {
+        let dimension = 1 << self.num_qubits;
+        let columns: Vec<DVector<Complex64>> = (0..dimension)
+            .into_par_iter()
+            .map(|index| {
+                let mut amplitudes = DVector::zeros(dimension);
+                amplitudes[index] = Complex64::new(1.0, 0.0);
+                let mut state = QuantumState {
+                    amplitudes,
+                    num_qubits: self.num_qubits,
+                    global_phase: 0.0,
+                };
+                for operation in &self.operations {
+                    operation.apply(&mut state);
+                }
+                state.apply_global_phase(self.global_phase);
+                state.amplitudes
+            })
+            .collect();
+        DMatrix::from_columns(&columns)
+    }
+
+    fn validate_norm(&self, state: &QuantumState) -> Result<(), QuantumError> {
+        let Some(tolerance) = self.norm_tolerance else {
+            return Ok(());
+        };
+
+        let norm = state.amplitudes().norm();
+        if norm.is_nan() || (norm - 1.0).abs() > tolerance {
+            return Err(QuantumError::InvalidState(format!(
+                "final state has norm {} (tolerance {:e})",
+                norm, tolerance
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that the qubits of one multi-qubit operation are in range and
+    /// pairwise distinct, so a repeated index fails when the circuit is built
+    /// rather than corrupting the state when it runs.
+    fn check_distinct_qubits(&self, qubits: &[usize]) -> Result<(), QuantumError> {
+        let mut seen = HashSet::new();
+        for &qubit in qubits {
+            if qubit >= self.num_qubits {
+                return Err(QuantumError::InvalidQubit {
+                    qubit,
+                    num_qubits: self.num_qubits,
+                });
+            }
+            if !seen.insert(qubit) {
+                return Err(QuantumError::DuplicateQubit { qubit });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_single_gate(&mut self, gate: DMatrix<Complex64>, kind: GateKind, qubit: usize) -> &mut Self {
+        self.add_operation(Operation::SingleGate { gate, kind, qubit })
+    }
+
+    fn push_fused(&mut self, gate: Option<DMatrix<Complex64>>, qubit: usize) {
+        if let Some(gate) = gate {
+            if !is_identity(&gate) {
+                self.add_single_gate(gate, GateKind::Custom, qubit);
+            }
+        }
+    }
+}
+
+/// Most frequent outcome in a [`QuantumState::sample_counts`] tally, with
+/// ties going to the lowest basis index to match
+/// [`QuantumState::most_likely_outcome`]. `None` for an empty tally.
+pub fn most_frequent_outcome(counts: &BTreeMap<usize, usize>) -> Option<usize> {
+    counts
+        .iter()
+        .fold(None, |best: Option<(usize, usize)>, (&index, &count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((index, count)),
+        })
+        .map(|(index, _)| index)
+}
+
+/// Least-squares fit of randomized-benchmarking survival probabilities to
+/// `A p^m + B`, returning the decay `p` and the sum of squared residuals. The
+/// average error per Clifford follows as `r = (1 - p)(d - 1) / d` with
+/// `d = 2^n`.
+///
+/// For a fixed `p` the model is linear in `A` and `B`, which are solved in
+/// closed form, so only `p` is searched: a grid over `[0, 1]` followed by a
+/// golden-section refinement around the best grid point. Panics if the
+/// slices differ in length or hold fewer than three points.
+pub fn fit_rb_decay(lengths: &[usize], survival: &[f64]) -> (f64, f64) {
+    assert_eq!(lengths.len(), survival.len(), "one survival value per sequence length");
+    assert!(lengths.len() >= 3, "fitting A p^m + B needs at least three points");
+
+    let residual = |p: f64| -> f64 {
+        let xs: Vec<f64> = lengths.iter().map(|&m| p.powi(m as i32)).collect();
+        let count = xs.len() as f64;
+        let mean_x = xs.iter().sum::<f64>() / count;
+        let mean_y = survival.iter().sum::<f64>() / count;
+        let var_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+        let cov: f64 = xs.iter().zip(survival).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let a = if var_x > 0.0 { cov / var_x } else { 0.0 };
+        let b = mean_y - a * mean_x;
+        xs.iter().zip(survival).map(|(x, y)| (a * x + b - y).powi(2)).sum()
+    };
+
+    const GRID: usize = 1000;
+    let best = (0..=GRID)
+        .map(|k| k as f64 / GRID as f64)
+        .min_by(|&a, &b| residual(a).total_cmp(&residual(b)))
+        .expect("grid is non-empty");
+
+    let inv_phi = (5f64.sqrt() - 1.0) / 2.0;
+    let step = 1.0 / GRID as f64;
+    let (mut low, mut high) = ((best - step).max(0.0), (best + step).min(1.0));
+    for _ in 0..100 {
+        let left = high - inv_phi * (high - low);
+        let right = low + inv_phi * (high - low);
+        if residual(left) < residual(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    let p = (low + high) / 2.0;
+    (p, residual(p))
+}
+
+/// `⟨ψ_a|ψ_b⟩` for the states `a` and `b` prepare from `|0...0⟩`, e.g. an
+/// optimized circuit against its reference (`|overlap|² = 1` when they agree
+/// up to global phase). Fails if the qubit counts differ.
+pub fn circuit_overlap(a: &QuantumCircuit, b: &QuantumCircuit) -> Result<Complex64, QuantumError> {
+    if a.num_qubits != b.num_qubits {
+        return Err(QuantumError::InvalidParameter(format!(
+            "cannot overlap a {}-qubit circuit with a {}-qubit circuit",
+            a.num_qubits, b.num_qubits
+        )));
+    }
+    Ok(a.execute().amplitudes().dotc(b.execute().amplitudes()))
+}
+
+/// Unit phase `e^(iφ)` with `b = e^(iφ) a`, if the two states differ only by
+/// a global phase, else `None`. Useful when an exact amplitude comparison
+/// fails even though the states are physically the same. Agreement is
+/// checked to `DEFAULT_TOL` (relative to the norm of `b`).
+pub fn global_phase_difference(a: &QuantumState, b: &QuantumState) -> Option<Complex64> {
+    global_phase_difference_with_tolerance(a, b, DEFAULT_TOL)
+}
+
+/// Like `global_phase_difference`, with residual `‖b - e^(iφ) a‖` allowed up
+/// to `tolerance` times the norm of `b`.
+pub fn global_phase_difference_with_tolerance(
+    a: &QuantumState,
+    b: &QuantumState,
+    tolerance: f64,
+) -> Option<Complex64> {
+    if a.num_qubits() != b.num_qubits() {
+        return None;
+    }
+
+    let overlap = a.amplitudes().dotc(b.amplitudes());
+    if overlap.norm() < 1e-12 {
+        return None;
+    }
+    let phase = overlap / overlap.norm();
+
+    let residual = (b.amplitudes() - a.amplitudes() * phase).norm();
+    (residual <= tolerance * b.amplitudes().norm().max(1.0)).then_some(phase)
+}
+
+/// Whether `matrix` is square with every entry of `U†U - I` at most
+/// `tolerance` in magnitude (typically `DEFAULT_TOL`).
+pub fn is_unitary(matrix: &DMatrix<Complex64>, tolerance: f64) -> bool {
+    if !matrix.is_square() {
+        return false;
+    }
+    let product = matrix.adjoint() * matrix;
+    product.iter().enumerate().all(|(index, entry)| {
+        let expected = if index % matrix.nrows() == index / matrix.nrows() { 1.0 } else { 0.0 };
+        (entry - Complex64::new(expected, 0.0)).norm() <= tolerance
+    })
+}
+
+/// Lists every `(row, col)` where `actual` and `expected` differ by more than
+/// `tolerance`, one entry per line, or `None` when the matrices agree. A shape
+/// mismatch is reported on its own.
+pub fn gate_mismatch(actual: &DMatrix<Complex64>, expected: &DMatrix<Complex64>, tolerance: f64) -> Option<String> {
+    if actual.shape() != expected.shape() {
+        return Some(format!(
+            "shape {:?} differs from expected {:?}",
+            actual.shape(),
+            expected.shape()
+        ));
+    }
+
+    let entries: Vec<String> = (0..actual.nrows())
+        .flat_map(|row| (0..actual.ncols()).map(move |col| (row, col)))
+        .filter(|&(row, col)| (actual[(row, col)] - expected[(row, col)]).norm() > tolerance)
+        .map(|(row, col)| {
+            format!(
+                "  ({}, {}): got {}, expected {}",
+                row,
+                col,
+                actual[(row, col)],
+                expected[(row, col)]
+            )
+        })
+        .collect();
+    (!entries.is_empty()).then(|| entries.join("\n"))
+}
+
+/// Panics, listing the differing entries, unless `actual` matches `expected`
+/// entrywise to within `tolerance`. See also the `assert_gate_eq!` macro.
+pub fn assert_gate_eq(actual: &DMatrix<Complex64>, expected: &DMatrix<Complex64>, tolerance: f64) {
+    if let Some(report) = gate_mismatch(actual, expected, tolerance) {
+        panic!("gate matrices differ beyond {:e}:\n{}", tolerance, report);
+    }
+}
+
+/// `assert_gate_eq!(actual, expected)` compares to within `DEFAULT_TOL`;
+/// `assert_gate_eq!(actual, expected, tol)` sets the tolerance.
+#[macro_export]
+macro_rules! assert_gate_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_gate_eq(&$actual, &$expected, $crate::DEFAULT_TOL)
+    };
+    ($actual:expr, $expected:expr, $tolerance:expr $(,)?) => {
+        $crate::assert_gate_eq(&$actual, &$expected, $tolerance)
+    };
+}
+
+/// Character order of the bitstrings produced by `counts_to_bitstrings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Qubit 0 is the rightmost character, as in Qiskit's `get_counts()`.
+    QubitZeroRight,
+    /// Qubit 0 is the leftmost character.
+    QubitZeroLeft,
+}
+
+/// Re-keys a measurement tally from packed basis indices to `num_qubits`
+/// character bitstrings (e.g. `"0101"`), for direct comparison with counts
+/// from tools that key by bitstring. Accepts a `HashMap` as well as the
+/// `BTreeMap` from `QuantumState::sample_counts`.
+pub fn counts_to_bitstrings<'a>(
+    counts: impl IntoIterator<Item = (&'a usize, &'a usize)>,
+    num_qubits: usize,
+    order: BitOrder,
+) -> HashMap<String, usize> {
+    counts
+        .into_iter()
+        .map(|(&index, &count)| {
+            let bits = (0..num_qubits).map(|qubit| if (index >> qubit) & 1 == 1 { '1' } else { '0' });
+            let key = match order {
+                BitOrder::QubitZeroRight => bits.rev().collect(),
+                BitOrder::QubitZeroLeft => bits.collect(),
+            };
+            (key, count)
+        })
+        .collect()
+}
+
+/// Largest per-gate error rate `ε` that keeps a circuit of `num_gates` gates
+/// above `target_fidelity`, assuming independent errors so that the circuit
+/// fidelity is `(1 - ε)^num_gates`. Solving gives `ε = 1 - F^(1/num_gates)`.
+///
+/// An empty circuit or a target of zero tolerates any error (returns 1.0); a
+/// target of one or more tolerates none (returns 0.0).
+pub fn max_gate_error_for_fidelity(num_gates: usize, target_fidelity: f64) -> f64 {
+    if num_gates == 0 || target_fidelity <= 0.0 {
+        return 1.0;
+    }
+    if target_fidelity >= 1.0 {
+        return 0.0;
+    }
+
+    1.0 - target_fidelity.powf(1.0 / num_gates as f64)
+}
+
+/// Two-CNOT decomposition of a controlled rotation about `axis` (`'x'`,
+/// `'y'` or `'z'`, either case) for hardware without native controlled
+/// rotations: `R(θ/2)` on the target, CNOT, `R(-θ/2)`, CNOT. Conjugating by X
+/// flips the sign of a Y or Z rotation, so the halves add up when the control
+/// is set and cancel when it isn't. X rotations commute with X, so CRX is
+/// done as CRZ between Hadamards on the target.
+///
+/// Panics on any other axis.
+pub fn decompose_controlled_rotation(axis: char, theta: f64, control: usize, target: usize) -> Vec<Operation> {
+    let half = |angle: f64| match axis.to_ascii_lowercase() {
+        'y' => Operation::SingleGate {
+            gate: Gates::ry(angle),
+            kind: GateKind::Ry(angle),
+            qubit: target,
+        },
+        'x' | 'z' => Operation::SingleGate {
+            gate: Gates::rz(angle),
+            kind: GateKind::Rz(angle),
+            qubit: target,
+        },
+        other => panic!("unknown rotation axis '{}'", other),
+    };
+    let cnot = || Operation::ControlledGate {
+        gate: Gates::pauli_x(),
+        kind: GateKind::X,
+        control,
+        target,
+        control_value: true,
+    };
+    let hadamard = || Operation::SingleGate {
+        gate: Gates::hadamard(),
+        kind: GateKind::H,
+        qubit: target,
+    };
+
+    let mut operations = vec![half(theta / 2.0), cnot(), half(-theta / 2.0), cnot()];
+    if axis.eq_ignore_ascii_case(&'x') {
+        operations.insert(0, hadamard());
+        operations.push(hadamard());
+    }
+    operations
+}
+
+fn hash_gate(gate: &DMatrix<Complex64>, kind: &GateKind, hasher: &mut DefaultHasher) {
+    std::mem::discriminant(kind).hash(hasher);
+    if let GateKind::Rx(theta) | GateKind::Ry(theta) | GateKind::Rz(theta) | GateKind::Phase(theta) = kind {
+        theta.to_bits().hash(hasher);
+    }
+    if let GateKind::U3(theta, phi, lambda) = kind {
+        (theta.to_bits(), phi.to_bits(), lambda.to_bits()).hash(hasher);
+    }
+    for entry in gate.iter() {
+        (entry.re.to_bits(), entry.im.to_bits()).hash(hasher);
+    }
+}
+
+/// Moves logical qubit `mover` one physical step at a time until it sits
+/// next to physical qubit `goal`, emitting each SWAP as three CNOTs and
+/// updating both layout maps. Returns the number of SWAPs.
+fn swap_until_adjacent(
+    routed: &mut QuantumCircuit,
+    position: &mut [usize],
+    logical_at: &mut [usize],
+    mover: usize,
+    goal: usize,
+) -> Result<usize, QuantumError> {
+    let mut swaps = 0;
+    while position[mover].abs_diff(goal) > 1 {
+        let from = position[mover];
+        let to = if from < goal { from + 1 } else { from - 1 };
+        routed.cnot(from, to)?.cnot(to, from)?.cnot(from, to)?;
+        logical_at.swap(from, to);
+        position[logical_at[from]] = from;
+        position[logical_at[to]] = to;
+        swaps += 1;
+    }
+    Ok(swaps)
+}
+
+/// A `HadamardTransform` touching two qubits is still a product of
+/// single-qubit gates, so it doesn't count as a two-qubit gate.
+fn is_two_qubit_gate(operation: &Operation) -> bool {
+    !matches!(operation, Operation::HadamardTransform { .. }) && operation.qubits().len() == 2
+}
+
+/// Whether `operations` opens with `num_qubits` Hadamards on distinct qubits,
+/// i.e. a full layer `schedule_for_simulation` can run as one transform.
+fn is_hadamard_layer(operations: &[Operation], num_qubits: usize) -> bool {
+    if num_qubits < 2 || operations.len() < num_qubits {
+        return false;
+    }
+
+    let mut seen = vec![false; num_qubits];
+    operations[..num_qubits].iter().all(|operation| match operation {
+        Operation::SingleGate {
+            kind: GateKind::H,
+            qubit,
+            ..
+        } => *qubit < num_qubits && !std::mem::replace(&mut seen[*qubit], true),
+        _ => false,
+    })
+}
+
+fn validate_permutation(perm: &[usize], num_qubits: usize) -> Result<(), QuantumError> {
+    if perm.len() != num_qubits {
+        return Err(QuantumError::InvalidParameter(format!(
+            "permutation has {} entries for a {}-qubit register",
+            perm.len(),
+            num_qubits
+        )));
+    }
+
+    let mut seen = vec![false; num_qubits];
+    for &position in perm {
+        if position >= num_qubits {
+            return Err(QuantumError::InvalidQubit {
+                qubit: position,
+                num_qubits,
+            });
+        }
+        if std::mem::replace(&mut seen[position], true) {
+            return Err(QuantumError::DuplicateQubit { qubit: position });
+        }
+    }
+
+    Ok(())
+}
+
+/// The two qubits exchanged by `perm` if it is a single transposition, so
+/// it can run as an in-place SWAP instead of a full relabelling pass.
+fn transposition(perm: &[usize]) -> Option<(usize, usize)> {
+    let mut moved = perm
+        .iter()
+        .enumerate()
+        .filter(|(qubit, position)| qubit != *position);
+    let (a, &b) = moved.next()?;
+    (perm[b] == a && moved.count() == 1).then_some((a, b))
+}
+
+/// Sum of two rotations about the same axis, or `None` if they can't be merged.
+fn merge_rotation_kinds(first: GateKind, second: GateKind) -> Option<GateKind> {
+    let period = 4.0 * std::f64::consts::PI;
+    match (first, second) {
+        (GateKind::Rx(a), GateKind::Rx(b)) => Some(GateKind::Rx((a + b).rem_euclid(period))),
+        (GateKind::Ry(a), GateKind::Ry(b)) => Some(GateKind::Ry((a + b).rem_euclid(period))),
+        (GateKind::Rz(a), GateKind::Rz(b)) => Some(GateKind::Rz((a + b).rem_euclid(period))),
+        _ => None,
+    }
+}
+
+/// Kind of `kind` applied `power` times, for the gates whose powers stay in
+/// the same family by scaling the angle.
+fn powered_kind(kind: GateKind, power: usize) -> Option<GateKind> {
+    let scale = power as f64;
+    match kind {
+        GateKind::Rx(angle) => Some(GateKind::Rx(angle * scale)),
+        GateKind::Ry(angle) => Some(GateKind::Ry(angle * scale)),
+        GateKind::Rz(angle) => Some(GateKind::Rz(angle * scale)),
+        GateKind::Phase(angle) => Some(GateKind::Phase(angle * scale)),
+        _ => None,
+    }
+}
+
+/// `gate` on `target` controlled by both `outer` and `inner`, from singly
+/// controlled gates (Barenco et al.): `C_inner V`, `CX(outer, inner)`,
+/// `C_inner V†`, `CX(outer, inner)`, `C_outer V` with `V² = U`. The `V`
+/// factors cancel unless `outer` is set and `inner` holds its control value.
+fn doubly_controlled(
+    gate: &DMatrix<Complex64>,
+    outer: usize,
+    (inner, inner_value): (usize, bool),
+    target: usize,
+) -> Vec<Operation> {
+    let root = unitary_sqrt(gate);
+    let controlled = |gate: DMatrix<Complex64>, control: usize, control_value: bool| Operation::ControlledGate {
+        gate,
+        kind: GateKind::Custom,
+        control,
+        target,
+        control_value,
+    };
+    let flip = || Operation::ControlledGate {
+        gate: Gates::pauli_x(),
+        kind: GateKind::X,
+        control: outer,
+        target: inner,
+        control_value: true,
+    };
+
+    vec![
+        controlled(root.clone(), inner, inner_value),
+        flip(),
+        controlled(root.adjoint(), inner, inner_value),
+        flip(),
+        controlled(root, outer, true),
+    ]
+}
+
+/// A square root of a 2x2 unitary: `(U + sI) / √(tr U + 2s)` with
+/// `s² = det U`, taking the sign of `s` that keeps the denominator away from
+/// zero (Cayley-Hamilton makes its square `U`).
+fn unitary_sqrt(matrix: &DMatrix<Complex64>) -> DMatrix<Complex64> {
+    let trace = matrix[(0, 0)] + matrix[(1, 1)];
+    let det = matrix[(0, 0)] * matrix[(1, 1)] - matrix[(0, 1)] * matrix[(1, 0)];
+    let root_det = det.sqrt();
+    let s = if (trace + 2.0 * root_det).norm() >= (trace - 2.0 * root_det).norm() {
+        root_det
+    } else {
+        -root_det
+    };
+    let scale = (trace + 2.0 * s).sqrt();
+    (matrix + DMatrix::identity(2, 2) * s).map(|entry| entry / scale)
+}
+
+/// `matrix^power` by repeated squaring.
+fn matrix_power(matrix: &DMatrix<Complex64>, mut power: usize) -> DMatrix<Complex64> {
+    let mut result = DMatrix::identity(matrix.nrows(), matrix.ncols());
+    let mut base = matrix.clone();
+    while power > 0 {
+        if power & 1 == 1 {
+            result = &result * &base;
+        }
+        base = &base * &base;
+        power >>= 1;
+    }
+    result
+}
+
+/// Builds the operation for a merged rotation, or `None` if the angle is a
+/// multiple of 4π and the rotation is exactly the identity.
+fn rotation_operation(kind: GateKind, qubit: usize) -> Option<Operation> {
+    let period = 4.0 * std::f64::consts::PI;
+    let (gate, angle) = match kind {
+        GateKind::Rx(angle) => (Gates::rx(angle), angle),
+        GateKind::Ry(angle) => (Gates::ry(angle), angle),
+        GateKind::Rz(angle) => (Gates::rz(angle), angle),
+        _ => return None,
+    };
+
+    if angle.abs() < 1e-12 || (period - angle).abs() < 1e-12 {
+        None
+    } else {
+        Some(Operation::SingleGate { gate, kind, qubit })
+    }
+}
+
+/// Image of basis state `index` under [`Operation::ControlledModMul`].
+pub(crate) fn mod_mul_index(index: usize, control: usize, register: &[usize], multiplier: usize, modulus: usize) -> usize {
+    if (index >> control) & 1 == 0 {
+        return index;
+    }
+
+    let value = register
+        .iter()
+        .enumerate()
+        .fold(0usize, |acc, (bit, &q)| acc | (((index >> q) & 1) << bit));
+    if value >= modulus {
+        return index;
+    }
+
+    let product = ((value as u128 * multiplier as u128) % modulus as u128) as usize;
+    register.iter().enumerate().fold(index, |acc, (bit, &q)| {
+        (acc & !(1 << q)) | (((product >> bit) & 1) << q)
+    })
+}
+
+/// Rotation taking `basis` to the computational basis and its inverse, or
+/// `None` for Z.
+fn measurement_basis_change(basis: PauliBasis) -> Option<(DMatrix<Complex64>, DMatrix<Complex64>)> {
+    match basis {
+        Pauli::X => Some((Gates::hadamard(), Gates::hadamard())),
+        Pauli::Y => Some((Gates::rx(PI / 2.0), Gates::rx(-PI / 2.0))),
+        Pauli::Z => None,
+    }
+}
+
+fn single_gate_operation(gate: &DMatrix<Complex64>, qubit: usize) -> Operation {
+    Operation::SingleGate {
+        gate: gate.clone(),
+        kind: GateKind::Custom,
+        qubit,
+    }
+}
+
+fn is_t_gate(operation: &Operation) -> bool {
+    matches!(
+        operation,
+        Operation::SingleGate {
+            kind: GateKind::T | GateKind::Tdg,
+            ..
+        }
+    )
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Spreads `value` around a new zero bit at `position`: bits below stay put,
+/// bits at or above it move up by one.
+pub(crate) fn insert_zero_bit(value: usize, position: usize) -> usize {
+    let low_mask = (1usize << position) - 1;
+    ((value & !low_mask) << 1) | (value & low_mask)
+}
+
+fn is_diagonal(matrix: &DMatrix<Complex64>) -> bool {
+    matrix[(0, 1)].norm() < 1e-12 && matrix[(1, 0)].norm() < 1e-12
+}
+
+fn is_identity(matrix: &DMatrix<Complex64>) -> bool {
+    is_diagonal(matrix)
+        && (matrix[(0, 0)] - Complex64::new(1.0, 0.0)).norm() < 1e-12
+        && (matrix[(1, 1)] - Complex64::new(1.0, 0.0)).norm() < 1e-12
+}
+
+fn commutes(a: &DMatrix<Complex64>, b: &DMatrix<Complex64>) -> bool {
+    (a * b - b * a).iter().all(|entry| entry.norm() < 1e-12)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f64::consts::PI;
+
+    fn assert_probabilities_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-10, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    fn assert_states_close(actual: &QuantumState, expected: &QuantumState) {
+        let difference = (actual.amplitudes() - expected.amplitudes()).norm();
+        assert!(difference < 1e-10, "states differ by {}", difference);
+    }
+
+    #[test]
+    fn pruning_preserves_measured_marginals() {
+        let mut circuit = QuantumCircuit::new(5);
+        circuit.h(0).ry(1, 0.7).rx(3, 1.1).h(4);
+        circuit.cnot(0, 1).unwrap();
+        circuit.rz(2, 0.4).rz(4, 0.9);
+        circuit.cnot(4, 2).unwrap();
+
+        let measured = [1, 2];
+        let pruned = circuit.prune_for_measurement(&measured);
+        assert!(pruned.operations.len() < circuit.operations.len());
+
+        let full = circuit.execute().marginal_probabilities(&measured).unwrap();
+        let reduced = pruned.execute().marginal_probabilities(&measured).unwrap();
+        assert_probabilities_close(&reduced, &full);
+    }
+
+    #[test]
+    fn from_fn_normalises_a_geometric_series() {
+        let ratio = Complex64::new(0.5, 0.5);
+        let state = QuantumState::from_fn(3, |i| ratio.powu(i as u32)).unwrap();
+
+        let norm: f64 = state.amplitudes().iter().map(|a| a.norm_sqr()).sum();
+        assert!((norm - 1.0).abs() < 1e-12);
+        for i in 1..8 {
+            let step = state.amplitudes()[i] / state.amplitudes()[i - 1];
+            assert!((step - ratio).norm() < 1e-12);
+        }
+        assert!(QuantumState::from_fn(2, |_| Complex64::new(0.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn ghz_cnot_chain_is_sequential() {
+        let n = 5;
+        let mut circuit = QuantumCircuit::new(n);
+        circuit.h(0);
+        for i in 0..n - 1 {
+            circuit.cnot(i, i + 1).unwrap();
+        }
+
+        let layers = circuit.layers();
+        assert_eq!(layers.len(), n);
+        let cnot_layers: Vec<_> = layers
+            .iter()
+            .filter(|layer| layer.iter().any(|op| matches!(op, Operation::ControlledGate { .. })))
+            .collect();
+        assert_eq!(cnot_layers.len(), n - 1);
+        assert!(cnot_layers.iter().all(|layer| layer.len() == 1));
+        assert_eq!(circuit.depth(), layers.len());
+    }
+
+    fn bell_circuit() -> QuantumCircuit {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1).unwrap();
+        circuit
+    }
+
+    #[test]
+    fn bell_state_qubit_probabilities_are_even() {
+        let state = bell_circuit().execute();
+        for qubit in 0..2 {
+            let (p0, p1) = state.qubit_probability(qubit);
+            assert!((p0 - 0.5).abs() < 1e-12 && (p1 - 0.5).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn scheduling_preserves_state_with_fewer_operations() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).rz(0, 0.9).rz(1, 0.3).ry(1, 1.2).x(2).ry(2, 0.8);
+        circuit.cnot(0, 1).unwrap();
+        circuit.rz(0, 0.5).h(1).rx(1, 0.7).rx(2, 0.2);
+        circuit.cnot(1, 2).unwrap();
+        circuit.x(0).x(0).rz(2, -0.4);
+
+        let scheduled = circuit.schedule_for_simulation();
+        assert!(scheduled.num_operations() < circuit.num_operations());
+        assert_states_close(&scheduled.execute(), &circuit.execute());
+    }
+
+    /// `factors[q]` acts on qubit `q`; qubit 0 is the least significant bit,
+    /// so it is the rightmost Kronecker factor.
+    fn kron_all(factors: &[DMatrix<Complex64>]) -> DMatrix<Complex64> {
+        factors
+            .iter()
+            .rev()
+            .fold(DMatrix::identity(1, 1), |acc, factor| acc.kronecker(factor))
+    }
+
+    fn kron_cnot(num_qubits: usize, control: usize, target: usize) -> DMatrix<Complex64> {
+        let zero = Complex64::new(0.0, 0.0);
+        let one = Complex64::new(1.0, 0.0);
+        let projector = |bit: usize| {
+            let mut p = DMatrix::from_element(2, 2, zero);
+            p[(bit, bit)] = one;
+            p
+        };
+
+        let mut idle = vec![DMatrix::identity(2, 2); num_qubits];
+        idle[control] = projector(0);
+        let mut active = vec![DMatrix::identity(2, 2); num_qubits];
+        active[control] = projector(1);
+        active[target] = Gates::pauli_x();
+        kron_all(&idle) + kron_all(&active)
+    }
+
+    fn basis_state(num_qubits: usize, index: usize) -> QuantumState {
+        QuantumState::from_fn(num_qubits, |i| Complex64::new((i == index) as u8 as f64, 0.0)).unwrap()
+    }
+
+    #[test]
+    fn cnot_matches_kron_matrix_for_every_three_qubit_pair() {
+        let n = 3;
+        for control in 0..n {
+            for target in (0..n).filter(|&t| t != control) {
+                let expected = kron_cnot(n, control, target);
+                for column in 0..1usize << n {
+                    let mut state = basis_state(n, column);
+                    state.apply_controlled_gate(&Gates::pauli_x(), control, target);
+                    let expected_column = expected.column(column).into_owned();
+                    assert!(
+                        (state.amplitudes() - expected_column).norm() < 1e-12,
+                        "CNOT({}, {}) differs on |{:03b}⟩",
+                        control,
+                        target,
+                        column
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn consecutive_rz_rotations_fuse() {
+        let mut circuit = QuantumCircuit::new(1);
+        circuit.rz(0, 0.3).rz(0, 0.4);
+
+        let merged = circuit.merge_rotations();
+        assert_eq!(merged.operations.len(), 1);
+        match &merged.operations[0] {
+            Operation::SingleGate {
+                kind: GateKind::Rz(theta),
+                qubit: 0,
+                ..
+            } => assert!((theta - 0.7).abs() < 1e-12),
+            other => panic!("expected rz(0.7), got {:?}", other),
+        }
+    }
+
+    fn random_state(num_qubits: usize, seed: u64) -> QuantumState {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let amplitudes: Vec<Complex64> = (0..1usize << num_qubits)
+            .map(|_| Complex64::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5))
+            .collect();
+        QuantumState::from_fn(num_qubits, |i| amplitudes[i]).unwrap()
+    }
+
+    #[test]
+    fn identity_permutation_is_a_no_op() {
+        let state = random_state(3, 7);
+        let permuted = state.apply_permutation(&[0, 1, 2]).unwrap();
+        assert_states_close(&permuted, &state);
+        assert!(state.apply_permutation(&[0, 0, 2]).is_err());
+        assert!(state.apply_permutation(&[0, 1]).is_err());
+    }
+
+    #[test]
+    fn transposition_matches_swap() {
+        let state = random_state(3, 11);
+        let permuted = state.apply_permutation(&[2, 1, 0]).unwrap();
+        let mut swap = QuantumCircuit::new(3);
+        swap.cnot(0, 2).unwrap().cnot(2, 0).unwrap().cnot(0, 2).unwrap();
+        assert_states_close(&permuted, &swap.execute_from(state));
+    }
+
+    #[test]
+    fn gate_error_budget_matches_formula() {
+        for (num_gates, target) in [(1, 0.9), (10, 0.99), (100, 0.5), (1000, 0.999)] {
+            let epsilon = max_gate_error_for_fidelity(num_gates, target);
+            assert!((epsilon - (1.0 - target.powf(1.0 / num_gates as f64))).abs() < 1e-15);
+            assert!(((1.0 - epsilon).powi(num_gates as i32) - target).abs() < 1e-10);
+        }
+        assert_eq!(max_gate_error_for_fidelity(1, 0.9), 1.0 - 0.9);
+        assert_eq!(max_gate_error_for_fidelity(0, 0.9), 1.0);
+        assert_eq!(max_gate_error_for_fidelity(5, 1.0), 0.0);
+    }
+
+    #[test]
+    fn execute_until_covers_both_ends() {
+        let mut circuit = bell_circuit();
+        circuit.ry(1, 0.3);
+        assert_states_close(&circuit.execute_until(0), &QuantumState::new(2));
+        let full = circuit.execute_until(circuit.num_operations());
+        assert_states_close(&full, &circuit.execute());
+        assert!((circuit.execute_until(1).amplitudes() - full.amplitudes()).norm() > 1e-6);
+    }
+
+    /// Distinct amplitudes everywhere, so any misplaced pair shows up.
+    fn distinct_amplitude_state(num_qubits: usize) -> QuantumState {
+        QuantumState::from_fn(num_qubits, |i| Complex64::new(1.0 + i as f64, 0.5 * i as f64 - 1.0)).unwrap()
+    }
+
+    fn assert_cnot_matches_kron(num_qubits: usize, control: usize, target: usize) {
+        let initial = distinct_amplitude_state(num_qubits);
+        let expected = kron_cnot(num_qubits, control, target) * initial.amplitudes();
+
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        circuit.cnot(control, target).unwrap();
+        let actual = circuit.execute_from(initial);
+        assert!(
+            (actual.amplitudes() - expected).norm() < 1e-12,
+            "CNOT({}, {}) deviates from the kron reference",
+            control,
+            target
+        );
+    }
+
+    #[test]
+    fn cnot_with_control_above_target_on_four_qubits() {
+        assert_cnot_matches_kron(4, 3, 0);
+    }
+
+    #[test]
+    fn cnot_matches_kron_for_every_four_qubit_ordering() {
+        for control in 0..4 {
+            for target in (0..4).filter(|&t| t != control) {
+                assert_cnot_matches_kron(4, control, target);
+            }
+        }
+    }
+
+    /// `gate` on `qubits` as a full-register matrix: each `|r⟩⟨c|` of the
+    /// gate's local basis splits into one `|r_b⟩⟨c_b|` factor on `qubits[b]`.
+    fn kron_unitary(num_qubits: usize, gate: &DMatrix<Complex64>, qubits: &[usize]) -> DMatrix<Complex64> {
+        let dimension = 1usize << num_qubits;
+        let mut full = DMatrix::zeros(dimension, dimension);
+        for row in 0..gate.nrows() {
+            for column in 0..gate.ncols() {
+                let mut factors = vec![DMatrix::identity(2, 2); num_qubits];
+                for (bit, &qubit) in qubits.iter().enumerate() {
+                    let mut outer = DMatrix::zeros(2, 2);
+                    outer[(row >> bit & 1, column >> bit & 1)] = Complex64::new(1.0, 0.0);
+                    factors[qubit] = outer;
+                }
+                full += kron_all(&factors) * gate[(row, column)];
+            }
+        }
+        full
+    }
+
+    #[test]
+    fn apply_unitary_matches_kron_on_unsorted_qubit_subsets() {
+        for (num_qubits, qubits) in [(4, vec![3, 1]), (5, vec![4, 0, 2]), (3, vec![1])] {
+            let dimension = 1usize << qubits.len();
+            // Distinct entries, so a transposed or permuted local index shows up
+            let gate = DMatrix::from_fn(dimension, dimension, |r, c| {
+                Complex64::new(1.0 + (r * dimension + c) as f64, 0.3 * r as f64 - 0.7 * c as f64)
+            });
+            let initial = distinct_amplitude_state(num_qubits);
+            let expected = kron_unitary(num_qubits, &gate, &qubits) * initial.amplitudes();
+
+            let mut state = initial.clone();
+            state.apply_unitary(&gate, &qubits).unwrap();
+            assert!(
+                (state.amplitudes() - expected).norm() < 1e-9,
+                "unitary on {:?} deviates from the kron reference",
+                qubits
+            );
+        }
+    }
+
+    #[test]
+    fn apply_unitary_rejects_bad_qubits_and_sizes() {
+        let initial = distinct_amplitude_state(4);
+        let gate = DMatrix::identity(4, 4);
+        let mut state = initial.clone();
+
+        assert_eq!(
+            state.apply_unitary(&gate, &[1, 4]),
+            Err(QuantumError::InvalidQubit { qubit: 4, num_qubits: 4 })
+        );
+        assert_eq!(state.apply_unitary(&gate, &[2, 2]), Err(QuantumError::DuplicateQubit { qubit: 2 }));
+        assert!(matches!(
+            state.apply_unitary(&gate, &[0, 1, 2]),
+            Err(QuantumError::InvalidParameter(_))
+        ));
+        assert_eq!(state.amplitudes(), initial.amplitudes());
+    }
+
+    #[test]
+    fn three_qubit_w_state_has_equal_one_hot_probabilities() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.prepare_w_state(&[0, 1, 2]).unwrap();
+        let probabilities = circuit.execute().probabilities();
+
+        for (index, probability) in probabilities.iter().enumerate() {
+            let expected = if index.count_ones() == 1 { 1.0 / 3.0 } else { 0.0 };
+            assert!((probability - expected).abs() < 1e-12, "P({:03b}) = {}", index, probability);
+        }
+    }
+
+    #[test]
+    fn ghz_on_a_non_contiguous_subset() {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.prepare_ghz(&[0, 2, 3]).unwrap();
+        let probabilities = circuit.execute().probabilities();
+
+        // Qubit 1 stays |0⟩; qubits 0, 2 and 3 are all 0 or all 1
+        for (index, probability) in probabilities.iter().enumerate() {
+            let expected = if index == 0b0000 || index == 0b1101 { 0.5 } else { 0.0 };
+            assert!((probability - expected).abs() < 1e-12, "P({:04b}) = {}", index, probability);
+        }
+        assert!(circuit.prepare_ghz(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn norm_check_catches_a_non_unitary_gate() {
+        let mut circuit = bell_circuit();
+        circuit.rx(0, 0.4).rz(1, 0.9).check_norm(1e-10);
+        assert!(circuit.execute_checked().is_ok());
+
+        let leaky = Gates::hadamard() * Complex64::new(0.9, 0.0);
+        circuit.add_operation(Operation::SingleGate {
+            gate: leaky,
+            kind: GateKind::Custom,
+            qubit: 1,
+        });
+        assert!(matches!(circuit.execute_checked(), Err(QuantumError::InvalidState(_))));
+    }
+
+    fn ghz_state(num_qubits: usize) -> QuantumState {
+        let qubits: Vec<usize> = (0..num_qubits).collect();
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        circuit.prepare_ghz(&qubits).unwrap();
+        circuit.execute()
+    }
+
+    #[test]
+    fn ghz_balanced_cuts_carry_ln_2() {
+        let state = ghz_state(4);
+        for subsystem in [[0, 1], [0, 2], [1, 3], [2, 3]] {
+            let entropy = state.entanglement_entropy(&subsystem).unwrap();
+            assert!((entropy - std::f64::consts::LN_2).abs() < 1e-10);
+        }
+
+        let (qubits, entropy) = state.max_entanglement_bipartition();
+        assert!(!qubits.is_empty() && qubits.len() < 4);
+        assert!((entropy - std::f64::consts::LN_2).abs() < 1e-10);
+    }
+
+    /// Textbook QFT `|x⟩ -> Σ_k e^(2πi xk / 2^n) |k⟩ / √2^n` with the closing swaps.
+    fn qft(num_qubits: usize) -> QuantumCircuit {
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        for i in (0..num_qubits).rev() {
+            circuit.h(i);
+            for j in (0..i).rev() {
+                let angle = PI / (1 << (i - j)) as f64;
+                circuit.add_operation(Operation::ControlledGate {
+                    gate: Gates::phase(angle),
+                    kind: GateKind::Phase(angle),
+                    control: j,
+                    target: i,
+                    control_value: true,
+                });
+            }
+        }
+        for q in 0..num_qubits / 2 {
+            let partner = num_qubits - 1 - q;
+            circuit.cnot(q, partner).unwrap().cnot(partner, q).unwrap().cnot(q, partner).unwrap();
+        }
+        circuit
+    }
+
+    #[test]
+    fn qft_amplitude_report_shows_linear_phases() {
+        let mut prep = QuantumCircuit::new(3);
+        prep.x(0);
+        let report = qft(3).execute_from(prep.execute()).amplitude_report(0.1);
+
+        assert_eq!(report.len(), 8);
+        for (k, &(index, magnitude, phase)) in report.iter().enumerate() {
+            assert_eq!(index, k);
+            assert!((magnitude - 8f64.sqrt().recip()).abs() < 1e-12);
+            let expected = Complex64::from_polar(1.0, 2.0 * PI * k as f64 / 8.0);
+            assert!((Complex64::from_polar(1.0, phase) - expected).norm() < 1e-12, "phase of {}", k);
+        }
+    }
+
+    #[test]
+    fn qft_amplitude_report_finds_the_period_peaks() {
+        // (|0⟩ + |4⟩) / √2 has period 4, so the QFT peaks on multiples of 8 / 4
+        let mut prep = QuantumCircuit::new(3);
+        prep.h(2);
+        let report = qft(3).execute_from(prep.execute()).amplitude_report(1e-6);
+
+        let indices: Vec<usize> = report.iter().map(|&(index, _, _)| index).collect();
+        assert_eq!(indices, vec![0, 2, 4, 6]);
+        for &(_, magnitude, phase) in &report {
+            assert!((magnitude - 0.5).abs() < 1e-12 && phase.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn counts_t_gates_and_their_depth() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.t(0).t(1).tdg(2).h(0).z(1);
+        circuit.cnot(0, 1).unwrap();
+        circuit.t(1).tdg(1).t(2).z(0);
+
+        assert_eq!(circuit.t_count(), 6);
+        // t(1) -> cnot -> t(1) -> tdg(1) is the longest T path
+        assert_eq!(circuit.t_depth(), 3);
+        assert_eq!(QuantumCircuit::new(2).t_count(), 0);
+    }
+
+    #[test]
+    fn average_hamming_weight_of_uniform_superposition() {
+        for n in 1..=5 {
+            let mut circuit = QuantumCircuit::new(n);
+            for qubit in 0..n {
+                circuit.h(qubit);
+            }
+            let weight = circuit.execute().expectation_fn(|i| i.count_ones() as f64);
+            assert!((weight - n as f64 / 2.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn amplitudes_round_trip_through_a_buffer() {
+        // 13 qubits spans two chunks
+        for n in [3, 13] {
+            let state = random_state(n, n as u64);
+            let mut buffer = Vec::new();
+            state.write_amplitudes(&mut buffer).unwrap();
+            assert_eq!(buffer.len(), 4 + (16 << n));
+
+            let read = QuantumState::read_amplitudes(&mut buffer.as_slice()).unwrap();
+            assert_eq!(read.num_qubits(), n);
+            assert_eq!(read.amplitudes(), state.amplitudes());
+        }
+        assert!(QuantumState::read_amplitudes(&mut [3u8, 0, 0, 0, 1].as_slice()).is_err());
+    }
+
+    #[test]
+    fn plus_state_measured_in_x_basis_always_gives_zero() {
+        let mut circuit = QuantumCircuit::new(1);
+        circuit.h(0).measure(0, Pauli::X).unwrap();
+        let mut rng = StdRng::seed_from_u64(233);
+        for _ in 0..200 {
+            let (_, outcomes) = circuit.execute_with_measurements(&mut rng);
+            assert_eq!(outcomes, vec![0]);
+        }
+    }
+
+    #[test]
+    fn measure_rejects_out_of_range_qubits() {
+        let mut circuit = QuantumCircuit::new(2);
+        assert!(matches!(
+            circuit.measure(2, Pauli::Z),
+            Err(QuantumError::InvalidQubit { qubit: 2, num_qubits: 2 })
+        ));
+        assert!(circuit.operations.is_empty());
+    }
+
+    #[test]
+    fn controlled_rotation_decomposition_matches_direct_gate() {
+        let theta = 1.234;
+        for axis in ['x', 'Y', 'z'] {
+            let (gate, kind) = match axis.to_ascii_lowercase() {
+                'x' => (Gates::rx(theta), GateKind::Rx(theta)),
+                'y' => (Gates::ry(theta), GateKind::Ry(theta)),
+                _ => (Gates::rz(theta), GateKind::Rz(theta)),
+            };
+            for (control, target) in [(0, 2), (2, 1)] {
+                let mut decomposed = QuantumCircuit::new(3);
+                for operation in decompose_controlled_rotation(axis, theta, control, target) {
+                    decomposed.add_operation(operation);
+                }
+
+                let mut direct = QuantumCircuit::new(3);
+                direct.add_operation(Operation::ControlledGate {
+                    gate: gate.clone(),
+                    kind,
+                    control,
+                    target,
+                    control_value: true,
+                });
+
+                let initial = random_state(3, 234);
+                assert_states_close(
+                    &decomposed.execute_from(initial.clone()),
+                    &direct.execute_from(initial),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ghz_fan_out_only_connects_qubit_zero() {
+        let mut circuit = QuantumCircuit::new(5);
+        circuit.prepare_ghz(&[0, 1, 2, 3, 4]).unwrap();
+        circuit.cnot(3, 0).unwrap();
+
+        let edges = circuit.connectivity_graph();
+        assert_eq!(edges.len(), 4);
+        assert!(edges.keys().all(|&(a, b)| a == 0 && b > 0));
+        // CNOT(3, 0) lands on the same undirected edge as CNOT(0, 3)
+        assert_eq!(edges[&(0, 3)], 2);
+        assert_eq!(edges.values().sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn parallelism_of_known_circuits() {
+        let mut chain = QuantumCircuit::new(4);
+        chain.prepare_ghz(&[0, 1, 2, 3]).unwrap();
+        // H then three CNOTs that all wait on qubit 0
+        assert_eq!(chain.parallelism(), 1.0);
+
+        let mut layer = QuantumCircuit::new(4);
+        for qubit in 0..4 {
+            layer.h(qubit);
+        }
+        assert_eq!(layer.parallelism(), 4.0);
+
+        let mut mixed = QuantumCircuit::new(4);
+        mixed.h(0).h(1).h(2).h(3);
+        mixed.cnot(0, 1).unwrap();
+        mixed.cnot(2, 3).unwrap();
+        assert_eq!(mixed.parallelism(), 3.0);
+
+        assert_eq!(QuantumCircuit::new(3).parallelism(), 0.0);
+    }
+
+    #[test]
+    fn ties_go_to_the_lowest_basis_index() {
+        let state = ghz_state(3);
+        assert_eq!(state.most_likely_outcome(), 0);
+
+        let mut flipped = QuantumCircuit::new(3);
+        flipped.prepare_ghz(&[0, 1, 2]).unwrap();
+        flipped.z(0).x(1);
+        // Outcomes 0b010 and 0b101 are equally likely
+        assert_eq!(flipped.execute().most_likely_outcome(), 0b010);
+
+        let mut counts = BTreeMap::new();
+        counts.insert(5, 10);
+        counts.insert(2, 10);
+        counts.insert(7, 3);
+        assert_eq!(most_frequent_outcome(&counts), Some(2));
+        assert_eq!(most_frequent_outcome(&BTreeMap::new()), None);
+    }
+
+    fn assert_duplicate(result: Result<&mut QuantumCircuit, QuantumError>, qubit: usize) {
+        match result {
+            Err(QuantumError::DuplicateQubit { qubit: reported }) => assert_eq!(reported, qubit),
+            Err(other) => panic!("expected DuplicateQubit({}), got {:?}", qubit, other),
+            Ok(_) => panic!("expected DuplicateQubit({}), got Ok", qubit),
+        }
+    }
+
+    #[test]
+    fn multi_qubit_builders_reject_duplicate_qubits() {
+        let mut c = QuantumCircuit::new(4);
+        assert_duplicate(c.cnot(1, 1), 1);
+        assert_duplicate(c.cnot_with_value(2, 2, false), 2);
+        assert_duplicate(c.controlled_gate_with_value(Gates::hadamard(), 0, 0, true), 0);
+        assert_duplicate(c.cp(0, 0, 0.5), 0);
+        assert_duplicate(c.crx(1, 1, 0.5), 1);
+        assert_duplicate(c.cry(2, 2, 0.5), 2);
+        assert_duplicate(c.crz(3, 3, 0.5), 3);
+        assert_duplicate(c.mcx(&[0, 2, 2], 3), 2);
+        assert_duplicate(c.multi_controlled_gate(Gates::pauli_z(), &[(1, true), (3, false)], 1), 1);
+        assert_duplicate(c.ecr(3, 3), 3);
+        assert_duplicate(c.rxx(1, 1, 0.3), 1);
+        assert_duplicate(c.ryy(2, 2, 0.3), 2);
+        assert_duplicate(c.rzz(0, 0, 0.3), 0);
+        assert_duplicate(c.controlled_multi_target(0, "h", &[1, 0]), 0);
+        assert_duplicate(c.controlled_multi_target(0, "x", &[2, 2]), 2);
+        assert_duplicate(c.controlled_mod_mul(1, &[0, 1, 2], 7, 15), 1);
+        assert_duplicate(c.prepare_ghz(&[0, 3, 3]), 3);
+        assert_duplicate(c.prepare_w_state(&[2, 1, 2]), 2);
+        assert_duplicate(c.cz(3, 3), 3);
+        assert_duplicate(c.swap(2, 2), 2);
+        assert_duplicate(c.ccx(0, 0, 1), 0);
+        assert_duplicate(c.ccx(0, 1, 1), 1);
+        assert_duplicate(c.cswap(0, 1, 1), 1);
+        assert_duplicate(c.cswap(2, 2, 3), 2);
+        assert_duplicate(c.iswap(1, 1), 1);
+        assert_duplicate(c.sqrt_iswap(0, 0), 0);
+        assert_duplicate(c.two_qubit_gate(Gates::iswap(), 3, 3), 3);
+        assert!(c.operations.is_empty());
+    }
+
+    #[test]
+    fn walsh_hadamard_transform_matches_sequential_hadamards() {
+        let n = 5;
+        let initial = random_state(n, 245);
+        let qubits: Vec<usize> = (0..n).collect();
+
+        let mut fast = initial.clone();
+        fast.apply_hadamard_transform(&qubits);
+        let mut sequential = initial.clone();
+        for &qubit in &qubits {
+            sequential.apply_single_gate(&Gates::hadamard(), qubit);
+        }
+        assert_states_close(&fast, &sequential);
+
+        // The scheduler picks the pattern up from a plain Hadamard layer
+        let mut circuit = QuantumCircuit::new(n);
+        for &qubit in &qubits {
+            circuit.h(qubit);
+        }
+        let scheduled = circuit.schedule_for_simulation();
+        assert!(matches!(scheduled.operations(), [Operation::HadamardTransform { .. }]));
+        assert_states_close(&scheduled.execute_from(initial), &sequential);
+    }
+
+    fn circuit_unitary(circuit: &QuantumCircuit) -> DMatrix<Complex64> {
+        let dimension = 1 << circuit.num_qubits();
+        let product = circuit.operations().iter().fold(DMatrix::identity(dimension, dimension), |acc, op| {
+            NaiveSimulator::operation_matrix(op, circuit.num_qubits()) * acc
+        });
+        product * Complex64::from_polar(1.0, circuit.global_phase())
+    }
+
+    /// `|0⟩⟨0| ⊗ I + |1⟩⟨1| ⊗ U^power` with the control as the top qubit.
+    fn reference_controlled_power(unitary: &QuantumCircuit, power: u32) -> DMatrix<Complex64> {
+        let u = circuit_unitary(unitary);
+        let dimension = u.nrows();
+        let powered = (0..power).fold(DMatrix::identity(dimension, dimension), |acc, _| &u * acc);
+        let mut matrix = DMatrix::identity(2 * dimension, 2 * dimension);
+        matrix
+            .view_mut((dimension, dimension), (dimension, dimension))
+            .copy_from(&powered);
+        matrix
+    }
+
+    fn controlled_power_cases() -> Vec<QuantumCircuit> {
+        let mut single = QuantumCircuit::new(3);
+        single.rz(1, 0.7).h(1).t(1);
+
+        let mut lifted = QuantumCircuit::new(3);
+        lifted.h(0).ry(2, 0.4);
+        lifted.cnot(0, 1).unwrap();
+        lifted.cry(2, 0, 0.9).unwrap();
+        lifted.cnot(1, 2).unwrap();
+        lifted.controlled_gate_with_value(Gates::ry(1.3), 1, 0, false).unwrap();
+        lifted.rx(1, 0.3);
+
+        let mut phase_only = QuantumCircuit::new(3);
+        phase_only.add_global_phase(0.8);
+
+        single.add_global_phase(0.4);
+        lifted.add_global_phase(1.3);
+        vec![single, lifted, phase_only]
+    }
+
+    #[test]
+    fn controlled_power_matches_the_block_reference() {
+        for unitary in controlled_power_cases() {
+            for power in [1, 2, 3] {
+                let mut circuit = QuantumCircuit::new(4);
+                circuit.append_controlled_power(&unitary, 3, power as usize).unwrap();
+                let difference = circuit_unitary(&circuit) - reference_controlled_power(&unitary, power);
+                assert!(difference.norm() < 1e-10, "power {} of {:?}", power, unitary.operations);
+            }
+        }
+    }
+
+    #[test]
+    fn controlled_square_equals_controlled_twice() {
+        for unitary in controlled_power_cases() {
+            let mut squared = QuantumCircuit::new(4);
+            squared.append_controlled_power(&unitary, 3, 2).unwrap();
+            let mut twice = QuantumCircuit::new(4);
+            twice.append_controlled_power(&unitary, 3, 1).unwrap();
+            twice.append_controlled_power(&unitary, 3, 1).unwrap();
+            assert!((circuit_unitary(&squared) - circuit_unitary(&twice)).norm() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn controlled_power_rejects_control_overlap_and_uncontrollable_operations() {
+        let mut unitary = QuantumCircuit::new(3);
+        unitary.cnot(0, 2).unwrap();
+        let mut circuit = QuantumCircuit::new(3);
+        assert_duplicate(circuit.append_controlled_power(&unitary, 2, 1), 2);
+
+        unitary = QuantumCircuit::new(2);
+        unitary.h(0).measure(0, Pauli::Z).unwrap();
+        assert!(circuit.append_controlled_power(&unitary, 2, 1).is_err());
+
+        unitary = QuantumCircuit::new(2);
+        unitary.permute(&[1, 0]).unwrap();
+        assert!(circuit.append_controlled_power(&unitary, 2, 1).is_err());
+        assert!(circuit.operations.is_empty());
+    }
+
+    #[test]
+    fn global_phase_difference_finds_the_phase() {
+        let state = random_state(3, 249);
+        let factor = Complex64::from_polar(1.0, 0.8);
+        let rotated = QuantumState::from_amplitudes(state.amplitudes() * factor).unwrap();
+        let phase = global_phase_difference(&state, &rotated).unwrap();
+        assert!((phase - factor).norm() < 1e-12);
+        assert!((state.amplitudes() - rotated.amplitudes()).norm() > 1e-6);
+    }
+
+    #[test]
+    fn global_phase_difference_rejects_different_states() {
+        let state = random_state(3, 249);
+        let mut other = state.clone();
+        other.apply_single_gate(&Gates::rx(0.4), 1);
+        assert_eq!(global_phase_difference(&state, &other), None);
+
+        // Orthogonal states and mismatched registers
+        assert_eq!(global_phase_difference(&QuantumState::new(2), &basis_state(2, 3)), None);
+        assert_eq!(global_phase_difference(&QuantumState::new(2), &QuantumState::new(3)), None);
+    }
+
+    #[test]
+    fn chunked_expectation_matches_serial() {
+        let state = random_state(8, 250);
+        let operators = [(0, Pauli::X), (3, Pauli::Y), (5, Pauli::Z), (7, Pauli::X)];
+        let serial = state.expectation_pauli_chunked(&operators, 1 << 8).unwrap();
+        for chunk_size in [1, 3, 16, 100] {
+            let chunked = state.expectation_pauli_chunked(&operators, chunk_size).unwrap();
+            assert!((chunked - serial).abs() < 1e-12, "chunk {}: {} vs {}", chunk_size, chunked, serial);
+        }
+        assert!(state.expectation_pauli_chunked(&operators, 0).is_err());
+    }
+
+    #[test]
+    fn from_gate_list_builds_ghz() {
+        let gate = |name: &str, qubits: &[usize]| (name.to_string(), qubits.to_vec(), Vec::new());
+        let gates = vec![gate("h", &[0]), gate("cx", &[0, 1]), gate("CNOT", &[1, 2])];
+        let circuit = QuantumCircuit::from_gate_list(3, &gates).unwrap();
+        assert_states_close(&circuit.execute(), &ghz_state(3));
+
+        let unknown = vec![gate("bogus", &[0])];
+        assert!(QuantumCircuit::from_gate_list(3, &unknown).is_err());
+        let wrong_arity = vec![gate("cx", &[0])];
+        assert!(QuantumCircuit::from_gate_list(3, &wrong_arity).is_err());
+        let wrong_params = vec![("rx".to_string(), vec![0], Vec::new())];
+        assert!(QuantumCircuit::from_gate_list(3, &wrong_params).is_err());
+        assert!(QuantumCircuit::from_gate_list(3, &[gate("x", &[3])]).is_err());
+    }
+
+    #[test]
+    fn fit_rb_decay_recovers_known_decay() {
+        let lengths = [1, 2, 4, 8, 16, 32, 64, 128];
+        let survival: Vec<f64> = lengths.iter().map(|&m| 0.5 * 0.97f64.powi(m as i32) + 0.5).collect();
+        let (p, residual) = fit_rb_decay(&lengths, &survival);
+        assert!((p - 0.97).abs() < 1e-6, "p = {}", p);
+        assert!(residual < 1e-12);
+
+        let noisy: Vec<f64> = survival
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s + if i % 2 == 0 { 1e-3 } else { -1e-3 })
+            .collect();
+        let (p, residual) = fit_rb_decay(&lengths, &noisy);
+        assert!((p - 0.97).abs() < 5e-3, "p = {}", p);
+        assert!(residual > 0.0);
+    }
+
+    #[test]
+    fn counts_to_bitstrings_keys_three_qubits() {
+        let counts: HashMap<usize, usize> = [(0b001, 10), (0b110, 20), (0b100, 5)].into_iter().collect();
+        let right = counts_to_bitstrings(&counts, 3, BitOrder::QubitZeroRight);
+        let expected: HashMap<String, usize> =
+            [("001", 10), ("110", 20), ("100", 5)].into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(right, expected);
+
+        let left = counts_to_bitstrings(&counts, 3, BitOrder::QubitZeroLeft);
+        let expected: HashMap<String, usize> =
+            [("100", 10), ("011", 20), ("001", 5)].into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(left, expected);
+    }
+
+    #[test]
+    fn structural_hash_tracks_structure_and_parameters() {
+        let build = |theta: f64| {
+            let mut circuit = QuantumCircuit::new(3);
+            circuit.h(0).rx(1, theta).cnot(0, 2).unwrap().cry(1, 2, 0.3).unwrap();
+            circuit
+        };
+        assert_eq!(build(0.7).structural_hash(), build(0.7).structural_hash());
+        assert_ne!(build(0.7).structural_hash(), build(0.71).structural_hash());
+
+        let mut swapped = QuantumCircuit::new(3);
+        swapped.h(0).rx(1, 0.7).cnot(2, 0).unwrap().cry(1, 2, 0.3).unwrap();
+        assert_ne!(build(0.7).structural_hash(), swapped.structural_hash());
+    }
+
+    #[test]
+    fn controlled_hadamard_on_three_targets() {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.x(0);
+        circuit.controlled_multi_target(0, "H", &[1, 2, 3]).unwrap();
+        assert_eq!(circuit.num_operations(), 4);
+        let expected =
+            QuantumState::from_fn(4, |i| Complex64::new((i & 1) as f64 / 8f64.sqrt(), 0.0)).unwrap();
+        assert_states_close(&circuit.execute(), &expected);
+
+        // Control unset: targets untouched
+        let mut idle = QuantumCircuit::new(4);
+        idle.controlled_multi_target(0, "h", &[1, 2, 3]).unwrap();
+        assert_states_close(&idle.execute(), &basis_state(4, 0));
+
+        assert!(idle.controlled_multi_target(0, "rx", &[1]).is_err());
+        assert_duplicate(idle.controlled_multi_target(1, "h", &[2, 1]), 1);
+    }
+
+    #[test]
+    fn support_trace_stays_flat_for_ghz_and_grows_for_rotations() {
+        let mut ghz = QuantumCircuit::new(4);
+        ghz.prepare_ghz(&[0, 1, 2, 3]).unwrap();
+        let (state, trace) = ghz.execute_with_support_trace();
+        assert_eq!(trace.len(), ghz.num_operations());
+        assert!(trace.iter().all(|&size| size == 2), "{:?}", trace);
+        assert_states_close(&state, &ghz_state(4));
+
+        let mut spread = QuantumCircuit::new(4);
+        for qubit in 0..4 {
+            spread.ry(qubit, 0.3 + qubit as f64);
+        }
+        spread.cnot(0, 3).unwrap();
+        let (_, trace) = spread.execute_with_support_trace();
+        assert_eq!(trace, vec![2, 4, 8, 16, 16]);
+    }
+
+    #[test]
+    fn tolerances_loosen_and_tighten_comparisons() {
+        let state = random_state(3, 262);
+        let mut perturbed = state.clone();
+        perturbed.apply_single_gate(&Gates::rx(1e-7), 2);
+        assert!(state.approx_eq(&perturbed, 1e-6));
+        assert!(!state.approx_eq(&perturbed, DEFAULT_TOL));
+
+        let nearly_unitary = Gates::hadamard() * Complex64::new(1.0 + 1e-8, 0.0);
+        assert!(is_unitary(&nearly_unitary, 1e-6));
+        assert!(!is_unitary(&nearly_unitary, DEFAULT_TOL));
+
+        let unnormalised = state.amplitudes() * Complex64::new(1.0 + 1e-8, 0.0);
+        assert!(QuantumState::from_amplitudes_with_tolerance(unnormalised.clone(), 1e-6).is_ok());
+        assert!(QuantumState::from_amplitudes(unnormalised).is_err());
+    }
+
+    #[test]
+    fn postselecting_bell_qubit_fixes_its_partner() {
+        for (outcome, index) in [(false, 0b00), (true, 0b11)] {
+            let mut state = bell_circuit().execute();
+            let probability = state.postselect(0, outcome).unwrap();
+            assert!((probability - 0.5).abs() < 1e-12);
+            assert!(state.approx_eq(&basis_state(2, index), 1e-12));
+        }
+
+        let mut collapsed = basis_state(2, 0b11);
+        assert!(collapsed.postselect(1, false).is_err());
+        assert!(collapsed.postselect(2, true).is_err());
+    }
+
+    #[test]
+    fn ghz_exports_expected_qir_ops() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.prepare_ghz(&[0, 1, 2]).unwrap();
+        for qubit in 0..3 {
+            circuit.measure(qubit, PauliBasis::Z).unwrap();
+        }
+        let op = |gate: &'static str, qubits: Vec<usize>| QirOp { gate, qubits, params: vec![] };
+        assert_eq!(
+            circuit.to_qir_ops(),
+            vec![
+                op("h", vec![0]),
+                op("cnot", vec![0, 1]),
+                op("cnot", vec![0, 2]),
+                op("mz", vec![0]),
+                op("mz", vec![1]),
+                op("mz", vec![2]),
+            ]
+        );
+    }
+
+    fn gate_matrix<const N: usize>(dimension: usize, entries: [Complex64; N]) -> DMatrix<Complex64> {
+        DMatrix::from_row_slice(dimension, dimension, &entries)
+    }
+
+    const fn c(re: f64, im: f64) -> Complex64 {
+        Complex64::new(re, im)
+    }
+
+    const ZERO: Complex64 = c(0.0, 0.0);
+    const ONE: Complex64 = c(1.0, 0.0);
+    const I: Complex64 = c(0.0, 1.0);
+    const H: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn pauli_and_hadamard_matrices() {
+        assert_gate_eq!(Gates::pauli_x(), gate_matrix(2, [ZERO, ONE, ONE, ZERO]));
+        assert_gate_eq!(Gates::pauli_y(), gate_matrix(2, [ZERO, -I, I, ZERO]));
+        assert_gate_eq!(Gates::pauli_z(), gate_matrix(2, [ONE, ZERO, ZERO, -ONE]));
+        assert_gate_eq!(Gates::hadamard(), gate_matrix(2, [c(H, 0.0), c(H, 0.0), c(H, 0.0), c(-H, 0.0)]));
+    }
+
+    #[test]
+    fn rotation_matrices() {
+        let root3_2 = 3f64.sqrt() / 2.0;
+        assert_gate_eq!(Gates::rx(0.0), gate_matrix(2, [ONE, ZERO, ZERO, ONE]));
+        assert_gate_eq!(Gates::rx(PI / 2.0), gate_matrix(2, [c(H, 0.0), c(0.0, -H), c(0.0, -H), c(H, 0.0)]));
+        assert_gate_eq!(Gates::rx(PI), gate_matrix(2, [ZERO, -I, -I, ZERO]));
+        assert_gate_eq!(
+            Gates::ry(PI / 3.0),
+            gate_matrix(2, [c(root3_2, 0.0), c(-0.5, 0.0), c(0.5, 0.0), c(root3_2, 0.0)])
+        );
+        assert_gate_eq!(Gates::ry(PI), gate_matrix(2, [ZERO, -ONE, ONE, ZERO]));
+        assert_gate_eq!(Gates::rz(PI / 2.0), gate_matrix(2, [c(H, -H), ZERO, ZERO, c(H, H)]));
+        assert_gate_eq!(Gates::rz(PI), gate_matrix(2, [-I, ZERO, ZERO, I]));
+        assert_gate_eq!(Gates::phase(PI / 3.0), gate_matrix(2, [ONE, ZERO, ZERO, c(0.5, root3_2)]));
+        assert_gate_eq!(Gates::u3(PI / 2.0, 0.0, PI), Gates::hadamard());
+        assert_gate_eq!(
+            Gates::u3(PI / 3.0, PI / 2.0, 0.0),
+            gate_matrix(2, [c(root3_2, 0.0), c(-0.5, 0.0), c(0.0, 0.5), c(0.0, root3_2)])
+        );
+    }
+
+    #[test]
+    fn clifford_and_t_matrices() {
+        assert_gate_eq!(Gates::sx(), gate_matrix(2, [c(0.5, 0.5), c(0.5, -0.5), c(0.5, -0.5), c(0.5, 0.5)]));
+        assert_gate_eq!(Gates::sxdg(), gate_matrix(2, [c(0.5, -0.5), c(0.5, 0.5), c(0.5, 0.5), c(0.5, -0.5)]));
+        assert_gate_eq!(Gates::s(), gate_matrix(2, [ONE, ZERO, ZERO, I]));
+        assert_gate_eq!(Gates::sdg(), gate_matrix(2, [ONE, ZERO, ZERO, -I]));
+        assert_gate_eq!(Gates::t(), gate_matrix(2, [ONE, ZERO, ZERO, c(H, H)]));
+        assert_gate_eq!(Gates::tdg(), gate_matrix(2, [ONE, ZERO, ZERO, c(H, -H)]));
+    }
+
+    #[test]
+    fn two_qubit_gate_matrices() {
+        #[rustfmt::skip]
+        let cases = [
+            (Gates::iswap(), [
+                ONE, ZERO, ZERO, ZERO,
+                ZERO, ZERO, I, ZERO,
+                ZERO, I, ZERO, ZERO,
+                ZERO, ZERO, ZERO, ONE,
+            ]),
+            (Gates::sqrt_iswap(), [
+                ONE, ZERO, ZERO, ZERO,
+                ZERO, c(H, 0.0), c(0.0, H), ZERO,
+                ZERO, c(0.0, H), c(H, 0.0), ZERO,
+                ZERO, ZERO, ZERO, ONE,
+            ]),
+            (Gates::rxx(PI / 2.0), [
+                c(H, 0.0), ZERO, ZERO, c(0.0, -H),
+                ZERO, c(H, 0.0), c(0.0, -H), ZERO,
+                ZERO, c(0.0, -H), c(H, 0.0), ZERO,
+                c(0.0, -H), ZERO, ZERO, c(H, 0.0),
+            ]),
+            (Gates::ryy(PI / 2.0), [
+                c(H, 0.0), ZERO, ZERO, c(0.0, H),
+                ZERO, c(H, 0.0), c(0.0, -H), ZERO,
+                ZERO, c(0.0, -H), c(H, 0.0), ZERO,
+                c(0.0, H), ZERO, ZERO, c(H, 0.0),
+            ]),
+            (Gates::rzz(PI / 2.0), [
+                c(H, -H), ZERO, ZERO, ZERO,
+                ZERO, c(H, H), ZERO, ZERO,
+                ZERO, ZERO, c(H, H), ZERO,
+                ZERO, ZERO, ZERO, c(H, -H),
+            ]),
+            // Qiskit's ECR with the first qubit as the low bit
+            (Gates::ecr(), [
+                ZERO, c(H, 0.0), ZERO, c(0.0, H),
+                c(H, 0.0), ZERO, c(0.0, -H), ZERO,
+                ZERO, c(0.0, H), ZERO, c(H, 0.0),
+                c(0.0, -H), ZERO, c(H, 0.0), ZERO,
+            ]),
+        ];
+        for (actual, expected) in cases {
+            assert_gate_eq!(actual, gate_matrix(4, expected));
+        }
+    }
+
+    #[test]
+    fn cached_unitary_is_reused_across_executions() {
+        let mut plain = QuantumCircuit::new(3);
+        plain.h(0).cnot(0, 1).unwrap().ry(2, 0.9).t(1).cry(2, 0, 0.4).unwrap();
+        let mut cached = plain.clone();
+        cached.cache_unitary().unwrap();
+        assert!(cached.cached_unitary().is_none());
+
+        let inputs = [random_state(3, 1), random_state(3, 2), basis_state(3, 5)];
+        let first = cached.execute_from(inputs[0].clone());
+        let unitary: *const DMatrix<Complex64> = cached.cached_unitary().unwrap();
+        assert!(first.approx_eq(&plain.execute_from(inputs[0].clone()), 1e-12));
+        for input in &inputs[1..] {
+            let state = cached.execute_from(input.clone());
+            assert!(std::ptr::eq(unitary, cached.cached_unitary().unwrap()));
+            assert!(state.approx_eq(&plain.execute_from(input.clone()), 1e-12));
+        }
+
+        // Adding an operation drops the stale matrix
+        cached.x(2);
+        plain.x(2);
+        assert!(cached.cached_unitary().is_none());
+        assert!(cached.execute_from(inputs[1].clone()).approx_eq(&plain.execute_from(inputs[1].clone()), 1e-12));
+
+        assert!(QuantumCircuit::new(MAX_CACHED_UNITARY_QUBITS + 1).cache_unitary().is_err());
+    }
+
+    #[test]
+    fn deferred_virtual_rz_keeps_measurement_distribution() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).h(1).rz(0, 0.7).rz(1, -1.3).cnot(0, 2).unwrap();
+        circuit.rx(0, 0.4).rz(2, 2.1).ry(2, 0.8).cry(1, 2, 0.6).unwrap();
+        circuit.rz(1, 0.25).permute(&[0, 2, 1]).unwrap().h(2).rz(0, 1.1);
+
+        let (deferred, frames) = circuit.defer_virtual_rz();
+        let count_rz = |c: &QuantumCircuit| {
+            c.operations()
+                .iter()
+                .filter(|op| matches!(op, Operation::SingleGate { kind: GateKind::Rz(_), .. }))
+                .count()
+        };
+        assert!(count_rz(&deferred) < count_rz(&circuit));
+
+        let expected = circuit.execute();
+        let state = deferred.execute();
+        assert_probabilities_close(&state.probabilities(), &expected.probabilities());
+
+        let mut restored = deferred.clone();
+        for (qubit, &frame) in frames.iter().enumerate() {
+            restored.rz(qubit, frame);
+        }
+        assert!(restored.execute().approx_eq(&expected, 1e-12));
+    }
+
+    #[test]
+    fn concurrence_of_bell_and_product_pairs() {
+        assert!((bell_circuit().execute().concurrence(0, 1) - 1.0).abs() < 1e-9);
+
+        let mut product = QuantumCircuit::new(3);
+        product.ry(0, 0.7).rx(1, 1.9).h(2).rz(2, 0.4);
+        let state = product.execute();
+        for (q0, q1) in [(0, 1), (1, 2), (2, 0)] {
+            assert!(state.concurrence(q0, q1) < 1e-9, "pair ({}, {})", q0, q1);
+        }
+
+        // cos(a)|00⟩ + sin(a)|11⟩ has concurrence sin(2a); here on qubits 0 and 2
+        let mut partial = QuantumCircuit::new(3);
+        partial.ry(0, 0.8).cnot(0, 2).unwrap().h(1);
+        assert!((partial.execute().concurrence(2, 0) - 0.8f64.sin()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn forward_then_inverse_returns_initial_state() {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.h(0).rx(1, 0.7).t(2).tdg(3).ry(1, 0.4).rz(0, -0.4);
+        circuit.cnot(0, 1).unwrap().cry(3, 0, 1.3).unwrap();
+        circuit.controlled_multi_target(2, "h", &[1, 3]).unwrap();
+        circuit.controlled_mod_mul(3, &[0, 1, 2], 3, 7).unwrap();
+        circuit.add_operation(Operation::HadamardTransform { qubits: vec![0, 2] });
+        circuit.permute(&[2, 0, 3, 1]).unwrap();
+
+        for seed in 0..3 {
+            let initial = random_state(4, seed);
+            let forward = circuit.execute_from(initial.clone());
+            assert!(!forward.approx_eq(&initial, 1e-6));
+            assert!(circuit.execute_inverse_from(forward).approx_eq(&initial, 1e-12));
+        }
+    }
+
+    #[test]
+    fn circuit_overlap_with_itself_and_flipped_copy() {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).cnot(0, 1).unwrap().ry(2, 0.5).t(1);
+        let overlap = circuit_overlap(&circuit, &circuit).unwrap();
+        assert!((overlap - Complex64::new(1.0, 0.0)).norm() < 1e-12);
+
+        // X on qubit 1 breaks the 00/11 correlation of the first two qubits
+        let mut flipped = circuit.clone();
+        flipped.x(1);
+        assert!(circuit_overlap(&circuit, &flipped).unwrap().norm() < 1e-12);
+
+        assert!(circuit_overlap(&circuit, &QuantumCircuit::new(2)).is_err());
+    }
+
+    #[test]
+    fn subsystem_purity_of_bell_and_product_subsystems() {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.h(0).cnot(0, 1).unwrap().ry(2, 0.9).rx(3, 0.4);
+        let state = circuit.execute();
+        assert!((state.subsystem_purity(&[0]) - 0.5).abs() < 1e-12);
+        assert!((state.subsystem_purity(&[1]) - 0.5).abs() < 1e-12);
+        assert!((state.subsystem_purity(&[0, 1]) - 1.0).abs() < 1e-12);
+        assert!((state.subsystem_purity(&[2]) - 1.0).abs() < 1e-12);
+        assert!((state.subsystem_purity(&[2, 3]) - 1.0).abs() < 1e-12);
+        assert!((state.subsystem_purity(&[1, 3]) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn single_qubit_gates_do_not_add_two_qubit_depth() {
+        let mut bare = QuantumCircuit::new(3);
+        bare.cnot(0, 1).unwrap().cnot(1, 2).unwrap().cry(0, 2, 0.7).unwrap();
+
+        let mut padded = QuantumCircuit::new(3);
+        padded.h(0).h(1).cnot(0, 1).unwrap();
+        padded.rx(1, 0.3).t(1).z(2).cnot(1, 2).unwrap();
+        padded.ry(0, 1.1).z(2).h(2).cry(0, 2, 0.7).unwrap().x(0);
+
+        assert_eq!(bare.two_qubit_depth(), 3);
+        assert_eq!(padded.two_qubit_depth(), 3);
+        assert!(padded.depth() > bare.depth());
+
+        // Disjoint pairs share a layer
+        let mut parallel = QuantumCircuit::new(4);
+        parallel.cnot(0, 1).unwrap().cnot(2, 3).unwrap().h(0).h(3);
+        assert_eq!(parallel.two_qubit_depth(), 1);
+    }
+
+    #[test]
+    fn state_accumulates_the_applied_global_phase() {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.h(0).cnot(0, 1).unwrap().add_global_phase(1.25);
+        let state = circuit.execute();
+        assert!((state.global_phase() - 1.25).abs() < 1e-12);
+
+        let mut cached = circuit.clone();
+        cached.cache_unitary().unwrap();
+        let mut shifted = QuantumState::new(2);
+        shifted.apply_global_phase(2.0 * PI - 0.25);
+        assert!((cached.execute_from(shifted).global_phase() - 1.0).abs() < 1e-12);
+
+        assert!(circuit.execute_inverse_from(state).global_phase().abs() < 1e-12);
+    }
+}
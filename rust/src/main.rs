@@ -1,197 +1,316 @@
-use logosq::QuantumCircuit;
-use serde::{Deserialize, Serialize};
-use std::time::Instant;
-use std::f64::consts::PI;
-use rand::Rng;
-
-#[derive(Serialize, Deserialize)]
-struct BenchmarkResult {
-    name: String,
-    num_qubits: usize,
-    num_gates: usize,
-    execution_time_ms: f64,
-    memory_usage_mb: f64,
-    circuit_depth: usize,
+use logosq::benchmarks::{
+    run_benchmarks, BenchmarkConfig, BenchmarkSuite, BudgetOrder, GateWeights, InterruptFlag, RANDOM_GATE_NAMES,
+};
+use std::time::Duration;
+
+#[derive(Debug, Default, PartialEq)]
+struct CliOptions {
+    pin_cores: Option<Vec<usize>>,
+    compact: bool,
+    gate_weights: Option<GateWeights>,
+    dump_state: bool,
+    qubit_marginals: bool,
+    time_budget: Option<Duration>,
+    budget_order: BudgetOrder,
 }
 
-#[derive(Serialize, Deserialize)]
-struct BenchmarkSuite {
-    library: String,
-    version: String,
-    results: Vec<BenchmarkResult>,
-    total_time_ms: f64,
+/// Parses `h=0.5,cnot=2` style weights; unlisted gates get weight zero.
+fn parse_gate_weights(value: &str) -> Result<GateWeights, String> {
+    let mut weights = vec![0.0; RANDOM_GATE_NAMES.len()];
+
+    for entry in value.split(',') {
+        let (name, weight) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("expected gate=weight in --gate-weights, got '{}'", entry))?;
+        let index = RANDOM_GATE_NAMES
+            .iter()
+            .position(|gate| *gate == name.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unknown gate '{}' in --gate-weights (expected one of {})",
+                    name,
+                    RANDOM_GATE_NAMES.join(", ")
+                )
+            })?;
+        weights[index] = weight
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("invalid weight '{}' for gate '{}'", weight, name))?;
+    }
+
+    GateWeights::new(weights)
 }
 
-fn get_memory_usage() -> f64 {
-    // Simple memory estimation - in a real implementation you'd use more sophisticated monitoring
-    std::process::Command::new("ps")
-        .args(&["-o", "rss=", "-p", &std::process::id().to_string()])
-        .output()
-        .ok()
-        .and_then(|output| {
-            String::from_utf8(output.stdout)
-                .ok()?
-                .trim()
-                .parse::<f64>()
-                .ok()
-        })
-        .map(|kb| kb / 1024.0) // Convert KB to MB
-        .unwrap_or(0.0)
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliOptions, String> {
+    let mut options = CliOptions::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pin-cores" => {
+                let value = args.next().ok_or("--pin-cores requires a core list")?;
+                options.pin_cores = Some(parse_core_list(&value)?);
+            }
+            "--compact" => options.compact = true,
+            "--dump-state" => options.dump_state = true,
+            "--qubit-marginals" => options.qubit_marginals = true,
+            "--gate-weights" => {
+                let value = args.next().ok_or("--gate-weights requires a weight list")?;
+                options.gate_weights = Some(parse_gate_weights(&value)?);
+            }
+            "--time-budget" => {
+                let value = args.next().ok_or("--time-budget requires a number of seconds")?;
+                let seconds = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                    .ok_or_else(|| format!("invalid --time-budget '{}'", value))?;
+                options.time_budget = Some(Duration::from_secs_f64(seconds));
+            }
+            "--budget-order" => {
+                let value = args.next().ok_or("--budget-order requires smallest-first or largest-first")?;
+                options.budget_order = match value.as_str() {
+                    "smallest-first" => BudgetOrder::SmallestFirst,
+                    "largest-first" => BudgetOrder::LargestFirst,
+                    other => return Err(format!("unknown --budget-order '{}'", other)),
+                };
+            }
+            other => return Err(format!("unknown argument: {}", other)),
+        }
+    }
+
+    Ok(options)
 }
 
-fn benchmark_ghz_state(num_qubits: usize) -> BenchmarkResult {
-    let start_memory = get_memory_usage();
-    let start_time = Instant::now();
-    
-    // Create GHZ state: |000...0⟩ + |111...1⟩
-    let mut circuit = QuantumCircuit::new(num_qubits);
-    
-    // Apply Hadamard to first qubit
-    circuit.h(0);
-    
-    // Apply CNOT gates to create entanglement
-    for i in 1..num_qubits {
-        circuit.cnot(0, i);
-    }
-    
-    let _final_state = circuit.execute();
-    
-    let execution_time = start_time.elapsed();
-    let end_memory = get_memory_usage();
-    
-    BenchmarkResult {
-        name: format!("GHZ-{}", num_qubits),
-        num_qubits,
-        num_gates: num_qubits, // 1 H + (n-1) CNOT
-        execution_time_ms: execution_time.as_secs_f64() * 1000.0,
-        memory_usage_mb: end_memory - start_memory,
-        circuit_depth: 2, // H gate depth + CNOT depth
+fn parse_core_list(value: &str) -> Result<Vec<usize>, String> {
+    let cores = value
+        .split(',')
+        .map(|core| {
+            core.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid core id '{}' in --pin-cores", core))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if cores.is_empty() {
+        return Err("--pin-cores requires at least one core".to_string());
     }
+    Ok(cores)
 }
 
-fn benchmark_random_circuit(num_qubits: usize, num_gates: usize) -> BenchmarkResult {
-    let start_memory = get_memory_usage();
-    let start_time = Instant::now();
-    
-    let mut circuit = QuantumCircuit::new(num_qubits);
-    let mut rng = rand::thread_rng();
-    
-    for _ in 0..num_gates {
-        let gate_type = rng.gen_range(0..6);
-        let qubit = rng.gen_range(0..num_qubits);
-        let angle = rng.gen::<f64>() * 2.0 * PI;
-        
-        match gate_type {
-            0 => { circuit.h(qubit); }
-            1 => { circuit.x(qubit); }
-            2 => { circuit.y(qubit); }
-            3 => { circuit.z(qubit); }
-            4 => { circuit.rx(qubit, angle); }
-            5 => { circuit.ry(qubit, angle); }
-            _ => { circuit.rz(qubit, angle); }
+/// Pins the main thread to the first requested core and one rayon worker to
+/// each of the rest (a single core is shared), so timings don't wander across
+/// heterogeneous (big.LITTLE/hybrid) cores. Falls back to a warning where
+/// affinity isn't supported.
+fn pin_to_cores(cores: &[usize]) {
+    let available = match core_affinity::get_core_ids() {
+        Some(ids) if !ids.is_empty() => ids,
+        _ => {
+            eprintln!("Warning: core pinning is not supported on this platform, ignoring --pin-cores");
+            return;
         }
+    };
+
+    let selected: Vec<core_affinity::CoreId> = cores
+        .iter()
+        .filter_map(|&id| {
+            let core = available.iter().find(|core| core.id == id).copied();
+            if core.is_none() {
+                eprintln!("Warning: core {} is not available, skipping", id);
+            }
+            core
+        })
+        .collect();
+
+    let Some(&main_core) = selected.first() else {
+        eprintln!("Warning: none of the requested cores are available, running unpinned");
+        return;
+    };
+
+    if !core_affinity::set_for_current(main_core) {
+        eprintln!("Warning: failed to pin the main thread to core {}", main_core.id);
     }
-    
-    // Add some CNOT gates for entanglement
-    let num_cnots = num_gates / 4;
-    for _ in 0..num_cnots {
-        let control = rng.gen_range(0..num_qubits);
-        let mut target = rng.gen_range(0..num_qubits);
-        while target == control {
-            target = rng.gen_range(0..num_qubits);
-        }
-        circuit.cnot(control, target);
-    }
-    
-    let _final_state = circuit.execute();
-    
-    let execution_time = start_time.elapsed();
-    let end_memory = get_memory_usage();
-    
-    BenchmarkResult {
-        name: format!("Random-{}-{}", num_qubits, num_gates),
-        num_qubits,
-        num_gates: num_gates + num_cnots,
-        execution_time_ms: execution_time.as_secs_f64() * 1000.0,
-        memory_usage_mb: end_memory - start_memory,
-        circuit_depth: num_gates + num_cnots, // Simplified depth calculation
+
+    let worker_cores = if selected.len() > 1 {
+        selected[1..].to_vec()
+    } else {
+        selected.clone()
+    };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(worker_cores.len())
+        .start_handler(move |index| {
+            core_affinity::set_for_current(worker_cores[index]);
+        })
+        .build_global();
+    if let Err(err) = pool {
+        eprintln!("Warning: failed to configure pinned worker threads: {}", err);
     }
+
+    eprintln!(
+        "Pinned to cores {:?}",
+        selected.iter().map(|core| core.id).collect::<Vec<_>>()
+    );
 }
 
-fn benchmark_qft_circuit(num_qubits: usize) -> BenchmarkResult {
-    let start_memory = get_memory_usage();
-    let start_time = Instant::now();
-    
-    let mut circuit = QuantumCircuit::new(num_qubits);
-    
-    // Implement simplified QFT
-    for i in 0..num_qubits {
-        circuit.h(i);
-        for j in (i + 1)..num_qubits {
-            let angle = PI / (1 << (j - i)) as f64;
-            circuit.rz(j, angle);
-            circuit.cnot(j, i);
-            circuit.rz(j, -angle);
-            circuit.cnot(j, i);
+/// First Ctrl-C lets the running configuration finish and the partial
+/// results be written; a second one exits at once.
+fn install_interrupt_handler() -> Option<InterruptFlag> {
+    let flag = InterruptFlag::new();
+    let handler_flag = flag.clone();
+    let installed = ctrlc::set_handler(move || {
+        if handler_flag.is_interrupted() {
+            std::process::exit(130);
+        }
+        handler_flag.interrupt();
+        eprintln!("Interrupted, finishing the current benchmark (press Ctrl-C again to quit)");
+    });
+    match installed {
+        Ok(()) => Some(flag),
+        Err(err) => {
+            eprintln!("Warning: failed to install the Ctrl-C handler: {}", err);
+            None
         }
     }
-    
-    let _final_state = circuit.execute();
-    
-    let execution_time = start_time.elapsed();
-    let end_memory = get_memory_usage();
-    
-    let num_gates = num_qubits + (num_qubits * (num_qubits - 1)) * 2; // H gates + controlled rotations
-    
-    BenchmarkResult {
-        name: format!("QFT-{}", num_qubits),
-        num_qubits,
-        num_gates,
-        execution_time_ms: execution_time.as_secs_f64() * 1000.0,
-        memory_usage_mb: end_memory - start_memory,
-        circuit_depth: num_qubits * 2,
+}
+
+fn render_suite(suite: &BenchmarkSuite, compact: bool) -> String {
+    if compact {
+        serde_json::to_string(suite)
+    } else {
+        serde_json::to_string_pretty(suite)
     }
+    .expect("Failed to serialize benchmark results")
 }
 
 fn main() {
-    let suite_start = Instant::now();
-    let mut results = Vec::new();
-    
-    eprintln!("Starting LogosQ Rust benchmarks...");
-    
-    // Test different qubit sizes
-    let qubit_sizes = vec![4, 6, 8, 10, 12];
-    
-    for &num_qubits in &qubit_sizes {
-        if num_qubits <= 14 { // Limit for exponential memory growth
-            eprintln!("Benchmarking {} qubits...", num_qubits);
-            
-            // GHZ state benchmark
-            results.push(benchmark_ghz_state(num_qubits));
-            
-            // Random circuit benchmark
-            let gate_count = num_qubits * 10;
-            results.push(benchmark_random_circuit(num_qubits, gate_count));
-            
-            // QFT benchmark (only for smaller systems due to complexity)
-            if num_qubits <= 10 {
-                results.push(benchmark_qft_circuit(num_qubits));
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            eprintln!(
+                "Usage: logosq_benchmark [--pin-cores 0,1,2,3] [--compact] [--gate-weights h=1,cnot=1] [--dump-state] [--qubit-marginals] \
+                 [--time-budget SECONDS] [--budget-order smallest-first|largest-first]"
+            );
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(cores) = &options.pin_cores {
+        pin_to_cores(cores);
+    }
+
+    let benchmark_suite = run_benchmarks(BenchmarkConfig {
+        gate_weights: options.gate_weights,
+        dump_state: options.dump_state,
+        qubit_marginals: options.qubit_marginals,
+        time_budget: options.time_budget,
+        budget_order: options.budget_order,
+        interrupt: install_interrupt_handler(),
+        ..BenchmarkConfig::default()
+    });
+
+    println!("{}", render_suite(&benchmark_suite, options.compact));
+    if benchmark_suite.interrupted {
+        eprintln!("LogosQ benchmarks interrupted after {:.2}ms", benchmark_suite.total_time_ms);
+        std::process::exit(130);
+    }
+    eprintln!("LogosQ benchmarks completed in {:.2}ms", benchmark_suite.total_time_ms);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_core_lists() {
+        assert_eq!(parse_core_list("0,1,2,3"), Ok(vec![0, 1, 2, 3]));
+        assert_eq!(parse_core_list(" 4 , 7"), Ok(vec![4, 7]));
+        assert_eq!(parse_core_list("5"), Ok(vec![5]));
+    }
+
+    #[test]
+    fn rejects_empty_core_entries() {
+        assert!(parse_core_list("").is_err());
+        assert!(parse_core_list("0,,2").is_err());
+        assert!(parse_core_list("1,").is_err());
+    }
+
+    #[test]
+    fn rejects_non_numeric_core_ids() {
+        assert!(parse_core_list("0,one").is_err());
+        assert!(parse_core_list("-1").is_err());
+        assert!(parse_core_list("2.5").is_err());
+    }
+
+    #[test]
+    fn parse_args_reads_pin_cores() {
+        let options = parse_args(args(&["--pin-cores", "2,3", "--compact"])).unwrap();
+        assert_eq!(options.pin_cores, Some(vec![2, 3]));
+        assert!(options.compact);
+
+        assert_eq!(parse_args(args(&[])), Ok(CliOptions::default()));
+        assert!(parse_args(args(&["--pin-cores"])).is_err());
+        assert!(parse_args(args(&["--pin-cores", "a,b"])).is_err());
+    }
+
+    #[test]
+    fn compact_and_pretty_output_round_trip_to_the_same_suite() {
+        let suite: BenchmarkSuite = serde_json::from_str(
+            r#"{
+                "library": "LogosQ",
+                "version": "0.1.0",
+                "results": [{
+                    "name": "GHZ-3",
+                    "num_qubits": 3,
+                    "num_gates": 3,
+                    "num_two_qubit_gates": 2,
+                    "execution_time_ms": 0.123456789,
+                    "memory_usage_mb": 0.0,
+                    "circuit_depth": 3,
+                    "fidelity": 0.9999999999999998
+                }],
+                "total_time_ms": 1.5
+            }"#,
+        )
+        .unwrap();
+
+        let compact = serde_json::to_string(&suite).unwrap();
+        let pretty = serde_json::to_string_pretty(&suite).unwrap();
+        assert!(compact.len() < pretty.len());
+
+        let from_compact: BenchmarkSuite = serde_json::from_str(&compact).unwrap();
+        let from_pretty: BenchmarkSuite = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(from_compact, suite);
+        assert_eq!(from_pretty, suite);
+    }
+
+    #[test]
+    fn interrupted_suite_is_flushed_with_its_partial_results() {
+        let mut suite = run_benchmarks(BenchmarkConfig {
+            qubit_sizes: vec![2],
+            serialization_repeats: 1,
+            vqe: Vec::new(),
+            ..BenchmarkConfig::default()
+        });
+        assert!(!suite.results.is_empty());
+        // As if the flag were raised once the first register size had finished.
+        suite.interrupted = true;
+        suite.skipped.insert(0, "3 qubits".to_string());
+
+        for compact in [true, false] {
+            let flushed: BenchmarkSuite = serde_json::from_str(&render_suite(&suite, compact)).unwrap();
+            assert!(flushed.interrupted);
+            assert_eq!(flushed.skipped, suite.skipped);
+            assert_eq!(flushed.results.len(), suite.results.len());
+            for (flushed, result) in flushed.results.iter().zip(&suite.results) {
+                assert_eq!((&flushed.name, flushed.num_gates, flushed.circuit_depth), (&result.name, result.num_gates, result.circuit_depth));
+                assert!((flushed.execution_time_ms - result.execution_time_ms).abs() <= 1e-9 * result.execution_time_ms);
             }
+            assert!((flushed.total_time_ms - suite.total_time_ms).abs() <= 1e-9 * suite.total_time_ms);
         }
     }
-    
-    let total_time = suite_start.elapsed();
-    
-    let benchmark_suite = BenchmarkSuite {
-        library: "LogosQ".to_string(),
-        version: "0.1.0".to_string(),
-        results,
-        total_time_ms: total_time.as_secs_f64() * 1000.0,
-    };
-    
-    let json_output = serde_json::to_string_pretty(&benchmark_suite)
-        .expect("Failed to serialize benchmark results");
-    
-    println!("{}", json_output);
-    eprintln!("LogosQ benchmarks completed in {:.2}ms", total_time.as_secs_f64() * 1000.0);
-}
\ No newline at end of file
+}
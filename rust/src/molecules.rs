@@ -0,0 +1,648 @@
+use crate::{Hamiltonian, Pauli, QuantumError, DEFAULT_TOL};
+use nalgebra::DMatrix;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+/// Experimental H2 bond length in ångström.
+pub const H2_EQUILIBRIUM_BOND_LENGTH: f64 = 0.7414;
+
+/// Experimental LiH bond length in ångström.
+pub const LIH_EQUILIBRIUM_BOND_LENGTH: f64 = 1.5949;
+
+const BOHR_PER_ANGSTROM: f64 = 1.0 / 0.529_177_210_903;
+
+/// Self-consistent field iterations allowed before `restricted_hartree_fock`
+/// gives up.
+const MAX_SCF_ITERATIONS: usize = 500;
+
+/// STO-3G contractions, `(exponents, coefficients)` over normalised
+/// primitives. Lithium's 2s and 2p shells share exponents.
+const H_1S: ([f64; 3], [f64; 3]) = ([3.42525091, 0.62391373, 0.16885540], [0.15432897, 0.53532814, 0.44463454]);
+const LI_1S: ([f64; 3], [f64; 3]) = ([16.1195750, 2.9362007, 0.7946505], [0.15432897, 0.53532814, 0.44463454]);
+const LI_2S: ([f64; 3], [f64; 3]) = ([0.6362897, 0.1478601, 0.0480887], [-0.09996723, 0.39951283, 0.70011547]);
+const LI_2P: ([f64; 3], [f64; 3]) = ([0.6362897, 0.1478601, 0.0480887], [0.15591627, 0.60768372, 0.39195739]);
+
+/// H2 in STO-3G at `bond_length` ångström, Jordan-Wigner encoded on 4
+/// qubits. Qubits `2k` and `2k + 1` are the spin-up and spin-down halves of
+/// the `k`-th Hartree-Fock orbital in energy order, as in `Molecule::H2`,
+/// which tabulates this at 0.74 Å. Computed from the integrals on each call,
+/// so the bond length can be swept for dissociation curves.
+///
+/// Fails if `bond_length` is not a positive finite number.
+pub fn h2_hamiltonian(bond_length: f64) -> Result<Hamiltonian, QuantumError> {
+    if !bond_length.is_finite() || bond_length <= 0.0 {
+        return Err(QuantumError::InvalidParameter(format!(
+            "bond length must be positive and finite, got {}",
+            bond_length
+        )));
+    }
+    let z = bond_length * BOHR_PER_ANGSTROM;
+    Ok(molecular_hamiltonian(&[(1.0, [0.0, 0.0, 0.0]), (1.0, [0.0, 0.0, z])], 2))
+}
+
+/// LiH in STO-3G at `LIH_EQUILIBRIUM_BOND_LENGTH`, Jordan-Wigner encoded on
+/// 12 qubits with the same orbital ordering as `h2_hamiltonian` and no
+/// frozen core.
+pub fn lih_hamiltonian() -> Hamiltonian {
+    let z = LIH_EQUILIBRIUM_BOND_LENGTH * BOHR_PER_ANGSTROM;
+    molecular_hamiltonian(&[(3.0, [0.0, 0.0, 0.0]), (1.0, [0.0, 0.0, z])], 4)
+}
+
+/// A contracted Cartesian Gaussian `x^l y^m z^n Σ c_k exp(-α_k r²)` around
+/// `center`, with the primitive and contraction normalisation folded into
+/// the coefficients.
+struct BasisFunction {
+    center: [f64; 3],
+    powers: [usize; 3],
+    exponents: [f64; 3],
+    coefficients: [f64; 3],
+}
+
+impl BasisFunction {
+    fn new(center: [f64; 3], powers: [usize; 3], (exponents, coefficients): ([f64; 3], [f64; 3])) -> Self {
+        let angular = powers.iter().sum::<usize>() as f64;
+        let double_factorials: f64 = powers.iter().map(|&l| odd_double_factorial(l)).product();
+        let mut function = BasisFunction {
+            center,
+            powers,
+            exponents,
+            coefficients,
+        };
+        for (coefficient, &alpha) in function.coefficients.iter_mut().zip(&exponents) {
+            *coefficient *= (2.0 * alpha / PI).powf(0.75) * (4.0 * alpha).powf(angular / 2.0)
+                / double_factorials.sqrt();
+        }
+
+        let norm = overlap(&function, &function).sqrt();
+        for coefficient in &mut function.coefficients {
+            *coefficient /= norm;
+        }
+        function
+    }
+}
+
+/// Second-quantised electronic Hamiltonian of the nuclei `(charge, position
+/// in bohr)` in the STO-3G basis over restricted Hartree-Fock orbitals, plus
+/// the nuclear repulsion, Jordan-Wigner encoded with spin orbitals
+/// interleaved. Only hydrogen and lithium are supported.
+fn molecular_hamiltonian(nuclei: &[(f64, [f64; 3])], num_electrons: usize) -> Hamiltonian {
+    let mut basis = Vec::new();
+    for &(charge, center) in nuclei {
+        match charge as usize {
+            1 => basis.push(BasisFunction::new(center, [0, 0, 0], H_1S)),
+            3 => {
+                basis.push(BasisFunction::new(center, [0, 0, 0], LI_1S));
+                basis.push(BasisFunction::new(center, [0, 0, 0], LI_2S));
+                for axis in 0..3 {
+                    let mut powers = [0; 3];
+                    powers[axis] = 1;
+                    basis.push(BasisFunction::new(center, powers, LI_2P));
+                }
+            }
+            other => panic!("no STO-3G basis for nuclear charge {}", other),
+        }
+    }
+
+    let size = basis.len();
+    let overlaps = DMatrix::from_fn(size, size, |i, j| overlap(&basis[i], &basis[j]));
+    let core = DMatrix::from_fn(size, size, |i, j| {
+        kinetic(&basis[i], &basis[j])
+            + nuclei
+                .iter()
+                .map(|&(charge, center)| -charge * nuclear_attraction(&basis[i], &basis[j], center))
+                .sum::<f64>()
+    });
+    let repulsion = ElectronRepulsion::new(&basis);
+
+    let orbitals = restricted_hartree_fock(&overlaps, &core, &repulsion, num_electrons / 2);
+    let one_body = orbitals.transpose() * &core * &orbitals;
+    let two_body = repulsion.transform(&orbitals);
+
+    let mut nuclear_repulsion = 0.0;
+    for (a, &(charge_a, center_a)) in nuclei.iter().enumerate() {
+        for &(charge_b, center_b) in &nuclei[a + 1..] {
+            nuclear_repulsion += charge_a * charge_b / distance_squared(center_a, center_b).sqrt();
+        }
+    }
+
+    jordan_wigner(nuclear_repulsion, &one_body, &two_body)
+}
+
+/// Closed-shell SCF from the core-Hamiltonian guess. Returns the molecular
+/// orbital coefficients, one column per orbital in ascending energy.
+///
+/// Panics if the density has not settled after `MAX_SCF_ITERATIONS`.
+fn restricted_hartree_fock(
+    overlaps: &DMatrix<f64>,
+    core: &DMatrix<f64>,
+    repulsion: &ElectronRepulsion,
+    occupied: usize,
+) -> DMatrix<f64> {
+    let size = overlaps.nrows();
+    let eigen = overlaps.clone().symmetric_eigen();
+    let inverse_sqrt = &eigen.eigenvectors
+        * DMatrix::from_diagonal(&eigen.eigenvalues.map(|value| 1.0 / value.sqrt()))
+        * eigen.eigenvectors.transpose();
+
+    let solve = |fock: &DMatrix<f64>| -> DMatrix<f64> {
+        let eigen = (inverse_sqrt.transpose() * fock * &inverse_sqrt).symmetric_eigen();
+        let mut order: Vec<usize> = (0..size).collect();
+        order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+        let sorted = DMatrix::from_fn(size, size, |i, k| eigen.eigenvectors[(i, order[k])]);
+        &inverse_sqrt * sorted
+    };
+    let density_of = |orbitals: &DMatrix<f64>| -> DMatrix<f64> {
+        let occupied_orbitals = orbitals.columns(0, occupied);
+        2.0 * &occupied_orbitals * occupied_orbitals.transpose()
+    };
+
+    let mut orbitals = solve(core);
+    let mut density = density_of(&orbitals);
+    for _ in 0..MAX_SCF_ITERATIONS {
+        let fock = DMatrix::from_fn(size, size, |m, n| {
+            let mut value = core[(m, n)];
+            for l in 0..size {
+                for s in 0..size {
+                    value += density[(l, s)] * (repulsion.get(m, n, l, s) - 0.5 * repulsion.get(m, l, n, s));
+                }
+            }
+            value
+        });
+        orbitals = solve(&fock);
+        let next = density_of(&orbitals);
+        let change = (&next - &density).abs().max();
+        // Damped update: plain iteration can oscillate between two densities
+        density = 0.5 * (&next + &density);
+        if change < DEFAULT_TOL {
+            return orbitals;
+        }
+    }
+    panic!("Hartree-Fock did not converge in {} iterations", MAX_SCF_ITERATIONS);
+}
+
+/// Two-electron integrals `(ij|kl)` in chemists' notation, stored densely.
+struct ElectronRepulsion {
+    size: usize,
+    values: Vec<f64>,
+}
+
+impl ElectronRepulsion {
+    /// Computes each of the 8-fold symmetric integrals once.
+    fn new(basis: &[BasisFunction]) -> Self {
+        let size = basis.len();
+        let mut integrals = ElectronRepulsion {
+            size,
+            values: vec![0.0; size.pow(4)],
+        };
+        for i in 0..size {
+            for j in 0..=i {
+                for k in 0..size {
+                    for l in 0..=k {
+                        if i * (i + 1) / 2 + j < k * (k + 1) / 2 + l {
+                            continue;
+                        }
+                        let value = electron_repulsion(&basis[i], &basis[j], &basis[k], &basis[l]);
+                        for (a, b, c, d) in [
+                            (i, j, k, l),
+                            (j, i, k, l),
+                            (i, j, l, k),
+                            (j, i, l, k),
+                            (k, l, i, j),
+                            (l, k, i, j),
+                            (k, l, j, i),
+                            (l, k, j, i),
+                        ] {
+                            let index = integrals.index(a, b, c, d);
+                            integrals.values[index] = value;
+                        }
+                    }
+                }
+            }
+        }
+        integrals
+    }
+
+    fn index(&self, i: usize, j: usize, k: usize, l: usize) -> usize {
+        ((i * self.size + j) * self.size + k) * self.size + l
+    }
+
+    fn get(&self, i: usize, j: usize, k: usize, l: usize) -> f64 {
+        self.values[self.index(i, j, k, l)]
+    }
+
+    /// Re-expresses the integrals over the columns of `orbitals`, one index
+    /// at a time.
+    fn transform(&self, orbitals: &DMatrix<f64>) -> ElectronRepulsion {
+        let mut current = self.values.clone();
+        for position in 0..4 {
+            let stride = self.size.pow(3 - position);
+            let mut next = vec![0.0; current.len()];
+            for (index, value) in next.iter_mut().enumerate() {
+                let target = (index / stride) % self.size;
+                let base = index - target * stride;
+                *value = (0..self.size)
+                    .map(|mu| orbitals[(mu, target)] * current[base + mu * stride])
+                    .sum();
+            }
+            current = next;
+        }
+        ElectronRepulsion {
+            size: self.size,
+            values: current,
+        }
+    }
+}
+
+/// `Σ h_pq a†_p a_q + ½ Σ (pq|rs) a†_p a†_r a_s a_q + constant` over spin
+/// orbitals `2k + σ`, mapped to Pauli strings. Operators are multiplied in
+/// the `X^x Z^z` form, where the ladder operators have real coefficients,
+/// and converted to X/Y/Z labels at the end.
+fn jordan_wigner(constant: f64, one_body: &DMatrix<f64>, two_body: &ElectronRepulsion) -> Hamiltonian {
+    let num_orbitals = one_body.nrows();
+    let num_qubits = 2 * num_orbitals;
+    let mut strings: BTreeMap<(u64, u64), f64> = BTreeMap::new();
+    strings.insert((0, 0), constant);
+
+    let mut add_product = |coefficient: f64, operators: &[(usize, bool)]| {
+        let mut product = vec![((0u64, 0u64), coefficient)];
+        for &(mode, creation) in operators {
+            let parity = (1u64 << mode) - 1;
+            let flip = 1u64 << mode;
+            let factors = [(0, 0.5), (flip, if creation { 0.5 } else { -0.5 })];
+            product = product
+                .iter()
+                .flat_map(|&((x, z), value)| {
+                    factors.iter().map(move |&(extra_z, factor)| {
+                        let (x2, z2) = (flip, parity | extra_z);
+                        let sign = if (z & x2).count_ones() % 2 == 0 { 1.0 } else { -1.0 };
+                        ((x ^ x2, z ^ z2), value * factor * sign)
+                    })
+                })
+                .collect();
+        }
+        for (key, value) in product {
+            *strings.entry(key).or_insert(0.0) += value;
+        }
+    };
+
+    for p in 0..num_orbitals {
+        for q in 0..num_orbitals {
+            let h = one_body[(p, q)];
+            if h.abs() < DEFAULT_TOL {
+                continue;
+            }
+            for spin in 0..2 {
+                add_product(h, &[(2 * p + spin, true), (2 * q + spin, false)]);
+            }
+        }
+    }
+    for p in 0..num_orbitals {
+        for q in 0..num_orbitals {
+            for r in 0..num_orbitals {
+                for s in 0..num_orbitals {
+                    let v = two_body.get(p, q, r, s);
+                    if v.abs() < DEFAULT_TOL {
+                        continue;
+                    }
+                    for sigma in 0..2 {
+                        for tau in 0..2 {
+                            let (a, b, c, d) = (2 * p + sigma, 2 * r + tau, 2 * s + tau, 2 * q + sigma);
+                            if a == b || c == d {
+                                continue;
+                            }
+                            add_product(0.5 * v, &[(a, true), (b, true), (c, false), (d, false)]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut terms = Vec::new();
+    for ((x, z), value) in strings {
+        let num_y = (x & z).count_ones();
+        // Y = i X Z, so X Z = -i Y; Hermiticity leaves an even number of Ys
+        if value.abs() < DEFAULT_TOL || num_y % 2 == 1 {
+            continue;
+        }
+        let coefficient = if num_y % 4 == 0 { value } else { -value };
+        let operators: Vec<(usize, Pauli)> = (0..num_qubits)
+            .filter_map(|qubit| match ((x >> qubit) & 1, (z >> qubit) & 1) {
+                (1, 1) => Some((qubit, Pauli::Y)),
+                (1, 0) => Some((qubit, Pauli::X)),
+                (0, 1) => Some((qubit, Pauli::Z)),
+                _ => None,
+            })
+            .collect();
+        terms.push((coefficient, operators));
+    }
+    terms.sort_by_key(|(_, operators)| operators.len());
+
+    let mut hamiltonian = Hamiltonian::new();
+    for (coefficient, operators) in terms {
+        hamiltonian.add_term(coefficient, &operators);
+    }
+    hamiltonian
+}
+
+fn overlap(a: &BasisFunction, b: &BasisFunction) -> f64 {
+    sum_over_primitives(a, b, |alpha, beta| {
+        let p = alpha + beta;
+        (0..3)
+            .map(|axis| hermite(a.powers[axis], b.powers[axis], 0, a.center[axis] - b.center[axis], alpha, beta))
+            .product::<f64>()
+            * (PI / p).powf(1.5)
+    })
+}
+
+fn kinetic(a: &BasisFunction, b: &BasisFunction) -> f64 {
+    sum_over_primitives(a, b, |alpha, beta| {
+        let overlap_1d = |axis: usize, shift: i32| -> f64 {
+            let power = b.powers[axis] as i32 + shift;
+            if power < 0 {
+                return 0.0;
+            }
+            hermite(a.powers[axis], power as usize, 0, a.center[axis] - b.center[axis], alpha, beta)
+                * (PI / (alpha + beta)).sqrt()
+        };
+        let plain: [f64; 3] = [overlap_1d(0, 0), overlap_1d(1, 0), overlap_1d(2, 0)];
+
+        // -½ ∇² on x^l e^(-β x²) gives β(2l+1) x^l - 2β² x^(l+2) - ½ l(l-1) x^(l-2)
+        (0..3)
+            .map(|axis| {
+                let l = b.powers[axis] as f64;
+                let second = beta * (2.0 * l + 1.0) * overlap_1d(axis, 0)
+                    - 2.0 * beta * beta * overlap_1d(axis, 2)
+                    - 0.5 * l * (l - 1.0) * overlap_1d(axis, -2);
+                (0..3)
+                    .map(|other| if other == axis { second } else { plain[other] })
+                    .product::<f64>()
+            })
+            .sum()
+    })
+}
+
+/// `⟨a| 1/|r - nucleus| |b⟩`; the caller supplies the charge and sign.
+fn nuclear_attraction(a: &BasisFunction, b: &BasisFunction, nucleus: [f64; 3]) -> f64 {
+    sum_over_primitives(a, b, |alpha, beta| {
+        let p = alpha + beta;
+        let center = gaussian_product_center(a.center, alpha, b.center, beta);
+        let offset = [center[0] - nucleus[0], center[1] - nucleus[1], center[2] - nucleus[2]];
+        let expansion = HermiteExpansion::new(a, alpha, b, beta);
+        let table = CoulombTable::new(p, offset, expansion.max_order());
+
+        let mut value = 0.0;
+        for (t, u, v, coefficient) in expansion.terms() {
+            value += coefficient * table.get(t, u, v);
+        }
+        2.0 * PI / p * value
+    })
+}
+
+fn electron_repulsion(a: &BasisFunction, b: &BasisFunction, c: &BasisFunction, d: &BasisFunction) -> f64 {
+    let mut value = 0.0;
+    for (&alpha, &ca) in a.exponents.iter().zip(&a.coefficients) {
+        for (&beta, &cb) in b.exponents.iter().zip(&b.coefficients) {
+            let bra = HermiteExpansion::new(a, alpha, b, beta);
+            let p = alpha + beta;
+            let bra_center = gaussian_product_center(a.center, alpha, b.center, beta);
+            for (&gamma, &cc) in c.exponents.iter().zip(&c.coefficients) {
+                for (&delta, &cd) in d.exponents.iter().zip(&d.coefficients) {
+                    let ket = HermiteExpansion::new(c, gamma, d, delta);
+                    let q = gamma + delta;
+                    let ket_center = gaussian_product_center(c.center, gamma, d.center, delta);
+                    let reduced = p * q / (p + q);
+                    let offset = [
+                        bra_center[0] - ket_center[0],
+                        bra_center[1] - ket_center[1],
+                        bra_center[2] - ket_center[2],
+                    ];
+                    let table = CoulombTable::new(reduced, offset, bra.max_order() + ket.max_order());
+
+                    let mut primitive = 0.0;
+                    for (t, u, v, bra_coefficient) in bra.terms() {
+                        for (tau, nu, phi, ket_coefficient) in ket.terms() {
+                            let sign = if (tau + nu + phi) % 2 == 0 { 1.0 } else { -1.0 };
+                            primitive += bra_coefficient * sign * ket_coefficient * table.get(t + tau, u + nu, v + phi);
+                        }
+                    }
+                    value += ca * cb * cc * cd * 2.0 * PI.powf(2.5) / (p * q * (p + q).sqrt()) * primitive;
+                }
+            }
+        }
+    }
+    value
+}
+
+fn sum_over_primitives(a: &BasisFunction, b: &BasisFunction, integral: impl Fn(f64, f64) -> f64) -> f64 {
+    let mut value = 0.0;
+    for (&alpha, &ca) in a.exponents.iter().zip(&a.coefficients) {
+        for (&beta, &cb) in b.exponents.iter().zip(&b.coefficients) {
+            value += ca * cb * integral(alpha, beta);
+        }
+    }
+    value
+}
+
+/// McMurchie-Davidson coefficient `E_t^(i,j)` expanding the 1D product
+/// `x_A^i x_B^j e^(-α x_A²) e^(-β x_B²)` in Hermite Gaussians of order `t`,
+/// with `separation = A - B`.
+fn hermite(i: usize, j: usize, t: i32, separation: f64, alpha: f64, beta: f64) -> f64 {
+    let p = alpha + beta;
+    let reduced = alpha * beta / p;
+    if t < 0 || t > (i + j) as i32 {
+        0.0
+    } else if i == 0 && j == 0 {
+        (-reduced * separation * separation).exp()
+    } else if j == 0 {
+        hermite(i - 1, j, t - 1, separation, alpha, beta) / (2.0 * p)
+            - reduced * separation / alpha * hermite(i - 1, j, t, separation, alpha, beta)
+            + (t + 1) as f64 * hermite(i - 1, j, t + 1, separation, alpha, beta)
+    } else {
+        hermite(i, j - 1, t - 1, separation, alpha, beta) / (2.0 * p)
+            + reduced * separation / beta * hermite(i, j - 1, t, separation, alpha, beta)
+            + (t + 1) as f64 * hermite(i, j - 1, t + 1, separation, alpha, beta)
+    }
+}
+
+/// Hermite expansion of a primitive product in all three dimensions.
+struct HermiteExpansion {
+    coefficients: [Vec<f64>; 3],
+}
+
+impl HermiteExpansion {
+    fn new(a: &BasisFunction, alpha: f64, b: &BasisFunction, beta: f64) -> Self {
+        let coefficients = [0, 1, 2].map(|axis| {
+            let (i, j) = (a.powers[axis], b.powers[axis]);
+            (0..=(i + j) as i32)
+                .map(|t| hermite(i, j, t, a.center[axis] - b.center[axis], alpha, beta))
+                .collect()
+        });
+        HermiteExpansion { coefficients }
+    }
+
+    fn max_order(&self) -> usize {
+        self.coefficients.iter().map(|axis| axis.len() - 1).sum()
+    }
+
+    fn terms(&self) -> impl Iterator<Item = (usize, usize, usize, f64)> + '_ {
+        let [x, y, z] = &self.coefficients;
+        x.iter().enumerate().flat_map(move |(t, &ex)| {
+            y.iter().enumerate().flat_map(move |(u, &ey)| {
+                z.iter().enumerate().map(move |(v, &ez)| (t, u, v, ex * ey * ez))
+            })
+        })
+    }
+}
+
+/// Hermite Coulomb integrals `R_tuv` for exponent `p` and centre offset
+/// `offset`, for every `t + u + v <= max_order`.
+struct CoulombTable {
+    max_order: usize,
+    values: Vec<f64>,
+}
+
+impl CoulombTable {
+    fn new(p: f64, offset: [f64; 3], max_order: usize) -> Self {
+        let side = max_order + 1;
+        let index = |n: usize, t: usize, u: usize, v: usize| ((n * side + t) * side + u) * side + v;
+        let mut values = vec![0.0; side.pow(4)];
+
+        let argument = p * (offset[0] * offset[0] + offset[1] * offset[1] + offset[2] * offset[2]);
+        for n in 0..=max_order {
+            values[index(n, 0, 0, 0)] = (-2.0 * p).powi(n as i32) * boys(n, argument);
+        }
+        for order in 1..=max_order {
+            for n in 0..=max_order - order {
+                for t in 0..=order {
+                    for u in 0..=order - t {
+                        let v = order - t - u;
+                        // Lower the first nonzero index: R^n_{t+1} = t R^{n+1}_{t-1} + X R^{n+1}_t
+                        let (axis, k) = if t > 0 {
+                            (0, t)
+                        } else if u > 0 {
+                            (1, u)
+                        } else {
+                            (2, v)
+                        };
+                        let lowered = |by: usize| match axis {
+                            0 => index(n + 1, t - by, u, v),
+                            1 => index(n + 1, t, u - by, v),
+                            _ => index(n + 1, t, u, v - by),
+                        };
+                        let mut value = offset[axis] * values[lowered(1)];
+                        if k > 1 {
+                            value += (k - 1) as f64 * values[lowered(2)];
+                        }
+                        values[index(n, t, u, v)] = value;
+                    }
+                }
+            }
+        }
+        CoulombTable { max_order, values }
+    }
+
+    fn get(&self, t: usize, u: usize, v: usize) -> f64 {
+        let side = self.max_order + 1;
+        self.values[(t * side + u) * side + v]
+    }
+}
+
+/// Boys function `F_n(x) = ∫_0^1 s^(2n) e^(-x s²) ds`, by its series
+/// `e^(-x) Σ_k (2x)^k / ((2n+1)(2n+3)...(2n+2k+1))`, or the asymptotic form
+/// once `e^(-x)` is negligible.
+fn boys(n: usize, x: f64) -> f64 {
+    if x > 50.0 {
+        return odd_double_factorial(n) / 2f64.powi(n as i32 + 1) * (PI / x.powi(2 * n as i32 + 1)).sqrt();
+    }
+    let mut term = 1.0 / (2 * n + 1) as f64;
+    let mut sum = term;
+    let mut k = 1;
+    while term > sum * 1e-17 {
+        term *= 2.0 * x / (2 * n + 2 * k + 1) as f64;
+        sum += term;
+        k += 1;
+    }
+    (-x).exp() * sum
+}
+
+/// `(2n - 1)!!`, with `(-1)!! = 1`.
+fn odd_double_factorial(n: usize) -> f64 {
+    (1..=n).map(|k| (2 * k - 1) as f64).product()
+}
+
+fn gaussian_product_center(a: [f64; 3], alpha: f64, b: [f64; 3], beta: f64) -> [f64; 3] {
+    [0, 1, 2].map(|axis| (alpha * a[axis] + beta * b[axis]) / (alpha + beta))
+}
+
+fn distance_squared(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|axis| (a[axis] - b[axis]).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex64;
+
+    #[test]
+    fn h2_equilibrium_ground_energy() {
+        // Full CI for H2/STO-3G at 0.7414 Å, nuclear repulsion included
+        let (energy, _) = h2_hamiltonian(H2_EQUILIBRIUM_BOND_LENGTH).unwrap().ground_state(4).unwrap();
+        assert!((energy + 1.1373).abs() < 1e-3, "ground energy {}", energy);
+    }
+
+    #[test]
+    fn h2_rejects_non_positive_bond_lengths() {
+        for bond_length in [0.0, -0.74, f64::NAN, f64::INFINITY] {
+            assert!(matches!(h2_hamiltonian(bond_length), Err(QuantumError::InvalidParameter(_))));
+        }
+    }
+
+    #[test]
+    fn lih_term_count_and_ground_energy() {
+        let hamiltonian = lih_hamiltonian();
+        assert_eq!(hamiltonian.terms.len(), 631);
+        assert!(hamiltonian
+            .terms
+            .iter()
+            .all(|term| term.operators.iter().all(|&(qubit, _)| qubit < 12)));
+
+        // The full 4096-dimensional matrix is too slow to diagonalise here, so
+        // restrict to the ground state's sector: two spin-up electrons on the
+        // even qubits and two spin-down on the odd ones, 225 basis states.
+        const UP: usize = 0b0101_0101_0101;
+        let sector: Vec<usize> = (0..1usize << 12)
+            .filter(|&b| (b & UP).count_ones() == 2 && (b & !UP).count_ones() == 2)
+            .collect();
+        let position: BTreeMap<usize, usize> = sector.iter().enumerate().map(|(i, &b)| (b, i)).collect();
+        let mut block = DMatrix::<Complex64>::zeros(sector.len(), sector.len());
+        for (column, &basis) in sector.iter().enumerate() {
+            for term in &hamiltonian.terms {
+                let mut image = basis;
+                let mut amplitude = Complex64::new(term.coefficient, 0.0);
+                for &(qubit, pauli) in &term.operators {
+                    let bit = (image >> qubit) & 1;
+                    amplitude *= match (pauli, bit) {
+                        (Pauli::X, _) => Complex64::new(1.0, 0.0),
+                        (Pauli::Y, 0) => Complex64::new(0.0, 1.0),
+                        (Pauli::Y, _) => Complex64::new(0.0, -1.0),
+                        (Pauli::Z, 0) => Complex64::new(1.0, 0.0),
+                        (Pauli::Z, _) => Complex64::new(-1.0, 0.0),
+                    };
+                    if pauli != Pauli::Z {
+                        image ^= 1 << qubit;
+                    }
+                }
+                // Single Pauli strings can leave the sector, but their sum
+                // conserves both spin counts, so those images cancel.
+                if let Some(&row) = position.get(&image) {
+                    block[(row, column)] += amplitude;
+                }
+            }
+        }
+
+        // Full CI for LiH/STO-3G at 1.5949 Å, nuclear repulsion included
+        let energy = block.symmetric_eigen().eigenvalues.min();
+        assert!((energy + 7.8824).abs() < 1e-3, "ground energy {}", energy);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{mod_mul_index, Gates, Operation, QuantumCircuit, QuantumState};
+use nalgebra::DMatrix;
+use num_complex::Complex64;
+
+/// Textbook reference simulator: every operation is expanded to its full
+/// `2^n x 2^n` matrix with Kronecker products and multiplied into the state.
+/// Exponentially slower than the bit-manipulation kernels in `QuantumState`,
+/// and only meant for validating them at small sizes.
+pub struct NaiveSimulator;
+
+impl NaiveSimulator {
+    /// Full-register matrix of an operation. Qubit 0 is the least significant
+    /// bit, matching `QuantumState`'s amplitude indexing.
+    pub fn operation_matrix(operation: &Operation, num_qubits: usize) -> DMatrix<Complex64> {
+        match operation {
+            Operation::SingleGate { gate, qubit, .. } => embed(&[(*qubit, gate.clone())], num_qubits),
+            Operation::ControlledGate {
+                gate,
+                control,
+                target,
+                control_value,
+                ..
+            } => {
+                let (idle, active) = if *control_value { (0, 1) } else { (1, 0) };
+                embed(&[(*control, projector(idle))], num_qubits)
+                    + embed(&[(*control, projector(active)), (*target, gate.clone())], num_qubits)
+            }
+            Operation::MultiControlledGate {
+                gate,
+                controls,
+                target,
+                ..
+            } => {
+                let mut factors: Vec<_> = controls
+                    .iter()
+                    .map(|&(control, value)| (control, projector(value as usize)))
+                    .collect();
+                let all_set = embed(&factors, num_qubits);
+                factors.push((*target, gate.clone()));
+                DMatrix::identity(1 << num_qubits, 1 << num_qubits) - all_set + embed(&factors, num_qubits)
+            }
+            Operation::TwoQubitGate { gate, qubits: [a, b], .. } => {
+                let dim = 1usize << num_qubits;
+                let mut matrix = DMatrix::from_element(dim, dim, Complex64::new(0.0, 0.0));
+                let cleared = !((1usize << a) | (1usize << b));
+                for column in 0..dim {
+                    let local_column = (column >> a & 1) | (column >> b & 1) << 1;
+                    for local_row in 0..4 {
+                        let row = (column & cleared) | (local_row & 1) << a | (local_row >> 1) << b;
+                        matrix[(row, column)] = gate[(local_row, local_column)];
+                    }
+                }
+                matrix
+            }
+            Operation::ControlledSwap { control, first, second } => {
+                let dim = 1usize << num_qubits;
+                let mut matrix = DMatrix::from_element(dim, dim, Complex64::new(0.0, 0.0));
+                for column in 0..dim {
+                    let differ = (column >> first) & 1 != (column >> second) & 1;
+                    let row = if column >> control & 1 == 1 && differ {
+                        column ^ (1 << first) ^ (1 << second)
+                    } else {
+                        column
+                    };
+                    matrix[(row, column)] = Complex64::new(1.0, 0.0);
+                }
+                matrix
+            }
+            Operation::CustomGate { gate, qubits } => {
+                let gate = gate.matrix();
+                let dim = 1usize << num_qubits;
+                let mut matrix = DMatrix::from_element(dim, dim, Complex64::new(0.0, 0.0));
+                let scatter = |local: usize| {
+                    qubits
+                        .iter()
+                        .enumerate()
+                        .fold(0usize, |index, (bit, &qubit)| index | (local >> bit & 1) << qubit)
+                };
+                let cleared = !scatter(gate.nrows() - 1);
+                for column in 0..dim {
+                    let local_column = qubits
+                        .iter()
+                        .enumerate()
+                        .fold(0usize, |local, (bit, &qubit)| local | (column >> qubit & 1) << bit);
+                    for local_row in 0..gate.nrows() {
+                        matrix[((column & cleared) | scatter(local_row), column)] = gate[(local_row, local_column)];
+                    }
+                }
+                matrix
+            }
+            Operation::Permutation { perm } => {
+                let dim = 1usize << num_qubits;
+                let mut matrix = DMatrix::from_element(dim, dim, Complex64::new(0.0, 0.0));
+                for column in 0..dim {
+                    let row = perm
+                        .iter()
+                        .enumerate()
+                        .fold(0usize, |acc, (q, &position)| acc | (((column >> q) & 1) << position));
+                    matrix[(row, column)] = Complex64::new(1.0, 0.0);
+                }
+                matrix
+            }
+            Operation::ControlledModMul {
+                control,
+                register,
+                multiplier,
+                modulus,
+            } => {
+                let dim = 1usize << num_qubits;
+                let mut matrix = DMatrix::from_element(dim, dim, Complex64::new(0.0, 0.0));
+                for column in 0..dim {
+                    let row = mod_mul_index(column, *control, register, *multiplier, *modulus);
+                    matrix[(row, column)] = Complex64::new(1.0, 0.0);
+                }
+                matrix
+            }
+            Operation::HadamardTransform { qubits } => {
+                let factors: Vec<_> = qubits.iter().map(|&qubit| (qubit, Gates::hadamard())).collect();
+                embed(&factors, num_qubits)
+            }
+            Operation::Measure { .. } => DMatrix::identity(1 << num_qubits, 1 << num_qubits),
+        }
+    }
+
+    pub fn execute(circuit: &QuantumCircuit) -> QuantumState {
+        Self::execute_from(circuit, QuantumState::new(circuit.num_qubits()))
+    }
+
+    pub fn execute_from(circuit: &QuantumCircuit, state: QuantumState) -> QuantumState {
+        let mut amplitudes = state.amplitudes().clone();
+        for operation in circuit.operations() {
+            amplitudes = Self::operation_matrix(operation, circuit.num_qubits()) * amplitudes;
+        }
+        amplitudes *= Complex64::from_polar(1.0, circuit.global_phase());
+
+        QuantumState::from_amplitudes(amplitudes).expect("unitary evolution preserves the norm")
+    }
+}
+
+/// Kronecker product over the whole register with the given single-qubit
+/// factors and identities everywhere else.
+fn embed(factors: &[(usize, DMatrix<Complex64>)], num_qubits: usize) -> DMatrix<Complex64> {
+    let identity = DMatrix::<Complex64>::identity(2, 2);
+    let mut result = DMatrix::from_element(1, 1, Complex64::new(1.0, 0.0));
+
+    for qubit in (0..num_qubits).rev() {
+        let factor = factors
+            .iter()
+            .find(|(q, _)| *q == qubit)
+            .map(|(_, matrix)| matrix)
+            .unwrap_or(&identity);
+        result = result.kronecker(factor);
+    }
+
+    result
+}
+
+fn projector(bit: usize) -> DMatrix<Complex64> {
+    let mut matrix = DMatrix::from_element(2, 2, Complex64::new(0.0, 0.0));
+    matrix[(bit, bit)] = Complex64::new(1.0, 0.0);
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_optimized_simulator_at_small_sizes() {
+        for n in 1..=4 {
+            let mut circuit = QuantumCircuit::new(n);
+            for qubit in 0..n {
+                circuit.h(qubit).rx(qubit, 0.3 + qubit as f64).rz(qubit, 0.7);
+            }
+            for qubit in 0..n.saturating_sub(1) {
+                circuit.cnot(qubit + 1, qubit).unwrap();
+                circuit.ry(qubit, 0.4).cnot(qubit, qubit + 1).unwrap();
+            }
+            circuit.ry(n - 1, 1.2).y(0);
+
+            let naive = NaiveSimulator::execute(&circuit);
+            let difference = (naive.amplitudes() - circuit.execute().amplitudes()).norm();
+            assert!(difference < 1e-10, "mismatch at n = {}", n);
+        }
+    }
+
+    #[test]
+    fn mixed_control_values_match_for_every_qubit_order() {
+        let gate = Gates::u3(1.1, 0.4, -0.9);
+        let mut prepare = QuantumCircuit::new(4);
+        for qubit in 0..4 {
+            prepare.ry(qubit, 0.5 + 0.4 * qubit as f64).rz(qubit, 0.3 * qubit as f64);
+        }
+        let initial = prepare.execute();
+
+        for a in 0..4 {
+            for b in (0..4).filter(|&b| b != a) {
+                for c in (0..4).filter(|&c| c != a && c != b) {
+                    let t = 6 - a - b - c;
+                    let mut circuit = QuantumCircuit::new(4);
+                    circuit
+                        .controlled_gate_with_value(gate.clone(), a, t, false)
+                        .unwrap()
+                        .multi_controlled_gate(gate.clone(), &[(a, false), (b, true)], t)
+                        .unwrap()
+                        .multi_controlled_gate(gate.clone(), &[(b, true), (c, false), (a, false)], t)
+                        .unwrap()
+                        .mcx(&[c, a], t)
+                        .unwrap()
+                        .controlled_gate_with_value(gate.clone(), t, b, true)
+                        .unwrap();
+
+                    let naive = NaiveSimulator::execute_from(&circuit, initial.clone());
+                    let difference = (naive.amplitudes() - circuit.execute_from(initial.clone()).amplitudes()).norm();
+                    assert!(difference < 1e-10, "circuit mismatch for order ({}, {}, {}) -> {}", a, b, c, t);
+
+                    let mut kernel = initial.clone();
+                    kernel.apply_controlled_gate_with_value(&gate, a, t, false);
+                    let mut reference = QuantumCircuit::new(4);
+                    reference.controlled_gate_with_value(gate.clone(), a, t, false).unwrap();
+                    let naive = NaiveSimulator::execute_from(&reference, initial.clone());
+                    assert!(
+                        (naive.amplitudes() - kernel.amplitudes()).norm() < 1e-10,
+                        "kernel mismatch for control {} on |0⟩ -> {}",
+                        a,
+                        t
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{insert_zero_bit, mod_mul_index, Operation, QuantumState};
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex64;
+
+/// Statevector with real amplitudes, for circuits built only from real gates
+/// (H, X, Z, RY, CNOT, permutations, ...). Half the memory of a
+/// `QuantumState` and no complex multiplies in the kernels; produced by
+/// `QuantumCircuit::execute_real`.
+#[derive(Debug, Clone)]
+pub struct RealStateVector {
+    amplitudes: DVector<f64>,
+    num_qubits: usize,
+}
+
+impl RealStateVector {
+    pub fn new(num_qubits: usize) -> Self {
+        let mut amplitudes = DVector::zeros(1 << num_qubits);
+        amplitudes[0] = 1.0;
+        RealStateVector { amplitudes, num_qubits }
+    }
+
+    /// Applies `operation`, which must have real matrix entries (see
+    /// `Operation::is_real`). Panics otherwise.
+    pub fn apply_operation(&mut self, operation: &Operation) {
+        match operation {
+            Operation::SingleGate { gate, qubit, .. } => {
+                let gate = real_part(gate);
+                let mask = 1usize << qubit;
+                for i in (0..self.amplitudes.len()).filter(|i| i & mask == 0) {
+                    let amp0 = self.amplitudes[i];
+                    let amp1 = self.amplitudes[i | mask];
+                    self.amplitudes[i] = gate[(0, 0)] * amp0 + gate[(0, 1)] * amp1;
+                    self.amplitudes[i | mask] = gate[(1, 0)] * amp0 + gate[(1, 1)] * amp1;
+                }
+            }
+            Operation::ControlledGate {
+                gate,
+                control,
+                target,
+                control_value,
+                ..
+            } => {
+                assert_ne!(control, target, "control and target must be different qubits");
+                let gate = real_part(gate);
+                let (low, high) = (*control.min(target), *control.max(target));
+                let control_mask = if *control_value { 1usize << control } else { 0 };
+                let target_mask = 1usize << target;
+                for k in 0..(self.amplitudes.len() >> 2) {
+                    let i = insert_zero_bit(insert_zero_bit(k, low), high) | control_mask;
+                    let j = i | target_mask;
+                    let amp0 = self.amplitudes[i];
+                    let amp1 = self.amplitudes[j];
+                    self.amplitudes[i] = gate[(0, 0)] * amp0 + gate[(0, 1)] * amp1;
+                    self.amplitudes[j] = gate[(1, 0)] * amp0 + gate[(1, 1)] * amp1;
+                }
+            }
+            Operation::MultiControlledGate {
+                gate,
+                controls,
+                target,
+                ..
+            } => {
+                let gate = real_part(gate);
+                let mut positions: Vec<usize> = controls
+                    .iter()
+                    .map(|&(control, _)| control)
+                    .chain(std::iter::once(*target))
+                    .collect();
+                positions.sort_unstable();
+                let control_mask = controls
+                    .iter()
+                    .filter(|&&(_, value)| value)
+                    .fold(0usize, |mask, &(q, _)| mask | (1 << q));
+                let target_mask = 1usize << target;
+                for k in 0..(self.amplitudes.len() >> positions.len()) {
+                    let i = positions.iter().fold(k, |index, &q| insert_zero_bit(index, q)) | control_mask;
+                    let j = i | target_mask;
+                    let amp0 = self.amplitudes[i];
+                    let amp1 = self.amplitudes[j];
+                    self.amplitudes[i] = gate[(0, 0)] * amp0 + gate[(0, 1)] * amp1;
+                    self.amplitudes[j] = gate[(1, 0)] * amp0 + gate[(1, 1)] * amp1;
+                }
+            }
+            Operation::TwoQubitGate { gate, qubits: [a, b], .. } => {
+                assert_ne!(a, b, "two-qubit gate needs two different qubits");
+                let gate = real_part(gate);
+                let (low, high) = (*a.min(b), *a.max(b));
+                let (mask_a, mask_b) = (1usize << a, 1usize << b);
+                for k in 0..(self.amplitudes.len() >> 2) {
+                    let base = insert_zero_bit(insert_zero_bit(k, low), high);
+                    let indices = [base, base | mask_a, base | mask_b, base | mask_a | mask_b];
+                    let amps = indices.map(|i| self.amplitudes[i]);
+                    for (row, &i) in indices.iter().enumerate() {
+                        self.amplitudes[i] = (0..4).map(|column| gate[(row, column)] * amps[column]).sum();
+                    }
+                }
+            }
+            Operation::ControlledSwap { control, first, second } => {
+                let mut positions = [*control, *first, *second];
+                positions.sort_unstable();
+                for k in 0..(self.amplitudes.len() >> 3) {
+                    let base = positions.iter().fold(k, |index, &q| insert_zero_bit(index, q)) | (1 << control);
+                    self.amplitudes.swap_rows(base | (1 << first), base | (1 << second));
+                }
+            }
+            Operation::CustomGate { gate, qubits } => {
+                let gate = real_part(&gate.matrix());
+                let offsets: Vec<usize> = (0..gate.nrows())
+                    .map(|local| {
+                        qubits
+                            .iter()
+                            .enumerate()
+                            .fold(0usize, |offset, (bit, &qubit)| offset | (local >> bit & 1) << qubit)
+                    })
+                    .collect();
+                let mut sorted = qubits.clone();
+                sorted.sort_unstable();
+                for k in 0..(self.amplitudes.len() >> qubits.len()) {
+                    let base = sorted.iter().fold(k, |index, &q| insert_zero_bit(index, q));
+                    let block =
+                        DVector::from_iterator(offsets.len(), offsets.iter().map(|&offset| self.amplitudes[base | offset]));
+                    let mixed = &gate * block;
+                    for (local, &offset) in offsets.iter().enumerate() {
+                        self.amplitudes[base | offset] = mixed[local];
+                    }
+                }
+            }
+            Operation::Permutation { perm } => {
+                let mut amplitudes = DVector::zeros(self.amplitudes.len());
+                for (i, amp) in self.amplitudes.iter().enumerate() {
+                    let j = perm
+                        .iter()
+                        .enumerate()
+                        .fold(0usize, |acc, (q, &position)| acc | (((i >> q) & 1) << position));
+                    amplitudes[j] = *amp;
+                }
+                self.amplitudes = amplitudes;
+            }
+            Operation::ControlledModMul {
+                control,
+                register,
+                multiplier,
+                modulus,
+            } => {
+                let mut amplitudes = DVector::zeros(self.amplitudes.len());
+                for (i, amp) in self.amplitudes.iter().enumerate() {
+                    amplitudes[mod_mul_index(i, *control, register, *multiplier, *modulus)] = *amp;
+                }
+                self.amplitudes = amplitudes;
+            }
+            Operation::HadamardTransform { qubits } => {
+                for &qubit in qubits {
+                    let mask = 1usize << qubit;
+                    for i in (0..self.amplitudes.len()).filter(|i| i & mask == 0) {
+                        let amp0 = self.amplitudes[i];
+                        let amp1 = self.amplitudes[i | mask];
+                        self.amplitudes[i] = amp0 + amp1;
+                        self.amplitudes[i | mask] = amp0 - amp1;
+                    }
+                }
+                self.amplitudes.scale_mut(0.5f64.powf(qubits.len() as f64 / 2.0));
+            }
+            Operation::Measure { .. } => {}
+        }
+    }
+
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.amplitudes.iter().map(|amp| amp * amp).collect()
+    }
+
+    /// Widens to a complex `QuantumState`, e.g. to compare with `execute`.
+    pub fn to_state(&self) -> QuantumState {
+        QuantumState {
+            amplitudes: self.amplitudes.map(|amp| Complex64::new(amp, 0.0)),
+            num_qubits: self.num_qubits,
+            global_phase: 0.0,
+        }
+    }
+
+    pub fn amplitudes(&self) -> &DVector<f64> {
+        &self.amplitudes
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+}
+
+fn real_part(gate: &DMatrix<Complex64>) -> DMatrix<f64> {
+    assert!(
+        gate.iter().all(|entry| entry.im == 0.0),
+        "gate has complex entries"
+    );
+    gate.map(|entry| entry.re)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::QuantumCircuit;
+
+    #[test]
+    fn real_path_matches_complex_path() {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.h(0).x(1).ry(2, 0.8).z(3).h(3);
+        circuit.cnot(0, 1).unwrap();
+        circuit.cry(3, 2, 0.6).unwrap();
+        circuit.cnot(2, 3).unwrap();
+        circuit.permute(&[2, 1, 0, 3]).unwrap();
+        circuit.ry(1, -1.3);
+        assert!(circuit.is_real());
+
+        let real = circuit.execute_real().unwrap();
+        assert!((real.to_state().amplitudes() - circuit.execute().amplitudes()).norm() < 1e-12);
+
+        circuit.t(0);
+        assert!(!circuit.is_real());
+        assert!(circuit.execute_real().is_err());
+    }
+}
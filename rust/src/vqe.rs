@@ -0,0 +1,317 @@
+use crate::{Hamiltonian, QuantumCircuit, QuantumError};
+use std::f64::consts::FRAC_PI_2;
+
+/// Classical optimizer driving the VQE parameter updates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Optimizer {
+    /// `θ <- θ - learning_rate * ∇E` with exact parameter-shift gradients,
+    /// costing `2k` energy evaluations per step for `k` parameters.
+    GradientDescent { learning_rate: f64 },
+    /// Gradient-free downhill simplex, started from `initial` plus
+    /// `initial_step` along each parameter axis. Usually one or two energy
+    /// evaluations per step, for when gradients are expensive or noisy.
+    NelderMead { initial_step: f64 },
+}
+
+impl Optimizer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Optimizer::GradientDescent { .. } => "GradientDescent",
+            Optimizer::NelderMead { .. } => "NelderMead",
+        }
+    }
+}
+
+/// Optimizer and stopping rule for `minimize_energy`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VqeOptions {
+    pub optimizer: Optimizer,
+    /// Stop once a step changes the energy by less than this. For
+    /// Nelder-Mead the change is the spread between the best and worst
+    /// simplex vertices.
+    pub tolerance: f64,
+    /// Upper bound on optimizer steps, reached on flat or slow landscapes.
+    pub max_iterations: usize,
+}
+
+impl Default for VqeOptions {
+    fn default() -> Self {
+        VqeOptions {
+            optimizer: Optimizer::GradientDescent { learning_rate: 0.4 },
+            tolerance: 1e-8,
+            max_iterations: 500,
+        }
+    }
+}
+
+/// Outcome of a `minimize_energy` run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VqeResult {
+    pub energy: f64,
+    pub parameters: Vec<f64>,
+    /// Optimizer steps taken.
+    pub iterations: usize,
+    /// Whether the energy change fell below the tolerance before the cap.
+    pub converged: bool,
+}
+
+/// Parameters used by `ry_ansatz`: one RY per qubit in each of the
+/// `layers + 1` rotation layers.
+pub fn ansatz_parameter_count(num_qubits: usize, layers: usize) -> usize {
+    num_qubits * (layers + 1)
+}
+
+/// Hardware-efficient ansatz: a layer of RY rotations, then `layers` rounds
+/// of a CNOT ladder `0 -> 1 -> ... -> n-1` each followed by another RY
+/// layer. Real amplitudes only, which suffices for molecular ground states.
+pub fn ry_ansatz(num_qubits: usize, layers: usize, parameters: &[f64]) -> Result<QuantumCircuit, QuantumError> {
+    let expected = ansatz_parameter_count(num_qubits, layers);
+    if parameters.len() != expected {
+        return Err(QuantumError::InvalidParameter(format!(
+            "ansatz needs {} parameters, got {}",
+            expected,
+            parameters.len()
+        )));
+    }
+
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    let mut angles = parameters.iter();
+    for layer in 0..=layers {
+        if layer > 0 {
+            for qubit in 1..num_qubits {
+                circuit.cnot(qubit - 1, qubit)?;
+            }
+        }
+        for (qubit, &theta) in (0..num_qubits).zip(&mut angles) {
+            circuit.ry(qubit, theta);
+        }
+    }
+    Ok(circuit)
+}
+
+/// Minimises `⟨ψ(θ)|H|ψ(θ)⟩` over the `ry_ansatz` parameters from `initial`
+/// with `options.optimizer`. Stops when a step moves the energy by less than
+/// `options.tolerance` or after `options.max_iterations` steps, whichever
+/// comes first.
+pub fn minimize_energy(
+    hamiltonian: &Hamiltonian,
+    num_qubits: usize,
+    layers: usize,
+    initial: &[f64],
+    options: &VqeOptions,
+) -> Result<VqeResult, QuantumError> {
+    let energy_at = |parameters: &[f64]| -> Result<f64, QuantumError> {
+        hamiltonian.expectation(&ry_ansatz(num_qubits, layers, parameters)?.execute())
+    };
+
+    match options.optimizer {
+        Optimizer::GradientDescent { learning_rate } => {
+            gradient_descent(energy_at, initial, learning_rate, options)
+        }
+        Optimizer::NelderMead { initial_step } => nelder_mead(energy_at, initial, initial_step, options),
+    }
+}
+
+/// Parameter-shift gradient descent:
+/// `∂E/∂θ_k = (E(θ_k + π/2) - E(θ_k - π/2)) / 2`.
+fn gradient_descent(
+    energy_at: impl Fn(&[f64]) -> Result<f64, QuantumError>,
+    initial: &[f64],
+    learning_rate: f64,
+    options: &VqeOptions,
+) -> Result<VqeResult, QuantumError> {
+    let mut parameters = initial.to_vec();
+    let mut energy = energy_at(&parameters)?;
+    for iteration in 1..=options.max_iterations {
+        let mut gradient = Vec::with_capacity(parameters.len());
+        for k in 0..parameters.len() {
+            let mut shifted = parameters.clone();
+            shifted[k] += FRAC_PI_2;
+            let plus = energy_at(&shifted)?;
+            shifted[k] -= 2.0 * FRAC_PI_2;
+            let minus = energy_at(&shifted)?;
+            gradient.push((plus - minus) / 2.0);
+        }
+        for (theta, slope) in parameters.iter_mut().zip(&gradient) {
+            *theta -= learning_rate * slope;
+        }
+
+        let previous = std::mem::replace(&mut energy, energy_at(&parameters)?);
+        if (energy - previous).abs() < options.tolerance {
+            return Ok(VqeResult {
+                energy,
+                parameters,
+                iterations: iteration,
+                converged: true,
+            });
+        }
+    }
+
+    Ok(VqeResult {
+        energy,
+        parameters,
+        iterations: options.max_iterations,
+        converged: false,
+    })
+}
+
+/// Nelder-Mead with the dimension-adaptive coefficients of Gao and Han
+/// (reflection 1, expansion `1 + 2/n`, contraction `3/4 - 1/(2n)`, shrink
+/// `1 - 1/n`), which hold up better than the classic 1, 2, 1/2, 1/2 once
+/// there are more than a few parameters. A simplex that has collapsed below the
+/// tolerance is rebuilt around its best vertex and the search restarted, as
+/// a collapse near a saddle point (such as the Hartree-Fock state) is common;
+/// the run only counts as converged once a restart finds no improvement.
+fn nelder_mead(
+    energy_at: impl Fn(&[f64]) -> Result<f64, QuantumError>,
+    initial: &[f64],
+    initial_step: f64,
+    options: &VqeOptions,
+) -> Result<VqeResult, QuantumError> {
+    let dimension = initial.len();
+    let mut best = initial.to_vec();
+    let mut best_value = energy_at(initial)?;
+    if dimension == 0 {
+        return Ok(VqeResult {
+            energy: best_value,
+            parameters: best,
+            iterations: 0,
+            converged: true,
+        });
+    }
+
+    let along = |from: &[f64], to: &[f64], t: f64| -> Vec<f64> {
+        from.iter().zip(to).map(|(a, b)| a + t * (b - a)).collect()
+    };
+    let n = dimension as f64;
+    let (expansion, contraction, shrink) = (1.0 + 2.0 / n, 0.75 - 0.5 / n, 1.0 - 1.0 / n);
+
+    let mut iterations = 0;
+    loop {
+        let mut simplex = vec![best.clone()];
+        let mut values = vec![best_value];
+        for axis in 0..dimension {
+            let mut vertex = best.clone();
+            vertex[axis] += initial_step;
+            values.push(energy_at(&vertex)?);
+            simplex.push(vertex);
+        }
+
+        let mut collapsed = false;
+        while iterations < options.max_iterations {
+            let mut order: Vec<usize> = (0..simplex.len()).collect();
+            order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+            simplex = order.iter().map(|&i| simplex[i].clone()).collect();
+            values = order.iter().map(|&i| values[i]).collect();
+
+            if values[dimension] - values[0] < options.tolerance {
+                collapsed = true;
+                break;
+            }
+            iterations += 1;
+
+            let centroid: Vec<f64> = (0..dimension)
+                .map(|k| simplex[..dimension].iter().map(|vertex| vertex[k]).sum::<f64>() / dimension as f64)
+                .collect();
+            let worst = simplex[dimension].clone();
+
+            let reflected = along(&centroid, &worst, -1.0);
+            let reflected_value = energy_at(&reflected)?;
+            if reflected_value < values[0] {
+                let expanded = along(&centroid, &worst, -expansion);
+                let expanded_value = energy_at(&expanded)?;
+                (simplex[dimension], values[dimension]) = if expanded_value < reflected_value {
+                    (expanded, expanded_value)
+                } else {
+                    (reflected, reflected_value)
+                };
+                continue;
+            }
+            if reflected_value < values[dimension - 1] {
+                (simplex[dimension], values[dimension]) = (reflected, reflected_value);
+                continue;
+            }
+
+            let contracted = if reflected_value < values[dimension] {
+                along(&centroid, &reflected, contraction)
+            } else {
+                along(&centroid, &worst, contraction)
+            };
+            let contracted_value = energy_at(&contracted)?;
+            if contracted_value < values[dimension].min(reflected_value) {
+                (simplex[dimension], values[dimension]) = (contracted, contracted_value);
+                continue;
+            }
+
+            let anchor = simplex[0].clone();
+            for index in 1..=dimension {
+                simplex[index] = along(&anchor, &simplex[index], shrink);
+                values[index] = energy_at(&simplex[index])?;
+            }
+        }
+
+        let index = (0..simplex.len())
+            .min_by(|&a, &b| values[a].total_cmp(&values[b]))
+            .expect("simplex has at least one vertex");
+        let improvement = best_value - values[index];
+        if values[index] < best_value {
+            best_value = values[index];
+            best = simplex.swap_remove(index);
+        }
+
+        let converged = collapsed && improvement < options.tolerance;
+        if converged || iterations >= options.max_iterations {
+            return Ok(VqeResult {
+                energy: best_value,
+                parameters: best,
+                iterations,
+                converged,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pauli;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn converged_run_stops_before_cap() {
+        let mut hamiltonian = Hamiltonian::new();
+        hamiltonian.add_term(1.0, &[(0, Pauli::Z)]);
+        let options = VqeOptions {
+            optimizer: Optimizer::GradientDescent { learning_rate: 0.5 },
+            tolerance: 1e-10,
+            max_iterations: 1000,
+        };
+        let result = minimize_energy(&hamiltonian, 1, 0, &[2.5], &options).unwrap();
+        assert!(result.converged);
+        assert!(result.iterations < options.max_iterations);
+        assert!((result.energy + 1.0).abs() < 1e-6);
+
+        let capped = VqeOptions { max_iterations: 2, ..options };
+        let result = minimize_energy(&hamiltonian, 1, 0, &[2.5], &capped).unwrap();
+        assert!(!result.converged);
+        assert_eq!(result.iterations, 2);
+    }
+
+    #[test]
+    fn nelder_mead_reaches_h2_ground_state() {
+        let hamiltonian = crate::Molecule::H2.hamiltonian();
+        let (exact, _) = hamiltonian.ground_state(4).unwrap();
+        let options = VqeOptions {
+            optimizer: Optimizer::NelderMead { initial_step: 0.1 },
+            max_iterations: 5000,
+            ..VqeOptions::default()
+        };
+        // Small random start and two layers, as in the benchmark
+        let mut rng = StdRng::seed_from_u64(256);
+        let initial: Vec<f64> = (0..ansatz_parameter_count(4, 2)).map(|_| rng.gen_range(-0.1..0.1)).collect();
+        let result = minimize_energy(&hamiltonian, 4, 2, &initial, &options).unwrap();
+        assert!(result.converged);
+        assert!((result.energy - exact).abs() < 1e-4, "{} vs {}", result.energy, exact);
+    }
+}